@@ -0,0 +1,90 @@
+//! Compares drawing the OSD on every video frame with blending a layer that is drawn once per OSD frame.
+//!
+//! Run with `cargo bench -p backend --bench overlay_layer`.
+
+use std::{
+    hint::black_box,
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+
+use backend::{
+    font::FontFile,
+    osd::{Frame, Glyph, GridPosition, GridSize, OsdOptions},
+    overlay::{overlay_osd, LayerCache},
+};
+use image::{Rgba, RgbaImage};
+
+const VIDEO_FRAMES: u32 = 240;
+/// 60 fps video with a 10 Hz OSD
+const VIDEO_FRAMES_PER_OSD_FRAME: u32 = 6;
+
+fn font_file() -> FontFile {
+    // 24x36 glyphs, 256 of them in a single column. Every glyph is a white block with a black outline.
+    let font_image = RgbaImage::from_fn(24, 36 * 256, |x, y| match (x, y % 36) {
+        (6..=17, 8..=27) => Rgba([255, 255, 255, 255]),
+        (4..=19, 6..=29) => Rgba([0, 0, 0, 255]),
+        _ => Rgba([0, 0, 0, 0]),
+    });
+    let path = std::env::temp_dir().join(format!("overlay-bench-font-{}.png", std::process::id()));
+    font_image.save(&path).expect("Font image is written");
+    let font_file = FontFile::open(PathBuf::from(&path)).expect("Font image is a valid font");
+    std::fs::remove_file(path).ok();
+    font_file
+}
+
+/// A busy OSD: three rows of text and some elements in the corners.
+fn osd_frame(grid_size: &GridSize) -> Frame {
+    let glyphs = (0..grid_size.width)
+        .flat_map(|x| [(x, 1), (x, grid_size.height / 2), (x, grid_size.height - 2)])
+        .chain((0..8).flat_map(|x| [(x, 0), (grid_size.width - 1 - x, grid_size.height - 1)]))
+        .map(|(x, y)| Glyph {
+            index: (0x41 + (x + y) % 26) as u16,
+            grid_position: GridPosition { x, y },
+        })
+        .collect();
+    Frame { time_millis: 0, glyphs }
+}
+
+fn bench(name: &str, mut render_frame: impl FnMut(u32, &mut RgbaImage)) -> Duration {
+    let video_frame = RgbaImage::from_pixel(1920, 1080, Rgba([40, 80, 120, 255]));
+    // Warm up the glyph cache
+    render_frame(0, &mut video_frame.clone());
+
+    let mut elapsed = Duration::ZERO;
+    for index in 0..VIDEO_FRAMES {
+        let mut frame = video_frame.clone();
+        let start = Instant::now();
+        render_frame(index, &mut frame);
+        elapsed += start.elapsed();
+        black_box(frame);
+    }
+    let per_frame = elapsed / VIDEO_FRAMES;
+    println!("{:<32} {:>10.3?} per frame", name, per_frame);
+    per_frame
+}
+
+fn main() {
+    let font_file = font_file();
+    let grid_size = GridSize::new(53, 20);
+    let osd_frame = osd_frame(&grid_size);
+    let osd_options = OsdOptions::default();
+
+    let redraw = bench("draw OSD on every frame", |_, frame| {
+        overlay_osd(frame, &osd_frame, &grid_size, &font_file, &osd_options);
+    });
+
+    let layers = LayerCache::default();
+    let draw_layer = |layer: &mut RgbaImage| overlay_osd(layer, &osd_frame, &grid_size, &font_file, &osd_options);
+    let cached = bench("blend cached layer", |index, frame| {
+        let layer_id = (index / VIDEO_FRAMES_PER_OSD_FRAME).into();
+        let layer = layers.get_or_draw(layer_id, frame.width(), frame.height(), draw_layer);
+        layer.blend_onto(frame);
+    });
+
+    println!(
+        "speedup with {} video frames per OSD frame: {:.1}x",
+        VIDEO_FRAMES_PER_OSD_FRAME,
+        redraw.as_secs_f64() / cached.as_secs_f64()
+    );
+}
@@ -59,6 +59,25 @@ impl Encoder {
         }
     }
 
+    /// Options that make the encoder trade quality for speed, for preview clips. VAAPI, V4L2 and ProRes have no speed
+    /// presets.
+    pub fn fast_preset_args(&self) -> &'static [&'static str] {
+        match self.name.as_str() {
+            "libx264" | "libx265" => &["-preset", "ultrafast"],
+            "libvpx-vp9" => &["-deadline", "realtime", "-cpu-used", "8"],
+            name if name.ends_with("_nvenc") => &["-preset", "p1"],
+            name if name.ends_with("_qsv") => &["-preset", "veryfast"],
+            name if name.ends_with("_amf") => &["-quality", "speed"],
+            name if name.ends_with("_videotoolbox") => &["-realtime", "1"],
+            _ => &[],
+        }
+    }
+
+    /// Whether the encoder options already pick a preset, e.g. the fast preset of a preview clip.
+    pub fn has_preset(&self) -> bool {
+        self.extra_args.iter().any(|arg| arg == "-preset")
+    }
+
     #[tracing::instrument(ret)]
     pub fn get_available_encoders(ffmpeg_path: &PathBuf) -> Vec<Self> {
         // Apple QuickTime player on Mac supports hvc1. It doesn't support hev1 which is the default.
@@ -188,6 +207,34 @@ impl Encoder {
             .collect()
     }
 
+    /// Keeps only the detected encoders, ordered so that the preferred one comes first: hardware encoders before
+    /// software ones, then by codec and name.
+    pub fn sort_and_filter(encoders: &[Encoder]) -> Vec<Encoder> {
+        let mut filtered_encoders: Vec<Encoder> = encoders.iter().filter(|e| e.detected).cloned().collect();
+
+        filtered_encoders.sort_by(|a, b| {
+            const CODEC_PRIORITY: [Codec; 4] = [Codec::H265, Codec::H264, Codec::VP9, Codec::ProRes];
+
+            let a_priority = CODEC_PRIORITY
+                .iter()
+                .position(|c| *c == a.codec)
+                .unwrap_or(CODEC_PRIORITY.len());
+            let b_priority = CODEC_PRIORITY
+                .iter()
+                .position(|c| *c == b.codec)
+                .unwrap_or(CODEC_PRIORITY.len());
+            let type_cmp = a_priority.cmp(&b_priority);
+
+            let hardware_cmp = b.hardware.cmp(&a.hardware);
+
+            let name_cmp = a.name.cmp(&b.name);
+
+            hardware_cmp.then_with(|| type_cmp).then_with(|| name_cmp)
+        });
+
+        filtered_encoders
+    }
+
     fn ffmpeg_encoder_available(encoder: &Encoder, ffmpeg_path: &PathBuf) -> bool {
         let mut command = Command::new(ffmpeg_path);
 
@@ -1,5 +1,9 @@
+use std::{io, path::PathBuf, time::Duration};
+
 use thiserror::Error;
 
+use crate::{font::FontFileError, osd::OsdFileError, srt::SrtFileError};
+
 #[derive(Debug, Error)]
 pub enum VideoInfoError {
     #[error("Failed to read frame width from video")]
@@ -22,3 +26,106 @@ pub enum VideoInfoError {
         source: ffprobe::FfProbeError,
     },
 }
+
+#[derive(Debug, Error)]
+pub enum RenderJobError {
+    #[error("No input video")]
+    NoInputVideo,
+    #[error("No output video")]
+    NoOutputVideo,
+    #[error("No OSD file")]
+    NoOsdFile,
+    #[error("No font file")]
+    NoFontFile,
+    #[error("No encoder")]
+    NoEncoder,
+    #[error("Output video {} is the same file as the input video", .0.display())]
+    OutputIsInput(PathBuf),
+    #[error("Encoder {encoder} does not support constant quality mode")]
+    ConstantQualityNotSupported { encoder: String },
+    #[error("Render start {start} s must be before its end {end} s")]
+    EmptyTimeRange { start: f32, end: f32 },
+    #[error("Render start {0} s is before the start of the video")]
+    NegativeStart(f32),
+    #[error("Failed to load font file")]
+    FontFile {
+        #[from]
+        source: FontFileError,
+    },
+    #[error("Failed to load OSD file")]
+    OsdFile {
+        #[from]
+        source: OsdFileError,
+    },
+    #[error("Failed to load SRT file")]
+    SrtFile {
+        #[from]
+        source: SrtFileError,
+    },
+}
+
+#[derive(Debug, Error)]
+pub enum RenderError {
+    #[error("Output video {} is the same file as the input video", .0.display())]
+    OutputIsInput(PathBuf),
+    #[error("Path {} contains characters that can't be passed to ffmpeg", .0.display())]
+    NonUtf8Path(PathBuf),
+    #[error("Failed to start ffmpeg {process}")]
+    SpawnFfmpeg {
+        process: &'static str,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Failed to start {thread} thread")]
+    SpawnThread {
+        thread: &'static str,
+        #[source]
+        source: io::Error,
+    },
+    #[error("Failed to start overlay workers")]
+    OverlayWorkers {
+        #[from]
+        source: rayon::ThreadPoolBuildError,
+    },
+    #[error("Failed to read output of ffmpeg {process}: {message}")]
+    FfmpegOutput { process: &'static str, message: String },
+    #[error("Failed to open the encoder's input")]
+    NoEncoderInput,
+    #[error("Lost connection to the encoder")]
+    BrokenPipe {
+        #[source]
+        source: io::Error,
+    },
+    #[error("Encoder {encoder} does not support constant quality mode")]
+    ConstantQualityNotSupported { encoder: String },
+    #[error("Failed to initialize encoder: {0}")]
+    EncoderInit(String),
+    #[error("Encoder error: {0}")]
+    Encoder(String),
+    #[error("Decoder error: {0}")]
+    Decoder(String),
+    #[error("Failed to read OSD file")]
+    OsdFile {
+        #[from]
+        source: OsdFileError,
+    },
+    #[error("Decoded frame is {width}x{height} but has {bytes} bytes")]
+    UnexpectedFrameSize { width: u32, height: u32, bytes: usize },
+}
+
+#[derive(Debug, Error)]
+pub enum ExtractFrameError {
+    #[error("Path {} contains characters that can't be passed to ffmpeg", .0.display())]
+    NonUtf8Path(PathBuf),
+    #[error("Failed to start ffmpeg")]
+    SpawnFfmpeg {
+        #[source]
+        source: io::Error,
+    },
+    #[error("Failed to read output of ffmpeg: {0}")]
+    FfmpegOutput(String),
+    #[error("ffmpeg error: {0}")]
+    Ffmpeg(String),
+    #[error("Video has no frame at {0:?}")]
+    NoFrame(Duration),
+}
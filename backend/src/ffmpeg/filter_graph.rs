@@ -0,0 +1,371 @@
+use std::{
+    fs::File,
+    io::{self, BufReader, Write},
+    path::PathBuf,
+    sync::atomic::{AtomicBool, Ordering},
+    thread,
+};
+
+use crossbeam_channel::{Receiver, Sender};
+use ffmpeg_sidecar::{command::FfmpegCommand, event::FfmpegEvent};
+use image::RgbaImage;
+use rayon::prelude::*;
+
+use super::{
+    render::{add_encoder_args, handle_encoder_events, path_str, picture_filter, video_filters},
+    FromFfmpegMessage, RenderError, RenderJob, ToFfmpegMessage, VideoInfo,
+};
+use crate::{
+    osd::{OsdOptions, OsdReader},
+    overlay::{rgba_to_yuva420p, Compositor, OverlaySelector, SelectedOverlay, YUVA_PIX_FMT},
+};
+
+/// Renders the video with the [`RenderMode::FilterGraph`](super::RenderMode::FilterGraph) mode. A single ffmpeg process
+/// overlays the layers on the video while encoding it. The layers are drawn while it runs and piped to its input as raw
+/// video, each repeated for the frames it is shown on. No frames are sent to the live view.
+#[allow(clippy::type_complexity)]
+pub(super) fn start_filter_graph_render(
+    ffmpeg_path: &PathBuf,
+    job: &RenderJob,
+    video_info: &VideoInfo,
+    osd_options: &OsdOptions,
+    osd_frames: Option<OsdReader<BufReader<File>>>,
+) -> Result<
+    (
+        Sender<ToFfmpegMessage>,
+        Receiver<FromFfmpegMessage>,
+        Receiver<RgbaImage>,
+    ),
+    RenderError,
+> {
+    // The layers are drawn at the size of the cropped and scaled picture, padding is added after they are put on it
+    let (width, height) = job
+        .render_settings()
+        .output_geometry
+        .picture_size(video_info.width, video_info.height);
+    let command = filter_graph_command(ffmpeg_path, job, video_info, width, height)?;
+
+    let (from_ffmpeg_tx, from_ffmpeg_rx) = crossbeam_channel::unbounded();
+    let (to_ffmpeg_tx, to_ffmpeg_rx) = crossbeam_channel::unbounded();
+    // The frames never leave ffmpeg, the live view stays empty
+    let (_, frames_for_ui_rx) = crossbeam_channel::bounded(1);
+
+    let render = FilterGraphRender {
+        selector: OverlaySelector::new(job, osd_frames, osd_options, from_ffmpeg_tx.clone()),
+        compositor: Compositor::new(job, osd_options),
+        width,
+        height,
+        frame_rate: video_info.frame_rate,
+        start_secs: job.time_range().start_secs(),
+        total_frames: job.frame_count(video_info),
+        command,
+    };
+
+    thread::Builder::new()
+        .name("Filter graph render".into())
+        .spawn(move || {
+            tracing::info_span!("filter graph render").in_scope(|| {
+                if let Err(e) = render.run(&to_ffmpeg_rx, &from_ffmpeg_tx) {
+                    tracing::error!("Filter graph render failed: {}", e);
+                    from_ffmpeg_tx.send(FromFfmpegMessage::Error(e)).ok();
+                }
+            });
+        })
+        .map_err(|source| RenderError::SpawnThread {
+            thread: "filter graph render",
+            source,
+        })?;
+
+    Ok((to_ffmpeg_tx, from_ffmpeg_rx, frames_for_ui_rx))
+}
+
+struct FilterGraphRender {
+    selector: OverlaySelector,
+    compositor: Compositor,
+    width: u32,
+    height: u32,
+    frame_rate: f32,
+    start_secs: f32,
+    total_frames: u32,
+    command: FfmpegCommand,
+}
+
+impl FilterGraphRender {
+    fn run(
+        self,
+        to_ffmpeg_rx: &Receiver<ToFfmpegMessage>,
+        from_ffmpeg_tx: &Sender<FromFfmpegMessage>,
+    ) -> Result<(), RenderError> {
+        let Self {
+            selector,
+            compositor,
+            width,
+            height,
+            frame_rate,
+            start_secs,
+            total_frames,
+            mut command,
+        } = self;
+
+        tracing::debug!(
+            "Filter graph command: {}",
+            crate::util::command_to_cli(command.as_inner())
+        );
+        let mut ffmpeg_process = command.spawn().map_err(|source| RenderError::SpawnFfmpeg {
+            process: "encoder",
+            source,
+        })?;
+        let pipes = (|| {
+            let layer_input = ffmpeg_process.take_stdin().ok_or(RenderError::NoEncoderInput)?;
+            let events = ffmpeg_process.iter().map_err(|e| RenderError::FfmpegOutput {
+                process: "encoder",
+                message: e.to_string(),
+            })?;
+            Ok((layer_input, events))
+        })();
+        let (layer_input, events) = match pipes {
+            Ok(pipes) => pipes,
+            Err(e) => {
+                ffmpeg_process.kill().ok();
+                return Err(e);
+            }
+        };
+
+        let aborted = AtomicBool::new(false);
+        thread::scope(|scope| {
+            let layers = layer_timeline(selector, start_secs, total_frames, frame_rate);
+            let writer = thread::Builder::new()
+                .name("Overlay layer writer".into())
+                .spawn_scoped(scope, || {
+                    if let Err(e) = write_layers(layers, &compositor, width, height, layer_input, &aborted) {
+                        // ffmpeg stops reading the layers at the end of the video, errors are reported by ffmpeg
+                        tracing::debug!("Stopped writing overlay layers: {}", e);
+                    }
+                });
+            if let Err(source) = writer {
+                ffmpeg_process.kill().ok();
+                return Err(RenderError::SpawnThread {
+                    thread: "overlay layer writer",
+                    source,
+                });
+            }
+
+            for event in events {
+                if let Ok(ToFfmpegMessage::AbortRender) = to_ffmpeg_rx.try_recv() {
+                    // The video ends with the layers, ffmpeg still finishes writing the output file
+                    aborted.store(true, Ordering::Relaxed);
+                }
+                handle_filter_graph_events(event, from_ffmpeg_tx);
+            }
+            ffmpeg_process.wait().ok();
+            Ok(())
+        })
+    }
+}
+
+/// Decodes the video and the layers, overlays them and encodes the result in one ffmpeg process.
+fn filter_graph_command(
+    ffmpeg_path: &PathBuf,
+    job: &RenderJob,
+    video_info: &VideoInfo,
+    layer_width: u32,
+    layer_height: u32,
+) -> Result<FfmpegCommand, RenderError> {
+    let render_settings = job.render_settings();
+    let mut command = FfmpegCommand::new_with_path(ffmpeg_path);
+
+    command
+        .create_no_window()
+        .args(["-hwaccel", "auto"])
+        .args(job.time_range().input_args())
+        .input(path_str(job.input_video())?)
+        .format("rawvideo")
+        .pix_fmt(YUVA_PIX_FMT)
+        .size(layer_width, layer_height)
+        .rate(video_info.frame_rate)
+        .input("-");
+
+    let base = if render_settings.use_chroma_key {
+        let [r, g, b, a] = render_settings.chroma_key.map(|c| (c * 255.0) as u8);
+        let (width, height) = render_settings
+            .output_geometry
+            .picture_size(video_info.width, video_info.height);
+        command.format("lavfi").input(format!(
+            "color=c=0x{r:02x}{g:02x}{b:02x}{a:02x}:s={width}x{height}:r={}:d={}",
+            video_info.frame_rate,
+            job.time_range().duration(video_info.duration).as_secs_f32()
+        ));
+        "[2:v]format=rgba".to_string()
+    } else {
+        match picture_filter(job, video_info) {
+            Some(picture_filter) => format!("[0:v]{picture_filter}"),
+            None => "[0:v]null".to_string(),
+        }
+    };
+
+    // The last layer is piped until ffmpeg stops reading it, the video ends early only when the layers do
+    let mut graph = format!("{base}[base];[base][1:v]overlay=shortest=1:format=auto");
+    for filter in video_filters(job, video_info) {
+        graph.push(',');
+        graph.push_str(&filter);
+    }
+    graph.push_str("[video]");
+
+    command
+        .args(["-filter_complex", &graph])
+        .map("[video]")
+        .map("0:a?")
+        .codec_audio("copy");
+
+    add_encoder_args(&mut command, job, video_info)?;
+    Ok(command)
+}
+
+/// The different overlays shown on the rendered part of the video starting at `start_secs`, in order and with the
+/// first frame each is shown on. Only the current overlay is kept, the OSD frames are streamed from disk.
+fn layer_timeline(
+    mut selector: OverlaySelector,
+    start_secs: f32,
+    total_frames: u32,
+    frame_rate: f32,
+) -> impl Iterator<Item = (u32, SelectedOverlay)> + Send {
+    let mut last_layer_id = None;
+    (0..total_frames.max(1)).filter_map(move |frame| {
+        let overlay = selector.select(start_secs + frame as f32 / frame_rate);
+        (last_layer_id.replace(overlay.layer_id) != Some(overlay.layer_id)).then_some((frame, overlay))
+    })
+}
+
+/// Draws the layers on the overlay workers, a batch of one layer per worker at a time while the previous batch is
+/// written to `output`. At most two batches are held, however long the video is.
+fn write_layers(
+    mut layers: impl Iterator<Item = (u32, SelectedOverlay)> + Send,
+    compositor: &Compositor,
+    width: u32,
+    height: u32,
+    output: impl Write,
+    aborted: &AtomicBool,
+) -> io::Result<()> {
+    let batch_size = rayon::current_num_threads();
+    let (batches_tx, batches_rx) = crossbeam_channel::bounded::<Vec<(u32, Vec<u8>)>>(1);
+    thread::scope(|scope| {
+        scope.spawn(move || loop {
+            let batch = layers
+                .by_ref()
+                .take(batch_size)
+                .collect::<Vec<_>>()
+                .into_par_iter()
+                .map(|(first_frame, overlay)| {
+                    let mut layer = RgbaImage::new(width, height);
+                    compositor.draw_layer(&mut layer, &overlay.osd_frame, overlay.srt_frame.as_ref());
+                    (first_frame, rgba_to_yuva420p(&layer))
+                })
+                .collect::<Vec<_>>();
+            // Stops when the writer is done
+            if batch.is_empty() || batches_tx.send(batch).is_err() {
+                break;
+            }
+        });
+        // Dropping the receiver when writing stops ends the drawing as well
+        write_layer_frames(batches_rx.into_iter().flatten(), output, aborted)
+    })
+}
+
+/// Writes every layer once for each frame it is shown on, until the next layer's first frame. The last layer is
+/// repeated until ffmpeg stops reading it at the end of the video, the frame count is only an estimate. Stops early
+/// when `aborted` is set.
+fn write_layer_frames(
+    layers: impl Iterator<Item = (u32, Vec<u8>)>,
+    mut output: impl Write,
+    aborted: &AtomicBool,
+) -> io::Result<()> {
+    let mut write = |layer: &[u8]| {
+        if aborted.load(Ordering::Relaxed) {
+            return Err(io::Error::new(io::ErrorKind::Interrupted, "render aborted"));
+        }
+        output.write_all(layer)
+    };
+
+    let mut current: Option<(u32, Vec<u8>)> = None;
+    for (first_frame, layer) in layers {
+        if let Some((current_first_frame, current_layer)) = current.replace((first_frame, layer)) {
+            for _ in current_first_frame..first_frame {
+                write(&current_layer)?;
+            }
+        }
+    }
+    if let Some((_, last_layer)) = current {
+        loop {
+            write(&last_layer)?;
+        }
+    }
+    Ok(())
+}
+
+/// The one ffmpeg process decodes and encodes, its progress is reported for both.
+fn handle_filter_graph_events(ffmpeg_event: FfmpegEvent, ffmpeg_sender: &Sender<FromFfmpegMessage>) {
+    match &ffmpeg_event {
+        FfmpegEvent::Progress(p) => {
+            ffmpeg_sender.send(FromFfmpegMessage::DecoderProgress(p.clone())).ok();
+        }
+        FfmpegEvent::LogEOF => {
+            ffmpeg_sender.send(FromFfmpegMessage::DecoderFinished).ok();
+        }
+        _ => {}
+    }
+    handle_encoder_events(ffmpeg_event, ffmpeg_sender);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Accepts `capacity` bytes, then fails like a pipe that ffmpeg closed.
+    struct ClosingPipe {
+        written: Vec<u8>,
+        capacity: usize,
+    }
+
+    impl Write for ClosingPipe {
+        fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+            if self.written.len() + buf.len() > self.capacity {
+                return Err(io::ErrorKind::BrokenPipe.into());
+            }
+            self.written.extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn layers_are_repeated_until_the_next_one() {
+        let layers = [(0, vec![1]), (2, vec![2]), (3, vec![3])];
+        let mut pipe = ClosingPipe {
+            written: vec![],
+            capacity: 6,
+        };
+        let result = write_layer_frames(layers.into_iter(), &mut pipe, &AtomicBool::new(false));
+
+        // The last layer is repeated until the pipe is closed
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::BrokenPipe);
+        assert_eq!(pipe.written, [1, 1, 2, 3, 3, 3]);
+    }
+
+    #[test]
+    fn aborted_render_stops_writing_layers() {
+        let mut pipe = ClosingPipe {
+            written: vec![],
+            capacity: 6,
+        };
+        let result = write_layer_frames(
+            [(0, vec![1]), (2, vec![2])].into_iter(),
+            &mut pipe,
+            &AtomicBool::new(true),
+        );
+        assert_eq!(result.unwrap_err().kind(), io::ErrorKind::Interrupted);
+        assert!(pipe.written.is_empty());
+    }
+}
@@ -0,0 +1,229 @@
+use std::{
+    collections::VecDeque,
+    path::{Path, PathBuf},
+    sync::Arc,
+    thread,
+    time::Duration,
+};
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+use ffmpeg_sidecar::{
+    command::FfmpegCommand,
+    event::{FfmpegEvent, LogLevel},
+};
+use image::RgbaImage;
+
+use super::error::ExtractFrameError;
+
+/// Extracted frames that are kept, a 4K frame is 33 MB.
+const FRAME_CACHE_SIZE: usize = 8;
+
+/// Requests that follow each other within this time are merged, so scrubbing through the preview only extracts the
+/// frame it stops at.
+const REQUEST_DEBOUNCE: Duration = Duration::from_millis(120);
+
+/// A frame of the video as far as the [`FrameExtractor`] knows it.
+#[derive(Debug, Clone)]
+pub enum ExtractedFrame {
+    Ready(Arc<RgbaImage>),
+    /// Being extracted in the background, [`FrameExtractor::poll`] tells when it's done.
+    Pending,
+    /// The video has no frame at this time or can't be decoded.
+    Unavailable,
+}
+
+/// Extracts decoded frames of a video at any time on a background thread, e.g. to show the OSD preview on the footage.
+/// The last few frames are cached by their frame number so going back and forth in the preview doesn't start ffmpeg for
+/// every step. Failures are cached as well.
+#[derive(Debug)]
+pub struct FrameExtractor {
+    video: PathBuf,
+    frame_rate: f32,
+    requests_tx: Sender<(u64, Duration)>,
+    results_rx: Receiver<(u64, Result<RgbaImage, ExtractFrameError>)>,
+    cache: FrameCache,
+    pending: Option<u64>,
+    /// Set when ffmpeg can't decode the video at all, no more frames are requested then.
+    failed: bool,
+}
+
+impl FrameExtractor {
+    pub fn new(ffmpeg_path: PathBuf, video: PathBuf, frame_rate: f32) -> Self {
+        let (requests_tx, requests_rx) = crossbeam_channel::unbounded();
+        let (results_tx, results_rx) = crossbeam_channel::unbounded();
+        let worker_video = video.clone();
+        // The thread ends when the extractor is dropped and the request channel closes
+        if let Err(e) = thread::Builder::new()
+            .name("Video frame extractor".into())
+            .spawn(move || run_extractor(&ffmpeg_path, &worker_video, requests_rx, results_tx))
+        {
+            tracing::error!("Failed to start video frame extractor: {}", e);
+        }
+
+        Self {
+            video,
+            frame_rate,
+            requests_tx,
+            results_rx,
+            cache: FrameCache::default(),
+            pending: None,
+            failed: false,
+        }
+    }
+
+    pub fn video(&self) -> &Path {
+        &self.video
+    }
+
+    /// The frame shown at `time`. Frames that aren't cached are extracted in the background, ask again once
+    /// [`FrameExtractor::poll`] returns `true`.
+    pub fn frame_at(&mut self, time: Duration) -> ExtractedFrame {
+        let frame_number = (time.as_secs_f32() * self.frame_rate).round() as u64;
+        if let Some(frame) = self.cache.get(frame_number) {
+            return frame;
+        }
+        if self.failed {
+            return ExtractedFrame::Unavailable;
+        }
+        if self.pending != Some(frame_number) {
+            if self.requests_tx.send((frame_number, time)).is_err() {
+                self.failed = true;
+                return ExtractedFrame::Unavailable;
+            }
+            self.pending = Some(frame_number);
+        }
+        ExtractedFrame::Pending
+    }
+
+    pub fn is_pending(&self) -> bool {
+        self.pending.is_some()
+    }
+
+    /// Caches the frames extracted since the last call. Returns `true` if the frame asked for last is done.
+    pub fn poll(&mut self) -> bool {
+        let mut pending_done = false;
+        while let Ok((frame_number, result)) = self.results_rx.try_recv() {
+            let frame = match result {
+                Ok(frame) => Some(Arc::new(frame)),
+                Err(e @ ExtractFrameError::NoFrame(_)) => {
+                    tracing::info!("{}", e);
+                    None
+                }
+                Err(e) => {
+                    tracing::warn!("Failed to extract video frame for preview: {}", e);
+                    self.failed = true;
+                    None
+                }
+            };
+            self.cache.insert(frame_number, frame);
+            if self.pending == Some(frame_number) {
+                self.pending = None;
+                pending_done = true;
+            }
+        }
+        pending_done
+    }
+}
+
+/// The last [`FRAME_CACHE_SIZE`] extracted frames by their frame number, the oldest is dropped first.
+#[derive(Debug, Default)]
+struct FrameCache {
+    /// `None` if the frame couldn't be extracted.
+    frames: VecDeque<(u64, Option<Arc<RgbaImage>>)>,
+}
+
+impl FrameCache {
+    /// `None` if the frame isn't cached, [`ExtractedFrame::Unavailable`] if extracting it failed.
+    fn get(&self, frame_number: u64) -> Option<ExtractedFrame> {
+        self.frames
+            .iter()
+            .find(|(cached, _)| *cached == frame_number)
+            .map(|(_, frame)| frame.clone().map_or(ExtractedFrame::Unavailable, ExtractedFrame::Ready))
+    }
+
+    fn insert(&mut self, frame_number: u64, frame: Option<Arc<RgbaImage>>) {
+        if self.frames.len() == FRAME_CACHE_SIZE {
+            self.frames.pop_front();
+        }
+        self.frames.push_back((frame_number, frame));
+    }
+}
+
+/// Extracts the frames requested through `requests_rx`. Only the last of the requests that come in quick succession
+/// is extracted, the frames asked for while scrubbing are already out of date.
+fn run_extractor(
+    ffmpeg_path: &PathBuf,
+    video: &Path,
+    requests_rx: Receiver<(u64, Duration)>,
+    results_tx: Sender<(u64, Result<RgbaImage, ExtractFrameError>)>,
+) {
+    while let Ok(mut request) = requests_rx.recv() {
+        loop {
+            match requests_rx.recv_timeout(REQUEST_DEBOUNCE) {
+                Ok(newer_request) => request = newer_request,
+                Err(RecvTimeoutError::Timeout) => break,
+                Err(RecvTimeoutError::Disconnected) => return,
+            }
+        }
+        let (frame_number, time) = request;
+        if results_tx
+            .send((frame_number, extract_frame(ffmpeg_path, video, time)))
+            .is_err()
+        {
+            return;
+        }
+    }
+}
+
+/// Decodes the frame of `video` shown at `time`. ffmpeg seeks to the keyframe before it and decodes from there.
+#[tracing::instrument(skip(ffmpeg_path), err)]
+pub fn extract_frame(ffmpeg_path: &PathBuf, video: &Path, time: Duration) -> Result<RgbaImage, ExtractFrameError> {
+    let video_str = video
+        .to_str()
+        .ok_or_else(|| ExtractFrameError::NonUtf8Path(video.to_path_buf()))?;
+    let mut ffmpeg_process = FfmpegCommand::new_with_path(ffmpeg_path)
+        .create_no_window()
+        .args(["-ss", &format!("{:.3}", time.as_secs_f32())])
+        .input(video_str)
+        .args(["-frames:v", "1", "-an", "-f", "rawvideo", "-pix_fmt", "rgba", "-"])
+        .spawn()
+        .map_err(|source| ExtractFrameError::SpawnFfmpeg { source })?;
+    let events = ffmpeg_process
+        .iter()
+        .map_err(|e| ExtractFrameError::FfmpegOutput(e.to_string()))?;
+
+    let mut result = Err(ExtractFrameError::NoFrame(time));
+    for event in events {
+        match event {
+            FfmpegEvent::OutputFrame(frame) => {
+                if let Some(image) = RgbaImage::from_raw(frame.width, frame.height, frame.data) {
+                    result = Ok(image);
+                }
+            }
+            FfmpegEvent::Log(LogLevel::Fatal, e) => result = Err(ExtractFrameError::Ffmpeg(e)),
+            _ => {}
+        }
+    }
+    ffmpeg_process.wait().ok();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn extracted_frames_and_failures_are_cached() {
+        let mut cache = FrameCache::default();
+        for frame_number in 0..=FRAME_CACHE_SIZE as u64 {
+            cache.insert(frame_number * 60, Some(Arc::new(RgbaImage::new(1, 1))));
+        }
+        assert_eq!(cache.frames.len(), FRAME_CACHE_SIZE);
+        assert!(cache.get(0).is_none());
+        cache.insert(600, None);
+
+        assert!(matches!(cache.get(180), Some(ExtractedFrame::Ready(_))));
+        assert!(matches!(cache.get(600), Some(ExtractedFrame::Unavailable)));
+        assert!(cache.get(60).is_none());
+    }
+}
@@ -1,8 +1,11 @@
 use ffmpeg_sidecar::event::FfmpegProgress;
 
+use super::RenderError;
+
 pub enum FromFfmpegMessage {
-    DecoderFatalError(String),
-    EncoderFatalError(String),
+    Error(RenderError),
+    /// A problem the render continues after, e.g. the rest of the OSD file can't be read.
+    Warning(RenderError),
     EncoderProgress(FfmpegProgress),
     DecoderProgress(FfmpegProgress),
     DecoderFinished,
@@ -1,14 +1,26 @@
 mod dependencies;
 mod encoders;
 mod error;
+mod filter_graph;
+mod frame_extractor;
 mod message;
+mod output_geometry;
 mod render;
+mod render_job;
 mod render_settings;
+mod time_range;
 pub(crate) mod video_info;
+mod wait;
 
 pub use dependencies::{ffmpeg_available, ffprobe_available};
 pub use encoders::{Codec, Encoder};
+pub use error::{ExtractFrameError, RenderError, RenderJobError, VideoInfoError};
+pub use frame_extractor::{extract_frame, ExtractedFrame, FrameExtractor};
 pub use message::{FromFfmpegMessage, ToFfmpegMessage};
+pub use output_geometry::{CropRect, OutputGeometry, OutputResolution, ScalingFilter};
 pub use render::{handle_decoder_events, start_video_render};
-pub use render_settings::RenderSettings;
+pub use render_job::{RenderJob, RenderJobBuilder, PREVIEW_CLIP_SECS};
+pub use render_settings::{RenderMode, RenderSettings};
+pub use time_range::TimeRange;
 pub use video_info::VideoInfo;
+pub use wait::{wait_for_render, RenderOutcome};
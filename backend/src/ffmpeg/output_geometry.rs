@@ -0,0 +1,350 @@
+use std::fmt::Display;
+
+use image::{imageops::FilterType, RgbaImage};
+use serde::{Deserialize, Serialize};
+
+use crate::util::Coordinates;
+
+/// Size of the rendered video.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum OutputResolution {
+    /// The size of the input video, after cropping.
+    #[default]
+    Source,
+    P720,
+    P1080,
+    /// YouTube gives 1440p videos a higher bitrate than 1080p ones.
+    P1440,
+    P2160,
+    Custom {
+        width: u32,
+        height: u32,
+    },
+}
+
+impl OutputResolution {
+    /// The box the video is fitted in, `None` if it keeps its size.
+    pub fn dimensions(&self) -> Option<(u32, u32)> {
+        match *self {
+            OutputResolution::Source => None,
+            OutputResolution::P720 => Some((1280, 720)),
+            OutputResolution::P1080 => Some((1920, 1080)),
+            OutputResolution::P1440 => Some((2560, 1440)),
+            OutputResolution::P2160 => Some((3840, 2160)),
+            // Even, so yuv420p frames have whole chroma samples
+            OutputResolution::Custom { width, height } => Some((width.max(2) & !1, height.max(2) & !1)),
+        }
+    }
+}
+
+impl Display for OutputResolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OutputResolution::Source => write!(f, "Same as input"),
+            OutputResolution::P720 => write!(f, "720p"),
+            OutputResolution::P1080 => write!(f, "1080p"),
+            OutputResolution::P1440 => write!(f, "1440p"),
+            OutputResolution::P2160 => write!(f, "2160p (4K)"),
+            OutputResolution::Custom { width, height } => write!(f, "{width}x{height}"),
+        }
+    }
+}
+
+/// Filter ffmpeg scales the video with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum ScalingFilter {
+    Bilinear,
+    #[default]
+    Bicubic,
+    Lanczos,
+    /// Keeps hard pixel edges, for integer scale factors.
+    Neighbor,
+}
+
+impl ScalingFilter {
+    /// Name of the filter in the `flags` option of ffmpeg's `scale` filter.
+    pub fn ffmpeg_name(&self) -> &'static str {
+        match self {
+            ScalingFilter::Bilinear => "bilinear",
+            ScalingFilter::Bicubic => "bicubic",
+            ScalingFilter::Lanczos => "lanczos",
+            ScalingFilter::Neighbor => "neighbor",
+        }
+    }
+
+    /// Name of the filter in the `interp_algo` option of ffmpeg's `scale_cuda` filter.
+    pub fn cuda_name(&self) -> &'static str {
+        match self {
+            ScalingFilter::Neighbor => "nearest",
+            _ => self.ffmpeg_name(),
+        }
+    }
+
+    /// The closest filter of the `image` crate, for scaling frames in the app.
+    fn image_filter(&self) -> FilterType {
+        match self {
+            ScalingFilter::Bilinear => FilterType::Triangle,
+            ScalingFilter::Bicubic => FilterType::CatmullRom,
+            ScalingFilter::Lanczos => FilterType::Lanczos3,
+            ScalingFilter::Neighbor => FilterType::Nearest,
+        }
+    }
+}
+
+impl Display for ScalingFilter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                ScalingFilter::Bilinear => "Bilinear",
+                ScalingFilter::Bicubic => "Bicubic",
+                ScalingFilter::Lanczos => "Lanczos",
+                ScalingFilter::Neighbor => "Nearest neighbor",
+            }
+        )
+    }
+}
+
+/// Part of the input video that is kept, in pixels of the input video.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct CropRect {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// How the input video is cropped, scaled and padded to get the output video. The OSD and SRT data are drawn on the
+/// video after it's cropped and scaled, so they cover the visible picture and not the bars added by padding.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct OutputGeometry {
+    pub resolution: OutputResolution,
+    pub scaling_filter: ScalingFilter,
+    pub crop: Option<CropRect>,
+    /// Letterbox or pillarbox the video to exactly the output resolution instead of only fitting it in.
+    pub pad: bool,
+    pub pad_color: [f32; 4],
+}
+
+impl Default for OutputGeometry {
+    fn default() -> Self {
+        Self {
+            resolution: OutputResolution::Source,
+            scaling_filter: ScalingFilter::Bicubic,
+            crop: None,
+            pad: false,
+            pad_color: [0.0, 0.0, 0.0, 1.0],
+        }
+    }
+}
+
+impl OutputGeometry {
+    /// The crop rectangle inside an input video of `width` x `height`. The position and size are even so the
+    /// chroma planes of yuv420p frames are cropped along.
+    pub fn crop_rect(&self, width: u32, height: u32) -> CropRect {
+        let Some(crop) = self.crop else {
+            return CropRect {
+                x: 0,
+                y: 0,
+                width,
+                height,
+            };
+        };
+        let x = (crop.x & !1).min(width.saturating_sub(2));
+        let y = (crop.y & !1).min(height.saturating_sub(2));
+        CropRect {
+            x,
+            y,
+            width: (crop.width.min(width - x) & !1).max(2),
+            height: (crop.height.min(height - y) & !1).max(2),
+        }
+    }
+
+    /// Size of the cropped video after scaling it to fit the output resolution with its aspect ratio kept. The overlay
+    /// is drawn at this size.
+    pub fn picture_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let crop = self.crop_rect(width, height);
+        let Some((box_width, box_height)) = self.resolution.dimensions() else {
+            return (crop.width, crop.height);
+        };
+        let (crop_width, crop_height) = (crop.width as u64, crop.height as u64);
+        if crop_width * box_height as u64 <= box_width as u64 * crop_height {
+            (round_even(crop_width * box_height as u64, crop_height), box_height)
+        } else {
+            (box_width, round_even(crop_height * box_width as u64, crop_width))
+        }
+    }
+
+    /// The OSD position, set in pixels of the input video, in pixels of the picture the overlay is drawn on.
+    pub fn osd_position(&self, width: u32, height: u32, position: &Coordinates<i32>) -> Coordinates<i32> {
+        let crop = self.crop_rect(width, height);
+        let (picture_width, picture_height) = self.picture_size(width, height);
+        Coordinates::new(
+            (position.x as f32 * picture_width as f32 / crop.width as f32).round() as i32,
+            (position.y as f32 * picture_height as f32 / crop.height as f32).round() as i32,
+        )
+    }
+
+    /// Crops and scales a frame of the input video to the picture like the ffmpeg filters do, for previewing it.
+    pub fn picture(&self, frame: &RgbaImage) -> RgbaImage {
+        let (width, height) = frame.dimensions();
+        let crop = self.crop_rect(width, height);
+        let (picture_width, picture_height) = self.picture_size(width, height);
+        let cropped = image::imageops::crop_imm(frame, crop.x, crop.y, crop.width, crop.height);
+        if (picture_width, picture_height) == (crop.width, crop.height) {
+            cropped.to_image()
+        } else {
+            image::imageops::resize(
+                &*cropped,
+                picture_width,
+                picture_height,
+                self.scaling_filter.image_filter(),
+            )
+        }
+    }
+
+    /// Size of the output video, the picture plus the padding around it.
+    pub fn frame_size(&self, width: u32, height: u32) -> (u32, u32) {
+        match self.resolution.dimensions() {
+            Some(dimensions) if self.pad => dimensions,
+            _ => self.picture_size(width, height),
+        }
+    }
+
+    /// ffmpeg filters that crop and scale the input video to the picture, `None` if it stays as it is.
+    pub fn picture_filter(&self, width: u32, height: u32) -> Option<String> {
+        self.picture_filter_with(width, height, |picture_width, picture_height| {
+            format!(
+                "scale={picture_width}:{picture_height}:flags={}",
+                self.scaling_filter.ffmpeg_name()
+            )
+        })
+    }
+
+    /// Like [`OutputGeometry::picture_filter`], but scales on an NVIDIA GPU with `scale_cuda`. The frames are uploaded
+    /// to the GPU for scaling and come back as yuv420p.
+    pub fn cuda_picture_filter(&self, width: u32, height: u32) -> Option<String> {
+        self.picture_filter_with(width, height, |picture_width, picture_height| {
+            format!(
+                "format=yuv420p,hwupload_cuda,scale_cuda={picture_width}:{picture_height}:interp_algo={},hwdownload,\
+                 format=yuv420p",
+                self.scaling_filter.cuda_name()
+            )
+        })
+    }
+
+    fn picture_filter_with(&self, width: u32, height: u32, scale: impl Fn(u32, u32) -> String) -> Option<String> {
+        let crop = self.crop_rect(width, height);
+        let (picture_width, picture_height) = self.picture_size(width, height);
+        let mut filters = vec![];
+        if (crop.width, crop.height) != (width, height) {
+            filters.push(format!("crop={}:{}:{}:{}", crop.width, crop.height, crop.x, crop.y));
+        }
+        if (picture_width, picture_height) != (crop.width, crop.height) {
+            filters.push(scale(picture_width, picture_height));
+        }
+        (!filters.is_empty()).then(|| filters.join(","))
+    }
+
+    /// ffmpeg filter that centers the picture on a frame of the output size, `None` if there is no padding.
+    pub fn pad_filter(&self, width: u32, height: u32) -> Option<String> {
+        let (picture_width, picture_height) = self.picture_size(width, height);
+        let (frame_width, frame_height) = self.frame_size(width, height);
+        let [r, g, b, a] = self.pad_color.map(|c| (c * 255.0) as u8);
+        ((picture_width, picture_height) != (frame_width, frame_height)).then(|| {
+            format!(
+                "pad={frame_width}:{frame_height}:{}:{}:color=0x{r:02x}{g:02x}{b:02x}{a:02x}",
+                ((frame_width - picture_width) / 2) & !1,
+                ((frame_height - picture_height) / 2) & !1
+            )
+        })
+    }
+}
+
+/// `numerator / denominator` rounded to the nearest even number, like ffmpeg does for a size of -2.
+fn round_even(numerator: u64, denominator: u64) -> u32 {
+    (((numerator + denominator) / (2 * denominator)) * 2).max(2) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn picture_is_fitted_in_output_resolution() {
+        let mut geometry = OutputGeometry::default();
+        assert_eq!(geometry.picture_size(1920, 1080), (1920, 1080));
+        assert_eq!(geometry.picture_filter(1920, 1080), None);
+
+        geometry.resolution = OutputResolution::P1440;
+        assert_eq!(geometry.picture_size(1920, 1080), (2560, 1440));
+        assert_eq!(geometry.picture_size(1280, 720), (2560, 1440));
+        assert_eq!(geometry.picture_size(1440, 1080), (1920, 1440));
+        assert_eq!(geometry.picture_size(1000, 748), (1926, 1440));
+        assert_eq!(
+            geometry.picture_filter(1920, 1080).as_deref(),
+            Some("scale=2560:1440:flags=bicubic")
+        );
+        assert_eq!(
+            geometry.cuda_picture_filter(1920, 1080).as_deref(),
+            Some("format=yuv420p,hwupload_cuda,scale_cuda=2560:1440:interp_algo=bicubic,hwdownload,format=yuv420p")
+        );
+        assert_eq!(
+            geometry.osd_position(1920, 1080, &Coordinates::new(30, -15)),
+            Coordinates::new(40, -20)
+        );
+
+        // Wider than the output resolution
+        geometry.resolution = OutputResolution::Custom {
+            width: 1000,
+            height: 1000,
+        };
+        assert_eq!(geometry.picture_size(1920, 1080), (1000, 562));
+    }
+
+    #[test]
+    fn cropped_picture_is_padded_to_output_resolution() {
+        let geometry = OutputGeometry {
+            resolution: OutputResolution::P1080,
+            scaling_filter: ScalingFilter::Lanczos,
+            crop: Some(CropRect {
+                x: 241,
+                y: 0,
+                width: 1440,
+                height: 2000,
+            }),
+            pad: true,
+            pad_color: [0.0, 0.0, 0.0, 1.0],
+        };
+        assert_eq!(
+            geometry.crop_rect(1920, 1080),
+            CropRect {
+                x: 240,
+                y: 0,
+                width: 1440,
+                height: 1080
+            }
+        );
+        assert_eq!(geometry.picture_size(1920, 1080), (1440, 1080));
+        assert_eq!(geometry.frame_size(1920, 1080), (1920, 1080));
+        assert_eq!(
+            geometry.picture_filter(1920, 1080).as_deref(),
+            Some("crop=1440:1080:240:0")
+        );
+        assert_eq!(
+            geometry.cuda_picture_filter(1920, 1080).as_deref(),
+            Some("crop=1440:1080:240:0")
+        );
+        assert_eq!(geometry.picture(&RgbaImage::new(1920, 1080)).dimensions(), (1440, 1080));
+        assert_eq!(
+            geometry.osd_position(1920, 1080, &Coordinates::new(-20, 10)),
+            Coordinates::new(-20, 10)
+        );
+        assert_eq!(
+            geometry.pad_filter(1920, 1080).as_deref(),
+            Some("pad=1920:1080:240:0:color=0x000000ff")
+        );
+    }
+}
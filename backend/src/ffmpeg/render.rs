@@ -1,6 +1,7 @@
 use std::{
-    io::{self, Write},
-    path::PathBuf,
+    collections::BTreeMap,
+    io::Write,
+    path::{Path, PathBuf},
     thread,
 };
 
@@ -8,113 +9,167 @@ use crossbeam_channel::{Receiver, Sender};
 use ffmpeg_sidecar::{
     child::FfmpegChild,
     command::FfmpegCommand,
-    event::{FfmpegEvent, LogLevel},
+    event::{FfmpegEvent, LogLevel, OutputVideoFrame},
 };
 use image::RgbaImage;
 
-use super::{render_settings::RenderSettings, Encoder, FromFfmpegMessage, ToFfmpegMessage, VideoInfo};
+use super::{
+    filter_graph::start_filter_graph_render, Encoder, FromFfmpegMessage, RenderError, RenderJob, RenderMode,
+    ToFfmpegMessage, VideoInfo,
+};
 use crate::{
-    font,
-    osd::{self, OsdOptions},
-    overlay::FrameOverlayIter,
-    srt::{self, SrtOptions},
+    osd::OsdFile,
+    overlay::{yuv420p_to_rgba, Compositor, FrameOverlayIter, OverlayFrame, OverlaySelector, YUV_PIX_FMT},
 };
 
-fn run_ready_frames_to_queue(
-    frame_iter: impl Iterator<Item = ffmpeg_sidecar::event::OutputVideoFrame>,
-    tx: Sender<ffmpeg_sidecar::event::OutputVideoFrame>,
+/// Frames that are being composited or wait to be written to the encoder, per overlay worker. This bounds the memory
+/// the render uses, a single decoded 4K frame is 33 MB.
+const FRAMES_IN_FLIGHT_PER_WORKER: usize = 2;
+
+/// Hands the frames to the overlay workers. Every frame takes a slot that is freed once it's written to the encoder,
+/// so the workers can't get too far ahead of a slow frame.
+fn run_overlay_frames_to_workers(
+    frame_iter: impl Iterator<Item = OverlayFrame>,
+    slots_tx: Sender<()>,
+    tx: Sender<OverlayFrame>,
 ) {
     for frame in frame_iter {
-        // If send fails (receiver dropped), we exit the thread
-        if tx.send(frame).is_err() {
+        // If send fails (encoder side stopped), we exit the thread
+        if slots_tx.send(()).is_err() || tx.send(frame).is_err() {
             break;
         }
-        // tracing::debug!("Frame queued for processing. Queued: {}", tx.len());
     }
 }
 
-fn run_ready_frames_from_queue_to_encoder(
-    rx: Receiver<ffmpeg_sidecar::event::OutputVideoFrame>,
-    mut encoder_stdin: impl Write,
-    frame_to_ui_tx: Sender<RgbaImage>,
+fn run_overlay_worker(
+    compositor: Compositor,
+    rx: Receiver<OverlayFrame>,
+    tx: Sender<(u64, Option<OutputVideoFrame>)>,
+    ffmpeg_sender: Sender<FromFfmpegMessage>,
 ) {
     while let Ok(frame) = rx.recv() {
-        let _start_write_all = std::time::Instant::now();
-
-        // write_all can take a lot of time if the encoder process is not ready to read it's stdin, it means encoder is the bottleneck.
-        // If write fails, we just log it and continue
-        if let Err(e) = encoder_stdin.write_all(&frame.data) {
-            tracing::error!("Failed to write frame: {}", e);
-            continue;
+        let index = frame.index;
+        let video_frame = compositor.composite(frame, &ffmpeg_sender);
+        if tx.send((index, video_frame)).is_err() {
+            break;
         }
+    }
+}
 
-        if frame_to_ui_tx.is_empty() {
-            let _start = std::time::Instant::now();
-
-            let rgba_image = RgbaImage::from_raw(frame.width, frame.height, frame.data).unwrap();
-            let _ = frame_to_ui_tx.send(rgba_image);
+/// Writes the composited frames to the encoder in video order. Frames that finished early wait in `pending` until the
+/// ones before them are done. Frames are only converted for the live view while there is one, `frame_to_ui_tx` is
+/// `None` when it's turned off.
+fn run_ready_frames_to_encoder(
+    rx: Receiver<(u64, Option<OutputVideoFrame>)>,
+    slots_rx: Receiver<()>,
+    mut encoder_stdin: impl Write,
+    mut frame_to_ui_tx: Option<Sender<RgbaImage>>,
+    ffmpeg_sender: Sender<FromFfmpegMessage>,
+) {
+    let mut pending = BTreeMap::new();
+    let mut next_index = 0;
+    while let Ok((index, frame)) = rx.recv() {
+        pending.insert(index, frame);
+
+        while let Some(frame) = pending.remove(&next_index) {
+            next_index += 1;
+            slots_rx.recv().ok();
+
+            // Frames the compositor couldn't use are left out
+            let Some(frame) = frame else {
+                continue;
+            };
+
+            // write_all can take a lot of time if the encoder process is not ready to read it's stdin, it means encoder is the bottleneck.
+            // If write fails the encoder is gone, report it and stop so the decoder side shuts down as well
+            if let Err(e) = encoder_stdin.write_all(&frame.data) {
+                tracing::error!("Failed to write frame: {}", e);
+                ffmpeg_sender
+                    .send(FromFfmpegMessage::Error(RenderError::BrokenPipe { source: e }))
+                    .ok();
+                return;
+            }
 
-            // tracing::info!(
-            //     "sending ffmpeg frame into ui pipe done in {:?}.",
-            //     _start.elapsed()
-            // );
+            if let Some(tx) = frame_to_ui_tx.as_ref().filter(|tx| tx.is_empty()) {
+                let rgba_image = if frame.pix_fmt == YUV_PIX_FMT {
+                    yuv420p_to_rgba(frame.width, frame.height, &frame.data)
+                } else {
+                    RgbaImage::from_raw(frame.width, frame.height, frame.data)
+                };
+                // Headless renders drop the receiver, stop converting frames nobody looks at
+                if rgba_image.is_some_and(|rgba_image| tx.send(rgba_image).is_err()) {
+                    frame_to_ui_tx = None;
+                }
+            }
         }
-
-        // tracing::info!(
-        //     "encoder_stdin.write_all done in {:?}.",
-        //     _start_write_all.elapsed()
-        // );
     }
 }
 
 #[allow(clippy::type_complexity)]
-#[tracing::instrument(skip(osd_frames, srt_frames, font_file), err)]
+#[tracing::instrument(skip(job), fields(input_video = ?job.input_video(), output_video = ?job.output_video()), err)]
 pub fn start_video_render(
     ffmpeg_path: &PathBuf,
-    input_video: &PathBuf,
-    output_video: &PathBuf,
-    osd_frames: Vec<osd::Frame>,
-    srt_frames: Option<Vec<srt::SrtFrame>>,
-    font_file: font::FontFile,
-    srt_font: rusttype::Font<'static>,
-    osd_options: &OsdOptions,
-    srt_options: &SrtOptions,
+    job: &RenderJob,
     video_info: &VideoInfo,
-    render_settings: &RenderSettings,
-    encoder: &Encoder,
 ) -> Result<
     (
         Sender<ToFfmpegMessage>,
         Receiver<FromFfmpegMessage>,
         Receiver<RgbaImage>,
     ),
-    io::Error,
+    RenderError,
 > {
-    if input_video == output_video {
-        panic!("This should never happen");
+    let input_video = job.input_video();
+    if input_video == job.output_video() {
+        return Err(RenderError::OutputIsInput(input_video.clone()));
     }
 
-    let mut decoder_process = spawn_decoder(ffmpeg_path, input_video)?;
-
-    let mut encoder_process = spawn_encoder(
-        ffmpeg_path,
-        video_info.width,
-        video_info.height,
-        video_info.frame_rate,
-        video_info.time_base,
-        render_settings.bitrate_mbps,
-        render_settings.keep_quality,
-        encoder,
-        output_video,
-        render_settings.upscale,
-        render_settings.rescale_to_4x3_aspect,
-        if render_settings.use_chroma_key {
-            Some(render_settings.chroma_key)
-        } else {
-            None
-        },
-        input_video,
-    )?;
+    let mut osd_options = job.osd_options().clone();
+    osd_options.osd_playback_speed_factor = job.osd_file().map_or(1.0, |osd_file| {
+        osd_options.playback_speed_factor(video_info.duration, osd_file.duration)
+    });
+    // The OSD offset is set in pixels of the input video, the overlay is drawn on the cropped and scaled picture
+    osd_options.position =
+        job.render_settings()
+            .output_geometry
+            .osd_position(video_info.width, video_info.height, &osd_options.position);
+
+    let osd_frames = job.osd_file().map(OsdFile::frames).transpose()?;
+
+    if job.render_settings().render_mode == RenderMode::FilterGraph {
+        return start_filter_graph_render(ffmpeg_path, job, video_info, &osd_options, osd_frames);
+    }
+
+    let overlay_workers = rayon::ThreadPoolBuilder::new()
+        .thread_name(|index| format!("Overlay worker {}", index))
+        .build()?;
+
+    let mut decoder_process = spawn_decoder(ffmpeg_path, job, video_info)?;
+
+    let mut encoder_process = spawn_encoder(ffmpeg_path, job, video_info).inspect_err(|_| {
+        decoder_process.kill().ok();
+    })?;
+
+    let pipes = (|| {
+        let decoder_iter = decoder_process.iter().map_err(|e| RenderError::FfmpegOutput {
+            process: "decoder",
+            message: e.to_string(),
+        })?;
+        let encoder_iter = encoder_process.iter().map_err(|e| RenderError::FfmpegOutput {
+            process: "encoder",
+            message: e.to_string(),
+        })?;
+        let encoder_stdin = encoder_process.take_stdin().ok_or(RenderError::NoEncoderInput)?;
+        Ok((decoder_iter, encoder_iter, encoder_stdin))
+    })();
+    let (decoder_iter, encoder_iter, encoder_stdin) = match pipes {
+        Ok(pipes) => pipes,
+        Err(e) => {
+            decoder_process.kill().ok();
+            encoder_process.kill().ok();
+            return Err(e);
+        }
+    };
 
     // Channels to communicate with ffmpeg handler thread
     let (from_ffmpeg_tx, from_ffmpeg_rx) = crossbeam_channel::unbounded();
@@ -122,170 +177,263 @@ pub fn start_video_render(
     let (frames_for_ui_tx, frames_for_ui_rx) = crossbeam_channel::bounded(1);
 
     // Iterator over decoded video and OSD frames
+    let selector = OverlaySelector::new(job, osd_frames, &osd_options, from_ffmpeg_tx.clone());
     let frame_overlay_iter = FrameOverlayIter::new(
-        decoder_process
-            .iter()
-            .expect("Failed to create `FfmpegIterator` for decoder"),
+        decoder_iter,
         decoder_process,
-        osd_frames,
-        srt_frames,
-        font_file,
-        srt_font,
-        osd_options,
-        srt_options,
+        selector,
+        job.time_range().start_secs(),
         from_ffmpeg_tx.clone(),
         to_ffmpeg_rx,
-        if render_settings.use_chroma_key {
-            Some(render_settings.chroma_key)
-        } else {
-            None
-        },
     );
 
-    // On another thread run the decoder iterator to completion and feed the output to the encoder's stdin
-    let encoder_stdin = encoder_process.take_stdin().expect("Failed to get `stdin` for encoder");
-
-    let (ready_frames_queue_in, ready_frames_queue_out) =
-        crossbeam_channel::bounded::<ffmpeg_sidecar::event::OutputVideoFrame>(256); // Much smaller buffer to prevent memory bloat
+    // On another thread run the decoder iterator to completion and fan the frames out to the overlay workers. The
+    // composited frames are put back in order and fed to the encoder's stdin.
+    let frames_in_flight = overlay_workers.current_num_threads() * FRAMES_IN_FLIGHT_PER_WORKER;
+    let (slots_tx, slots_rx) = crossbeam_channel::bounded(frames_in_flight);
+    let (overlay_frames_in, overlay_frames_out) = crossbeam_channel::bounded(frames_in_flight);
+    let (ready_frames_in, ready_frames_out) = crossbeam_channel::bounded(frames_in_flight);
+
+    let compositor = Compositor::new(job, &osd_options);
+    for _ in 0..overlay_workers.current_num_threads() {
+        let (compositor, overlay_frames_out, ready_frames_in, ffmpeg_sender) = (
+            compositor.clone(),
+            overlay_frames_out.clone(),
+            ready_frames_in.clone(),
+            from_ffmpeg_tx.clone(),
+        );
+        overlay_workers.spawn(move || {
+            tracing::info_span!("overlay worker").in_scope(|| {
+                run_overlay_worker(compositor, overlay_frames_out, ready_frames_in, ffmpeg_sender);
+            });
+        });
+    }
+    // The workers keep running until the decoder is done, dropping the pool doesn't wait for them
+    drop((overlay_frames_out, ready_frames_in));
 
     thread::Builder::new()
-        .name("Push ready frames to queue".into())
+        .name("Push frames to overlay workers".into())
         .spawn(move || {
-            tracing::info_span!("ready frames iter -> queue").in_scope(|| {
-                run_ready_frames_to_queue(frame_overlay_iter, ready_frames_queue_in);
+            tracing::info_span!("frame overlay iter -> workers").in_scope(|| {
+                run_overlay_frames_to_workers(frame_overlay_iter, slots_tx, overlay_frames_in);
             });
         })
-        .expect("Failed to spawn producer thread");
+        .map_err(|source| RenderError::SpawnThread {
+            thread: "producer",
+            source,
+        })?;
+
+    let encoder_error_tx = from_ffmpeg_tx.clone();
+    let live_view_tx = job.render_settings().rendering_live_view.then_some(frames_for_ui_tx);
 
     thread::Builder::new()
-        .name("Pop ready frames from queue to encoder".into())
+        .name("Write ready frames to encoder".into())
         .spawn(move || {
-            tracing::info_span!("ready frames queue -> encoder").in_scope(|| {
-                run_ready_frames_from_queue_to_encoder(ready_frames_queue_out, encoder_stdin, frames_for_ui_tx);
+            tracing::info_span!("ready frames -> encoder").in_scope(|| {
+                run_ready_frames_to_encoder(
+                    ready_frames_out,
+                    slots_rx,
+                    encoder_stdin,
+                    live_view_tx,
+                    encoder_error_tx,
+                );
             });
         })
-        .expect("Failed to spawn consumer thread");
+        .map_err(|source| RenderError::SpawnThread {
+            thread: "consumer",
+            source,
+        })?;
 
     // On yet another thread run the encoder to completion
     thread::Builder::new()
         .name("Encoder handler".into())
         .spawn(move || {
             tracing::info_span!("Encoder handler thread").in_scope(|| {
-                encoder_process
-                    .iter()
-                    .expect("Failed to create encoder iterator")
-                    .for_each(|event| handle_encoder_events(event, &from_ffmpeg_tx));
+                // Keep the encoder process alive for as long as its output is read
+                let _encoder_process = encoder_process;
+                encoder_iter.for_each(|event| handle_encoder_events(event, &from_ffmpeg_tx));
             });
         })
-        .expect("Failed to spawn encoder handler thread");
+        .map_err(|source| RenderError::SpawnThread {
+            thread: "encoder handler",
+            source,
+        })?;
 
     Ok((to_ffmpeg_tx, from_ffmpeg_rx, frames_for_ui_rx))
 }
 
-#[tracing::instrument(skip(ffmpeg_path))]
-pub fn spawn_decoder(ffmpeg_path: &PathBuf, input_video: &PathBuf) -> Result<FfmpegChild, io::Error> {
-    let decoder = FfmpegCommand::new_with_path(ffmpeg_path)
+/// Decodes the video to raw frames of the cropped and scaled picture of the output video, so the overlay is drawn at
+/// the final resolution instead of being scaled with the video.
+#[tracing::instrument(skip(ffmpeg_path, job, video_info))]
+pub fn spawn_decoder(
+    ffmpeg_path: &PathBuf,
+    job: &RenderJob,
+    video_info: &VideoInfo,
+) -> Result<FfmpegChild, RenderError> {
+    let mut decoder_command = FfmpegCommand::new_with_path(ffmpeg_path);
+    decoder_command
         .create_no_window()
         .args(["-hwaccel", "auto"])
-        .input(input_video.to_str().unwrap())
-        .args(["-f", "rawvideo", "-pix_fmt", "rgba", "-"])
-        .spawn()?;
-    Ok(decoder)
+        .args(job.time_range().input_args())
+        .input(path_str(job.input_video())?);
+    if let Some(picture_filter) = picture_filter(job, video_info) {
+        decoder_command.args(["-vf", &picture_filter]);
+    }
+    decoder_command
+        .args(["-f", "rawvideo", "-pix_fmt", frame_pix_fmt(job), "-"])
+        .spawn()
+        .map_err(|source| RenderError::SpawnFfmpeg {
+            process: "decoder",
+            source,
+        })
 }
 
-#[tracing::instrument(skip(ffmpeg_path))]
+#[tracing::instrument(skip(ffmpeg_path, job, video_info))]
 pub fn spawn_encoder(
     ffmpeg_path: &PathBuf,
-    width: u32,
-    height: u32,
-    frame_rate: f32,
-    time_base: u32,
-    bitrate_mbps: u32,
-    keep_quality: bool,
-    video_encoder: &Encoder,
-    output_video: &PathBuf,
-    upscale: bool,
-    rescale_to_4x3_aspect: bool,
-    chroma_key: Option<[f32; 4]>,
-    original_file: &PathBuf,
-) -> Result<FfmpegChild, io::Error> {
+    job: &RenderJob,
+    video_info: &VideoInfo,
+) -> Result<FfmpegChild, RenderError> {
+    // The frames are the picture, padding is added by the encoder
+    let (picture_width, picture_height) = job
+        .render_settings()
+        .output_geometry
+        .picture_size(video_info.width, video_info.height);
     let mut encoder_command = FfmpegCommand::new_with_path(ffmpeg_path);
-    let mut output_video = output_video.clone();
 
     encoder_command
         .create_no_window()
         .format("rawvideo")
-        .pix_fmt("rgba")
-        .size(width, height)
-        .rate(frame_rate)
+        .pix_fmt(frame_pix_fmt(job))
+        .size(picture_width, picture_height)
+        .rate(video_info.frame_rate)
         .input("-");
 
+    // The audio is cut to the same part of the video as the decoded frames
     encoder_command
-        .input(original_file.to_str().unwrap())
+        .args(job.time_range().input_args())
+        .input(path_str(job.input_video())?)
         .map("0")
         .map("1:a?")
         .codec_audio("copy");
 
-    if upscale {
-        if video_encoder.name.contains("nvenc") {
-            encoder_command.args([
-                "-vf",
-                "format=rgb24,hwupload_cuda,scale_cuda=-2:1440:3",
-                "-preset",
-                "p7",
-                "-tune",
-                "hq",
-            ]);
-        } else {
-            encoder_command.args(["-vf", "scale=-2:1440:flags=bicubic"]);
-        }
+    let filters = video_filters(job, video_info);
+    if !filters.is_empty() {
+        encoder_command.args(["-vf", &filters.join(",")]);
+    }
+    add_encoder_args(&mut encoder_command, job, video_info)?;
+
+    tracing::debug!(
+        "Encoder command: {}",
+        crate::util::command_to_cli(encoder_command.as_inner())
+    );
+
+    encoder_command.spawn().map_err(|source| RenderError::SpawnFfmpeg {
+        process: "encoder",
+        source,
+    })
+}
+
+/// Pixel format of the frames the overlay is put on. The video stays in yuv420p, the format the encoders take, unless
+/// the output keeps an alpha channel.
+pub(super) fn frame_pix_fmt(job: &RenderJob) -> &'static str {
+    if job.render_settings().use_chroma_key || job.encoder().name == "prores_ks" {
+        "rgba"
     } else {
-        if video_encoder.name.contains("nvenc") {
-            encoder_command.args(["-vf", "format=rgb24,hwupload_cuda", "-preset", "p7", "-tune", "hq"]);
+        YUV_PIX_FMT
+    }
+}
+
+/// Filters that crop and scale the input video to the picture. With an NVENC encoder the video is scaled on the GPU.
+pub(super) fn picture_filter(job: &RenderJob, video_info: &VideoInfo) -> Option<String> {
+    let geometry = &job.render_settings().output_geometry;
+    if job.encoder().name.contains("nvenc") {
+        geometry.cuda_picture_filter(video_info.width, video_info.height)
+    } else {
+        geometry.picture_filter(video_info.width, video_info.height)
+    }
+}
+
+/// Filters applied to the video with the overlay before it's encoded.
+pub(super) fn video_filters(job: &RenderJob, video_info: &VideoInfo) -> Vec<String> {
+    let mut filters = vec![];
+    if let Some(pad_filter) = job
+        .render_settings()
+        .output_geometry
+        .pad_filter(video_info.width, video_info.height)
+    {
+        filters.push(pad_filter);
+    }
+    if job.encoder().name.contains("nvenc") {
+        if frame_pix_fmt(job) != YUV_PIX_FMT {
+            filters.push("format=rgb24".into());
         }
+        filters.push("hwupload_cuda".into());
     }
+    filters
+}
+
+/// The slowest, highest quality preset for NVENC, unless the encoder options already pick a preset.
+fn quality_preset_args(encoder: &Encoder) -> &'static [&'static str] {
+    if encoder.name.contains("nvenc") && !encoder.has_preset() {
+        &["-preset", "p7", "-tune", "hq"]
+    } else {
+        &[]
+    }
+}
 
-    if rescale_to_4x3_aspect {
+/// Adds the encoder options and the output video to `command`.
+pub(super) fn add_encoder_args(
+    command: &mut FfmpegCommand,
+    job: &RenderJob,
+    video_info: &VideoInfo,
+) -> Result<(), RenderError> {
+    let render_settings = job.render_settings();
+    let video_encoder = job.encoder();
+
+    command.args(quality_preset_args(video_encoder));
+
+    if render_settings.rescale_to_4x3_aspect {
         // It will affect the aspect ratio stored at container level without affecting final video resolution.
         // Example ffprobe of a final video: "... 1280x720 [SAR 3:4 DAR 4:3] ...".
         // Such video will be played back the same way as if it really was 4:3.
-        encoder_command.args(["-aspect", "4:3"]);
+        command.args(["-aspect", "4:3"]);
     }
 
-    encoder_command.codec_video(&video_encoder.name);
-
-    if keep_quality {
-        // this will crash when encoder is not setup to support constant quality mode.
-        let args = &video_encoder.constant_quality_args.clone().unwrap();
-        encoder_command.args(args);
+    command.codec_video(&video_encoder.name);
+
+    if render_settings.keep_quality {
+        let args =
+            video_encoder
+                .constant_quality_args
+                .as_ref()
+                .ok_or_else(|| RenderError::ConstantQualityNotSupported {
+                    encoder: video_encoder.name.clone(),
+                })?;
+        command.args(args);
     } else {
-        encoder_command.args(["-b:v", &format!("{}M", bitrate_mbps)]);
+        command.args(["-b:v", &format!("{}M", render_settings.bitrate_mbps)]);
     }
 
-    encoder_command
+    command
         .args(&video_encoder.extra_args)
-        .args(["-video_track_timescale", time_base.to_string().as_str()]);
+        .args(["-video_track_timescale", video_info.time_base.to_string().as_str()]);
 
-    if &video_encoder.name == "prores_ks" {
-        output_video.set_extension("mov");
-    } else {
-        if chroma_key.is_none() && !video_encoder.name.contains("nvenc") {
-            encoder_command.pix_fmt("yuv420p");
-        }
+    // ProRes keeps the alpha channel
+    if video_encoder.name != "prores_ks" && !render_settings.use_chroma_key && !video_encoder.name.contains("nvenc") {
+        command.pix_fmt("yuv420p");
     }
 
-    encoder_command.overwrite().output(output_video.to_str().unwrap());
-
-    tracing::info!(
-        "✅✅✅✅✅✅✅ {}",
-        crate::util::command_to_cli(encoder_command.as_inner())
-    );
+    command.overwrite().output(path_str(job.output_video())?);
+    Ok(())
+}
 
-    let encoder = encoder_command.spawn()?;
-    Ok(encoder)
+pub(super) fn path_str(path: &Path) -> Result<&str, RenderError> {
+    path.to_str()
+        .ok_or_else(|| RenderError::NonUtf8Path(path.to_path_buf()))
 }
 
-fn handle_encoder_events(ffmpeg_event: FfmpegEvent, ffmpeg_sender: &Sender<FromFfmpegMessage>) {
+pub(super) fn handle_encoder_events(ffmpeg_event: FfmpegEvent, ffmpeg_sender: &Sender<FromFfmpegMessage>) {
     match ffmpeg_event {
         FfmpegEvent::ParsedVersion(version) => {
             println!("ffmpeg encoder >>> FFmpeg version: {:?}", version);
@@ -312,16 +460,20 @@ fn handle_encoder_events(ffmpeg_event: FfmpegEvent, ffmpeg_sender: &Sender<FromF
             println!("ffmpeg encoder >>> Duration: {:?}", duration);
         }
         FfmpegEvent::Progress(p) => {
-            ffmpeg_sender.send(FromFfmpegMessage::EncoderProgress(p)).unwrap();
+            ffmpeg_sender.send(FromFfmpegMessage::EncoderProgress(p)).ok();
         }
         FfmpegEvent::Log(level, e) => {
-            if level == LogLevel::Fatal
             // there are some fatal errors that ffmpeg considers normal errors
-            || e.contains("Error initializing output stream")
-            || e.contains("[error] Cannot load")
-            {
+            if e.contains("Error initializing output stream") || e.contains("[error] Cannot load") {
+                tracing::error!("ffmpeg encoder init error: {}", &e);
+                ffmpeg_sender
+                    .send(FromFfmpegMessage::Error(RenderError::EncoderInit(e)))
+                    .ok();
+            } else if level == LogLevel::Fatal {
                 tracing::error!("ffmpeg fatal error: {}", &e);
-                ffmpeg_sender.send(FromFfmpegMessage::EncoderFatalError(e)).unwrap();
+                ffmpeg_sender
+                    .send(FromFfmpegMessage::Error(RenderError::Encoder(e)))
+                    .ok();
             } else {
                 println!("ffmpeg encoder >>> {}", &e);
             }
@@ -337,14 +489,16 @@ fn handle_encoder_events(ffmpeg_event: FfmpegEvent, ffmpeg_sender: &Sender<FromF
 pub fn handle_decoder_events(ffmpeg_event: FfmpegEvent, ffmpeg_sender: &Sender<FromFfmpegMessage>) {
     match ffmpeg_event {
         FfmpegEvent::Progress(p) => {
-            ffmpeg_sender.send(FromFfmpegMessage::DecoderProgress(p)).unwrap();
+            ffmpeg_sender.send(FromFfmpegMessage::DecoderProgress(p)).ok();
         }
         FfmpegEvent::Done | FfmpegEvent::LogEOF => {
-            ffmpeg_sender.send(FromFfmpegMessage::DecoderFinished).unwrap();
+            ffmpeg_sender.send(FromFfmpegMessage::DecoderFinished).ok();
         }
         FfmpegEvent::Log(LogLevel::Fatal, e) => {
             tracing::error!("ffmpeg fatal error: {}", &e);
-            ffmpeg_sender.send(FromFfmpegMessage::DecoderFatalError(e)).unwrap();
+            ffmpeg_sender
+                .send(FromFfmpegMessage::Error(RenderError::Decoder(e)))
+                .ok();
         }
         FfmpegEvent::Log(LogLevel::Warning | LogLevel::Error, e) => {
             tracing::warn!("ffmpeg log: {}", e);
@@ -352,3 +506,83 @@ pub fn handle_decoder_events(ffmpeg_event: FfmpegEvent, ffmpeg_sender: &Sender<F
         _ => {}
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ffmpeg::Codec;
+
+    fn video_frame(value: u8) -> OutputVideoFrame {
+        OutputVideoFrame {
+            width: 1,
+            height: 1,
+            pix_fmt: "rgba".into(),
+            output_index: 0,
+            data: vec![value; 4],
+            frame_num: value.into(),
+            timestamp: 0.0,
+        }
+    }
+
+    #[test]
+    fn composited_frames_are_written_in_order() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let (slots_tx, slots_rx) = crossbeam_channel::unbounded();
+        for (index, frame) in [
+            (2, Some(video_frame(2))),
+            (0, Some(video_frame(0))),
+            (3, None),
+            (1, Some(video_frame(1))),
+        ] {
+            slots_tx.send(()).unwrap();
+            tx.send((index, frame)).unwrap();
+        }
+        drop(tx);
+
+        let mut encoder_input = vec![];
+        let (frame_to_ui_tx, _frame_to_ui_rx) = crossbeam_channel::bounded(1);
+        let (ffmpeg_sender, _ffmpeg_receiver) = crossbeam_channel::unbounded();
+        run_ready_frames_to_encoder(rx, slots_rx, &mut encoder_input, Some(frame_to_ui_tx), ffmpeg_sender);
+
+        assert_eq!(encoder_input, [[0; 4], [1; 4], [2; 4]].concat());
+        assert!(slots_tx.is_empty());
+    }
+
+    #[test]
+    fn frames_are_written_when_live_view_is_gone() {
+        let (tx, rx) = crossbeam_channel::unbounded();
+        let (slots_tx, slots_rx) = crossbeam_channel::unbounded();
+        for index in 0..3 {
+            slots_tx.send(()).unwrap();
+            tx.send((index, Some(video_frame(index as u8)))).unwrap();
+        }
+        drop(tx);
+
+        let mut encoder_input = vec![];
+        let (frame_to_ui_tx, frame_to_ui_rx) = crossbeam_channel::bounded(1);
+        drop(frame_to_ui_rx);
+        let (ffmpeg_sender, _ffmpeg_receiver) = crossbeam_channel::unbounded();
+        run_ready_frames_to_encoder(rx, slots_rx, &mut encoder_input, Some(frame_to_ui_tx), ffmpeg_sender);
+
+        assert_eq!(encoder_input, [[0; 4], [1; 4], [2; 4]].concat());
+    }
+
+    #[test]
+    fn fast_preset_replaces_the_nvenc_quality_preset() {
+        let mut encoder = Encoder {
+            name: "h264_nvenc".to_string(),
+            codec: Codec::H264,
+            hardware: true,
+            detected: true,
+            constant_quality_args: None,
+            extra_args: vec![],
+        };
+        assert_eq!(quality_preset_args(&encoder), ["-preset", "p7", "-tune", "hq"]);
+
+        encoder
+            .extra_args
+            .extend(encoder.fast_preset_args().iter().map(|arg| arg.to_string()));
+        assert_eq!(encoder.extra_args, ["-preset", "p1"]);
+        assert!(quality_preset_args(&encoder).is_empty());
+    }
+}
@@ -0,0 +1,407 @@
+use std::path::PathBuf;
+
+use serde::{Deserialize, Serialize};
+
+use super::{error::RenderJobError, Encoder, OutputResolution, RenderSettings, ScalingFilter, TimeRange, VideoInfo};
+use crate::{
+    font::FontFile,
+    osd::{OsdFile, OsdOptions},
+    srt::{SrtFile, SrtOptions},
+};
+
+/// Length of a preview clip made with [`RenderJob::preview_clip`].
+pub const PREVIEW_CLIP_SECS: f32 = 8.0;
+
+/// Preview clips are scaled down to fit in this size.
+const PREVIEW_CLIP_MAX_SIZE: (u32, u32) = (1280, 720);
+
+/// Bitrate of preview clips, enough to see the OSD at 720p.
+const PREVIEW_CLIP_BITRATE_MBPS: u32 = 8;
+
+/// Everything that is needed to render one video, validated and with the OSD, SRT and font files loaded.
+///
+/// Created with [`RenderJob::builder`]. Serializes to the paths and options it was built from so it can be saved as a
+/// job file, deserializing loads and validates the files again.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "RenderJobBuilder", into = "RenderJobBuilder")]
+pub struct RenderJob {
+    input_video: PathBuf,
+    output_video: PathBuf,
+    osd_file: Option<OsdFile>,
+    srt_file: Option<SrtFile>,
+    font_file: FontFile,
+    encoder: Encoder,
+    osd_options: OsdOptions,
+    srt_options: SrtOptions,
+    render_settings: RenderSettings,
+    time_range: TimeRange,
+}
+
+impl RenderJob {
+    pub fn builder() -> RenderJobBuilder {
+        RenderJobBuilder::default()
+    }
+
+    pub fn input_video(&self) -> &PathBuf {
+        &self.input_video
+    }
+
+    /// Can differ from the requested output video, e.g. ProRes is always written to a `.mov` file.
+    pub fn output_video(&self) -> &PathBuf {
+        &self.output_video
+    }
+
+    /// `None` when the OSD is not rendered.
+    pub fn osd_file(&self) -> Option<&OsdFile> {
+        self.osd_file.as_ref()
+    }
+
+    /// `None` when there is no SRT file or the SRT data is not rendered.
+    pub fn srt_file(&self) -> Option<&SrtFile> {
+        self.srt_file.as_ref()
+    }
+
+    pub fn font_file(&self) -> &FontFile {
+        &self.font_file
+    }
+
+    pub fn encoder(&self) -> &Encoder {
+        &self.encoder
+    }
+
+    pub fn osd_options(&self) -> &OsdOptions {
+        &self.osd_options
+    }
+
+    pub fn srt_options(&self) -> &SrtOptions {
+        &self.srt_options
+    }
+
+    pub fn render_settings(&self) -> &RenderSettings {
+        &self.render_settings
+    }
+
+    pub fn time_range(&self) -> &TimeRange {
+        &self.time_range
+    }
+
+    /// Number of frames of `video_info`'s video that are rendered.
+    pub fn frame_count(&self, video_info: &VideoInfo) -> u32 {
+        self.time_range.frame_count(video_info)
+    }
+
+    /// A cheap render of the [`PREVIEW_CLIP_SECS`] around `position_secs` of the video to check the position, size and
+    /// sync of the overlay in motion before rendering the whole video. The clip is scaled down to at most 720p, encoded
+    /// with a fast preset at a low bitrate and written to a temporary file that is replaced by the next preview clip.
+    pub fn preview_clip(&self, video_info: &VideoInfo, position_secs: f32) -> RenderJob {
+        let mut job = self.clone();
+        job.time_range = TimeRange::around(position_secs, PREVIEW_CLIP_SECS, video_info.duration);
+
+        let extension = if job.encoder.name == "prores_ks" { "mov" } else { "mp4" };
+        job.output_video =
+            std::env::temp_dir().join(format!("walksnail-osd-tool-preview-{}.{extension}", std::process::id()));
+        job.encoder
+            .extra_args
+            .extend(job.encoder.fast_preset_args().iter().map(|arg| arg.to_string()));
+
+        let render_settings = &mut job.render_settings;
+        render_settings.keep_quality = false;
+        render_settings.bitrate_mbps = PREVIEW_CLIP_BITRATE_MBPS;
+        // Show the overlay on the video, a chroma key background can't be checked against the video
+        render_settings.use_chroma_key = false;
+
+        // Keep the aspect ratio of the output video and its padding, only smaller
+        let geometry = &mut render_settings.output_geometry;
+        let (width, height) = geometry.frame_size(video_info.width, video_info.height);
+        let (max_width, max_height) = PREVIEW_CLIP_MAX_SIZE;
+        let scale = (max_width as f32 / width as f32).min(max_height as f32 / height as f32);
+        if scale < 1.0 {
+            geometry.resolution = OutputResolution::Custom {
+                width: (width as f32 * scale).round() as u32,
+                height: (height as f32 * scale).round() as u32,
+            };
+            geometry.scaling_filter = ScalingFilter::Bilinear;
+        }
+        job
+    }
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct RenderJobBuilder {
+    input_video: Option<PathBuf>,
+    output_video: Option<PathBuf>,
+    osd_file: Option<PathBuf>,
+    srt_file: Option<PathBuf>,
+    font_file: Option<PathBuf>,
+    encoder: Option<Encoder>,
+    osd_options: OsdOptions,
+    srt_options: SrtOptions,
+    render_settings: RenderSettings,
+    time_range: TimeRange,
+    /// Files that are already open, used instead of opening the paths above again.
+    #[serde(skip)]
+    loaded_osd_file: Option<OsdFile>,
+    #[serde(skip)]
+    loaded_srt_file: Option<SrtFile>,
+    #[serde(skip)]
+    loaded_font_file: Option<FontFile>,
+}
+
+impl RenderJobBuilder {
+    pub fn input_video(mut self, input_video: PathBuf) -> Self {
+        self.input_video = Some(input_video);
+        self
+    }
+
+    pub fn output_video(mut self, output_video: PathBuf) -> Self {
+        self.output_video = Some(output_video);
+        self
+    }
+
+    pub fn osd_file(mut self, osd_file: Option<PathBuf>) -> Self {
+        self.osd_file = osd_file;
+        self
+    }
+
+    pub fn srt_file(mut self, srt_file: Option<PathBuf>) -> Self {
+        self.srt_file = srt_file;
+        self
+    }
+
+    pub fn font_file(mut self, font_file: PathBuf) -> Self {
+        self.font_file = Some(font_file);
+        self
+    }
+
+    /// Renders with an OSD file that is already open, e.g. the one shown in the app, instead of reading it again.
+    pub fn loaded_osd_file(mut self, osd_file: Option<OsdFile>) -> Self {
+        self.osd_file = osd_file.as_ref().map(|osd_file| osd_file.file_path.clone());
+        self.loaded_osd_file = osd_file;
+        self
+    }
+
+    /// Renders with an SRT file that is already open instead of reading it again.
+    pub fn loaded_srt_file(mut self, srt_file: Option<SrtFile>) -> Self {
+        self.srt_file = srt_file.as_ref().map(|srt_file| srt_file.file_path.clone());
+        self.loaded_srt_file = srt_file;
+        self
+    }
+
+    /// Renders with a font file that is already open instead of reading it again.
+    pub fn loaded_font_file(mut self, font_file: FontFile) -> Self {
+        self.font_file = Some(font_file.file_path.clone());
+        self.loaded_font_file = Some(font_file);
+        self
+    }
+
+    pub fn encoder(mut self, encoder: Encoder) -> Self {
+        self.encoder = Some(encoder);
+        self
+    }
+
+    pub fn osd_options(mut self, osd_options: OsdOptions) -> Self {
+        self.osd_options = osd_options;
+        self
+    }
+
+    pub fn srt_options(mut self, srt_options: SrtOptions) -> Self {
+        self.srt_options = srt_options;
+        self
+    }
+
+    pub fn render_settings(mut self, render_settings: RenderSettings) -> Self {
+        self.render_settings = render_settings;
+        self
+    }
+
+    pub fn time_range(mut self, time_range: TimeRange) -> Self {
+        self.time_range = time_range;
+        self
+    }
+
+    /// Checks the job and loads the OSD, SRT and font files that weren't passed in already loaded.
+    #[tracing::instrument(ret, err)]
+    pub fn build(self) -> Result<RenderJob, RenderJobError> {
+        let input_video = self.input_video.ok_or(RenderJobError::NoInputVideo)?;
+        let mut output_video = self.output_video.ok_or(RenderJobError::NoOutputVideo)?;
+        let encoder = self.encoder.ok_or(RenderJobError::NoEncoder)?;
+
+        if encoder.name == "prores_ks" {
+            output_video.set_extension("mov");
+        }
+        if output_video == input_video {
+            return Err(RenderJobError::OutputIsInput(output_video));
+        }
+
+        if self.render_settings.keep_quality && encoder.constant_quality_args.is_none() {
+            return Err(RenderJobError::ConstantQualityNotSupported { encoder: encoder.name });
+        }
+
+        if let Some(start) = self.time_range.start.filter(|start| *start < 0.0) {
+            return Err(RenderJobError::NegativeStart(start));
+        }
+        if let (Some(start), Some(end)) = (self.time_range.start, self.time_range.end) {
+            if start >= end {
+                return Err(RenderJobError::EmptyTimeRange { start, end });
+            }
+        }
+
+        // A loaded file is only used while the path still points to it
+        let font_file = match (self.font_file, self.loaded_font_file) {
+            (Some(path), Some(font_file)) if font_file.file_path == path => font_file,
+            (Some(path), _) => FontFile::open(path)?,
+            (None, _) => return Err(RenderJobError::NoFontFile),
+        };
+
+        let osd_file = match (self.osd_file, self.loaded_osd_file) {
+            _ if self.osd_options.no_osd => None,
+            (Some(path), Some(osd_file)) if osd_file.file_path == path => Some(osd_file),
+            (Some(path), _) => Some(OsdFile::open_with_recovery(path)?),
+            (None, _) => return Err(RenderJobError::NoOsdFile),
+        };
+
+        let srt_file = match (self.srt_file, self.loaded_srt_file) {
+            _ if self.srt_options.no_srt => None,
+            (Some(path), Some(srt_file)) if srt_file.file_path == path => Some(srt_file),
+            (Some(path), _) => Some(SrtFile::open(path)?),
+            (None, _) => None,
+        };
+
+        Ok(RenderJob {
+            input_video,
+            output_video,
+            osd_file,
+            srt_file,
+            font_file,
+            encoder,
+            osd_options: self.osd_options,
+            srt_options: self.srt_options,
+            render_settings: self.render_settings,
+            time_range: self.time_range,
+        })
+    }
+}
+
+impl TryFrom<RenderJobBuilder> for RenderJob {
+    type Error = RenderJobError;
+
+    fn try_from(builder: RenderJobBuilder) -> Result<Self, Self::Error> {
+        builder.build()
+    }
+}
+
+impl From<RenderJob> for RenderJobBuilder {
+    fn from(job: RenderJob) -> Self {
+        Self {
+            input_video: Some(job.input_video),
+            output_video: Some(job.output_video),
+            osd_file: job.osd_file.map(|f| f.file_path),
+            srt_file: job.srt_file.map(|f| f.file_path),
+            font_file: Some(job.font_file.file_path),
+            encoder: Some(job.encoder),
+            osd_options: job.osd_options,
+            srt_options: job.srt_options,
+            render_settings: job.render_settings,
+            time_range: job.time_range,
+            ..Default::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+
+    use claims::assert_matches;
+
+    use super::*;
+    use crate::ffmpeg::Codec;
+
+    fn encoder(name: &str, constant_quality: bool) -> Encoder {
+        Encoder {
+            name: name.to_string(),
+            codec: Codec::H264,
+            hardware: false,
+            detected: true,
+            constant_quality_args: constant_quality.then(|| vec!["-crf".to_string(), "23".to_string()]),
+            extra_args: vec![],
+        }
+    }
+
+    fn builder() -> RenderJobBuilder {
+        RenderJob::builder()
+            .input_video("DVR/AvatarG0001.mov".into())
+            .output_video("DVR/AvatarG0001_with_osd.mp4".into())
+            .osd_file(Some("DVR/AvatarG0001.osd".into()))
+            .font_file("fonts/WS_BFx4_Nexus_Moonlight_2160p.png".into())
+    }
+
+    #[test]
+    fn prores_output_is_mov() {
+        let job = builder()
+            .output_video("DVR/AvatarG0001.mp4".into())
+            .encoder(encoder("prores_ks", true))
+            .build();
+        assert_matches!(job, Err(RenderJobError::OutputIsInput(path)) if path == Path::new("DVR/AvatarG0001.mov"));
+    }
+
+    #[test]
+    fn constant_quality_needs_support_from_encoder() {
+        let render_settings = RenderSettings {
+            keep_quality: true,
+            ..Default::default()
+        };
+        let job = builder()
+            .encoder(encoder("h264_videotoolbox", false))
+            .render_settings(render_settings)
+            .build();
+        assert_matches!(job, Err(RenderJobError::ConstantQualityNotSupported { encoder }) if encoder == "h264_videotoolbox");
+    }
+
+    #[test]
+    fn time_range_must_not_be_empty() {
+        let job = builder()
+            .encoder(encoder("libx264", true))
+            .time_range(TimeRange {
+                start: Some(40.0),
+                end: Some(10.0),
+            })
+            .build();
+        assert_matches!(job, Err(RenderJobError::EmptyTimeRange { .. }));
+    }
+
+    #[test]
+    fn loaded_files_are_not_opened_again() {
+        // 24x36 glyphs, 256 of them in a single column
+        let font_path = std::env::temp_dir().join(format!("render-job-font-{}.png", std::process::id()));
+        image::RgbaImage::new(24, 36 * 256).save(&font_path).unwrap();
+        let font_file = FontFile::open(font_path.clone()).unwrap();
+        std::fs::remove_file(&font_path).unwrap();
+
+        let job = builder()
+            .encoder(encoder("libx264", true))
+            .osd_options(OsdOptions {
+                no_osd: true,
+                ..Default::default()
+            })
+            .loaded_font_file(font_file)
+            .build()
+            .unwrap();
+        assert_eq!(job.font_file().file_path, font_path);
+
+        // Once the path changes the loaded file isn't used anymore
+        let job = builder()
+            .encoder(encoder("libx264", true))
+            .loaded_font_file(job.font_file().clone())
+            .font_file(font_path.with_file_name("other-font.png"))
+            .build();
+        assert_matches!(job, Err(RenderJobError::FontFile { .. }));
+    }
+
+    #[test]
+    fn missing_font_file_is_reported() {
+        let job = builder().encoder(encoder("libx264", true)).build();
+        assert_matches!(job, Err(RenderJobError::FontFile { .. }));
+    }
+}
@@ -1,16 +1,53 @@
-use serde::{Deserialize, Serialize};
+use std::fmt::Display;
 
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{OutputGeometry, OutputResolution};
+
+/// How the OSD and SRT data get on the video.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum RenderMode {
+    /// Decode the video to raw frames, draw the overlay on every frame and pipe them to the encoder. Shows the frames
+    /// in the live view.
+    #[default]
+    Composite,
+    /// Draw only the overlay layers, once per OSD or SRT frame, and let a single ffmpeg process overlay them on the
+    /// video. The decoded frames don't go through the app, there's no live view.
+    FilterGraph,
+}
+
+impl Display for RenderMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                RenderMode::Composite => "Composite frames",
+                RenderMode::FilterGraph => "ffmpeg overlay filter",
+            }
+        )
+    }
+}
+
+// Deserialized through the impl below, which moves the settings of older versions to where they are now
 #[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(default, remote = "Self")]
 pub struct RenderSettings {
     pub selected_encoder_idx: usize,
     pub show_undetected_encoders: bool,
     pub bitrate_mbps: u32,
     pub keep_quality: bool,
-    pub upscale: bool,
+    pub output_geometry: OutputGeometry,
     pub rescale_to_4x3_aspect: bool,
     pub rendering_live_view: bool,
     pub use_chroma_key: bool,
     pub chroma_key: [f32; 4],
+    pub queue_concurrency: usize,
+    pub render_mode: RenderMode,
+    /// Deprecated, replaced by the 1440p [`OutputGeometry::resolution`]. Still read from the configs and job files of
+    /// older versions, never written.
+    #[serde(skip_serializing)]
+    pub(crate) upscale: bool,
 }
 
 impl Default for RenderSettings {
@@ -20,11 +57,46 @@ impl Default for RenderSettings {
             show_undetected_encoders: false,
             bitrate_mbps: 40,
             keep_quality: true,
-            upscale: false,
+            output_geometry: OutputGeometry::default(),
             rescale_to_4x3_aspect: false,
             rendering_live_view: true,
             use_chroma_key: false,
             chroma_key: [1.0 / 255.0, 177.0 / 255.0, 64.0 / 255.0, 1.0],
+            queue_concurrency: 1,
+            render_mode: RenderMode::default(),
+            upscale: false,
         }
     }
 }
+
+impl<'de> Deserialize<'de> for RenderSettings {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let mut settings = RenderSettings::deserialize(deserializer)?;
+        if std::mem::take(&mut settings.upscale) {
+            settings.output_geometry.resolution = OutputResolution::P1440;
+        }
+        Ok(settings)
+    }
+}
+
+impl Serialize for RenderSettings {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        RenderSettings::serialize(self, serializer)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn upscale_from_older_versions_is_1440p() {
+        let settings: RenderSettings = serde_json::from_str(r#"{"bitrate_mbps": 20, "upscale": true}"#).unwrap();
+        assert_eq!(settings.output_geometry.resolution, OutputResolution::P1440);
+        assert_eq!(settings.bitrate_mbps, 20);
+        assert!(!serde_json::to_string(&settings).unwrap().contains("upscale"));
+
+        let settings: RenderSettings = serde_json::from_str(r#"{"upscale": false}"#).unwrap();
+        assert_eq!(settings.output_geometry.resolution, OutputResolution::Source);
+    }
+}
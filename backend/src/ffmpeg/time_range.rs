@@ -0,0 +1,133 @@
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use super::VideoInfo;
+
+/// Part of the input video that is rendered, in seconds from the start of the video.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TimeRange {
+    /// `None` renders from the start of the video.
+    pub start: Option<f32>,
+    /// `None` renders until the end of the video.
+    pub end: Option<f32>,
+}
+
+impl TimeRange {
+    /// The `length_secs` long part of a video that is `video_duration` long centered on `position_secs`, moved to stay
+    /// inside the video.
+    pub fn around(position_secs: f32, length_secs: f32, video_duration: Duration) -> Self {
+        let video_secs = video_duration.as_secs_f32();
+        let start = (position_secs - length_secs / 2.0).clamp(0.0, (video_secs - length_secs).max(0.0));
+        Self {
+            start: Some(start),
+            end: Some((start + length_secs).min(video_secs)),
+        }
+    }
+
+    pub fn is_whole_video(&self) -> bool {
+        self.start.is_none() && self.end.is_none()
+    }
+
+    /// Position of the first rendered frame in the input video. The decoded frames' timestamps start at zero again,
+    /// this is added to them to look up the OSD and SRT data.
+    pub fn start_secs(&self) -> f32 {
+        self.start.unwrap_or(0.0)
+    }
+
+    /// Duration of the rendered part of a video that is `video_duration` long.
+    pub fn duration(&self, video_duration: Duration) -> Duration {
+        let video_secs = video_duration.as_secs_f32();
+        let end = self.end.map_or(video_secs, |end| end.min(video_secs));
+        Duration::from_secs_f32((end - self.start_secs()).max(0.0))
+    }
+
+    /// Number of frames that are rendered, the progress of a render is measured against it.
+    pub fn frame_count(&self, video_info: &VideoInfo) -> u32 {
+        if self.is_whole_video() {
+            video_info.total_frames
+        } else {
+            (self.duration(video_info.duration).as_secs_f32() * video_info.frame_rate) as u32
+        }
+    }
+
+    /// ffmpeg options that go before an input to read only this part of it.
+    pub fn input_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let Some(start) = self.start {
+            args.extend(["-ss".to_string(), format!("{start:.3}")]);
+        }
+        if let Some(end) = self.end {
+            args.extend(["-to".to_string(), format!("{end:.3}")]);
+        }
+        args
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn frames_are_counted_in_the_trimmed_part() {
+        let video_info = VideoInfo {
+            width: 1920,
+            height: 1080,
+            frame_rate: 60.0,
+            time_base: 90000,
+            bitrate: 20_000_000,
+            duration: Duration::from_secs(360),
+            total_frames: 21600,
+        };
+        assert_eq!(TimeRange::default().frame_count(&video_info), 21600);
+        assert!(TimeRange::default().input_args().is_empty());
+
+        let time_range = TimeRange {
+            start: Some(125.5),
+            end: Some(165.5),
+        };
+        assert_eq!(time_range.frame_count(&video_info), 2400);
+        assert_eq!(time_range.input_args(), ["-ss", "125.500", "-to", "165.500"]);
+
+        // The end is capped at the end of the video
+        let time_range = TimeRange {
+            start: Some(350.0),
+            end: Some(400.0),
+        };
+        assert_eq!(time_range.frame_count(&video_info), 600);
+    }
+
+    #[test]
+    fn window_stays_inside_the_video() {
+        let duration = Duration::from_secs(60);
+        assert_eq!(
+            TimeRange::around(30.0, 8.0, duration),
+            TimeRange {
+                start: Some(26.0),
+                end: Some(34.0)
+            }
+        );
+        assert_eq!(
+            TimeRange::around(1.0, 8.0, duration),
+            TimeRange {
+                start: Some(0.0),
+                end: Some(8.0)
+            }
+        );
+        assert_eq!(
+            TimeRange::around(59.0, 8.0, duration),
+            TimeRange {
+                start: Some(52.0),
+                end: Some(60.0)
+            }
+        );
+        assert_eq!(
+            TimeRange::around(2.0, 8.0, Duration::from_secs(5)),
+            TimeRange {
+                start: Some(0.0),
+                end: Some(5.0)
+            }
+        );
+    }
+}
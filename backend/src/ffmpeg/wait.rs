@@ -0,0 +1,128 @@
+use std::time::Duration;
+
+use crossbeam_channel::{Receiver, RecvTimeoutError, Sender};
+
+use super::{FromFfmpegMessage, RenderError, ToFfmpegMessage};
+
+/// How often a render checks whether it was cancelled while ffmpeg is quiet.
+const CANCEL_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// How a render ended, see [`wait_for_render`].
+#[derive(Debug)]
+pub enum RenderOutcome {
+    Completed,
+    Cancelled,
+    /// The first error of the render.
+    Failed(RenderError),
+    /// ffmpeg stopped before the end of the video without reporting an error.
+    Incomplete,
+}
+
+/// Waits for a render started with [`start_video_render`](super::start_video_render) to end, passing every message to
+/// `on_message` to show the progress and warnings. The render is stopped after the first error, once the encoder is
+/// done and once `is_cancelled` returns `true`.
+pub fn wait_for_render(
+    to_ffmpeg_tx: &Sender<ToFfmpegMessage>,
+    from_ffmpeg_rx: &Receiver<FromFfmpegMessage>,
+    is_cancelled: impl Fn() -> bool,
+    mut on_message: impl FnMut(&FromFfmpegMessage),
+) -> RenderOutcome {
+    let mut error = None;
+    let mut decoder_finished = false;
+    let mut cancelled = false;
+    let mut stop = false;
+    let mut stop_sent = false;
+
+    loop {
+        match from_ffmpeg_rx.recv_timeout(CANCEL_POLL_INTERVAL) {
+            Ok(message) => {
+                on_message(&message);
+                match message {
+                    FromFfmpegMessage::DecoderFinished => decoder_finished = true,
+                    FromFfmpegMessage::Error(e) => {
+                        error.get_or_insert(e);
+                        stop = true;
+                    }
+                    // Also stops the decoder in case it is still running after the encoder went away
+                    FromFfmpegMessage::EncoderFinished => stop = true,
+                    _ => {}
+                }
+            }
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => break,
+        }
+
+        if !cancelled && is_cancelled() {
+            cancelled = true;
+            stop = true;
+        }
+        // Only ask once, the decoder can't be stopped twice
+        if stop && !stop_sent {
+            to_ffmpeg_tx.send(ToFfmpegMessage::AbortRender).ok();
+            stop_sent = true;
+        }
+    }
+
+    match error {
+        Some(e) => RenderOutcome::Failed(e),
+        None if cancelled => RenderOutcome::Cancelled,
+        None if !decoder_finished => RenderOutcome::Incomplete,
+        None => RenderOutcome::Completed,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use claims::assert_matches;
+
+    use super::*;
+
+    /// Waits for a render that sent `messages` and has ended, returns the outcome and how often it was stopped.
+    fn wait(messages: Vec<FromFfmpegMessage>, cancelled: bool) -> (RenderOutcome, usize) {
+        let (to_ffmpeg_tx, to_ffmpeg_rx) = crossbeam_channel::unbounded();
+        let (from_ffmpeg_tx, from_ffmpeg_rx) = crossbeam_channel::unbounded();
+        let message_count = messages.len();
+        for message in messages {
+            from_ffmpeg_tx.send(message).unwrap();
+        }
+        drop(from_ffmpeg_tx);
+        let mut received = 0;
+        let outcome = wait_for_render(&to_ffmpeg_tx, &from_ffmpeg_rx, || cancelled, |_| received += 1);
+        assert_eq!(received, message_count);
+        (outcome, to_ffmpeg_rx.len())
+    }
+
+    #[test]
+    fn render_is_stopped_once_after_the_first_error() {
+        let (outcome, stops) = wait(
+            vec![
+                FromFfmpegMessage::Error(RenderError::Decoder("first".into())),
+                FromFfmpegMessage::Error(RenderError::Encoder("second".into())),
+                FromFfmpegMessage::EncoderFinished,
+            ],
+            false,
+        );
+        assert_matches!(outcome, RenderOutcome::Failed(RenderError::Decoder(e)) if e == "first");
+        assert_eq!(stops, 1);
+    }
+
+    #[test]
+    fn render_ends_when_the_decoder_and_encoder_finish() {
+        let (outcome, stops) = wait(
+            vec![FromFfmpegMessage::DecoderFinished, FromFfmpegMessage::EncoderFinished],
+            false,
+        );
+        assert_matches!(outcome, RenderOutcome::Completed);
+        assert_eq!(stops, 1);
+
+        let (outcome, _) = wait(vec![FromFfmpegMessage::EncoderFinished], false);
+        assert_matches!(outcome, RenderOutcome::Incomplete);
+    }
+
+    #[test]
+    fn cancelled_render_is_stopped() {
+        let (outcome, stops) = wait(vec![FromFfmpegMessage::EncoderFinished], true);
+        assert_matches!(outcome, RenderOutcome::Cancelled);
+        assert_eq!(stops, 1);
+    }
+}
@@ -1,43 +1,12 @@
-use std::{cell::RefCell, collections::HashMap, path::PathBuf};
+use std::{path::PathBuf, sync::Arc};
 
 use derivative::Derivative;
 use image::{imageops::FilterType, io::Reader, DynamicImage, GenericImageView, ImageBuffer, Rgba, RgbaImage};
 
-// Cache for resized glyphs with size limit (simplified key since size is constant per session)
-#[allow(clippy::type_complexity)]
-#[derive(Derivative, Clone, Debug)]
-pub struct ImageCache {
-    cache: RefCell<HashMap<usize, ImageBuffer<Rgba<u8>, Vec<u8>>>>,
-    max_size: usize,
-}
-
-impl ImageCache {
-    pub fn new(max_size: usize) -> Self {
-        ImageCache {
-            cache: RefCell::new(HashMap::new()),
-            max_size,
-        }
-    }
-
-    pub fn get(&self, index: usize) -> Option<ImageBuffer<Rgba<u8>, Vec<u8>>> {
-        self.cache.borrow().get(&index).cloned()
-    }
-
-    pub fn insert(&self, index: usize, image: ImageBuffer<Rgba<u8>, Vec<u8>>) {
-        let mut cache = self.cache.borrow_mut();
-
-        // If cache is full, don't add new entries (simple bounded cache)
-        if cache.len() >= self.max_size {
-            return;
-        }
-
-        cache.insert(index, image);
-    }
-}
-
 use super::{
     dimensions::{detect_font_character_size, CharacterSizeClass, FontType},
     error::FontFileError,
+    glyph_cache::{GlyphCache, GlyphKey, MAX_CACHE_BYTES},
 };
 use crate::util::Dimension;
 
@@ -49,8 +18,9 @@ pub struct FontFile {
     pub font_type: FontType,
     pub font_character_size: Dimension<u32>,
     #[derivative(Debug = "ignore")]
-    characters: Vec<RgbaImage>,
-    cache: ImageCache,
+    characters: Arc<[RgbaImage]>,
+    #[derivative(Debug = "ignore")]
+    cache: GlyphCache,
 }
 
 impl FontFile {
@@ -64,31 +34,34 @@ impl FontFile {
         let characters = split_characters(&font_image, &font_character_size, &font_type);
         let character_count = characters.len() as u32;
 
-        // Create cache for resized glyphs (grows as needed, bounded)
-        let cache = ImageCache::new(256); // Reduced cache size to prevent memory bloat
-
         Ok(Self {
             file_path: path,
             font_type,
             font_character_size,
-            characters,
-            cache,
+            characters: characters.into(),
+            cache: GlyphCache::new(MAX_CACHE_BYTES),
             character_count,
         })
     }
 
+    /// The glyph at `index` scaled to `desired_size` times the size class. Resized glyphs are cached.
     pub fn get_character(
         &self,
         index: usize,
         size_class: &CharacterSizeClass,
         desired_size: Dimension<u32>,
-    ) -> Option<ImageBuffer<Rgba<u8>, Vec<u8>>> {
-        // Check cache first (simplified key since size is constant per session)
-        if let Some(cached_image) = self.cache.get(index) {
-            return Some(cached_image);
-        }
+    ) -> Option<Arc<RgbaImage>> {
+        self.get_character_filtered(index, size_class, desired_size, FilterType::Triangle)
+    }
 
-        // Cache miss - resize from original glyph
+    /// Like [`FontFile::get_character`], resizing with `filter`.
+    pub fn get_character_filtered(
+        &self,
+        index: usize,
+        size_class: &CharacterSizeClass,
+        desired_size: Dimension<u32>,
+        filter: FilterType,
+    ) -> Option<Arc<RgbaImage>> {
         let final_size = Dimension {
             width: ((desired_size.width as f32) * size_class.multiplier()).round() as u32,
             height: ((desired_size.height as f32) * size_class.multiplier()).round() as u32,
@@ -97,20 +70,20 @@ impl FontFile {
         // this allows us to use single color fonts for multicolor osd files.
         let wrapped_char_index = index % self.character_count as usize;
 
+        let key = GlyphKey::new(wrapped_char_index, final_size.clone(), filter);
+        if let Some(cached_image) = self.cache.get(&key) {
+            return Some(cached_image);
+        }
+
         self.characters.get(wrapped_char_index).map(|original_image| {
             let resized_image = if final_size != self.font_character_size {
-                image::imageops::resize(
-                    original_image,
-                    final_size.width,
-                    final_size.height,
-                    FilterType::Triangle,
-                )
+                image::imageops::resize(original_image, final_size.width, final_size.height, filter)
             } else {
                 original_image.clone()
             };
 
-            // Cache the resized image (simplified key)
-            self.cache.insert(index, resized_image.clone());
+            let resized_image = Arc::new(resized_image);
+            self.cache.insert(key, resized_image.clone());
             resized_image
         })
     }
@@ -0,0 +1,145 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+};
+
+use image::{imageops::FilterType, RgbaImage};
+
+use crate::util::Dimension;
+
+/// Resized glyphs of a font are kept up to this many bytes. That's all glyphs of a four page font at 4K plus some room
+/// for a preview at a different size.
+pub(super) const MAX_CACHE_BYTES: usize = 64 * 1024 * 1024;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(super) struct GlyphKey {
+    index: usize,
+    width: u32,
+    height: u32,
+    filter: u8,
+}
+
+impl GlyphKey {
+    pub fn new(index: usize, size: Dimension<u32>, filter: FilterType) -> Self {
+        let filter = match filter {
+            FilterType::Nearest => 0,
+            FilterType::Triangle => 1,
+            FilterType::CatmullRom => 2,
+            FilterType::Gaussian => 3,
+            FilterType::Lanczos3 => 4,
+        };
+        Self {
+            index,
+            width: size.width,
+            height: size.height,
+            filter,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct CacheState {
+    glyphs: HashMap<GlyphKey, (Arc<RgbaImage>, u64)>,
+    bytes: usize,
+    /// Incremented on every access, the glyph with the oldest tick is evicted first.
+    tick: u64,
+}
+
+/// Least recently used cache of resized glyphs, shared by all clones of a [`FontFile`](super::FontFile) and the
+/// threads using them.
+#[derive(Debug, Clone)]
+pub(super) struct GlyphCache {
+    state: Arc<Mutex<CacheState>>,
+    max_bytes: usize,
+}
+
+impl GlyphCache {
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            state: Default::default(),
+            max_bytes,
+        }
+    }
+
+    pub fn get(&self, key: &GlyphKey) -> Option<Arc<RgbaImage>> {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.tick += 1;
+        let tick = state.tick;
+        state.glyphs.get_mut(key).map(|(glyph, last_used)| {
+            *last_used = tick;
+            glyph.clone()
+        })
+    }
+
+    pub fn insert(&self, key: GlyphKey, glyph: Arc<RgbaImage>) {
+        let mut state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.tick += 1;
+        let tick = state.tick;
+        state.bytes += glyph.len();
+        if let Some((replaced, _)) = state.glyphs.insert(key, (glyph, tick)) {
+            state.bytes -= replaced.len();
+        }
+
+        while state.bytes > self.max_bytes {
+            let Some(oldest) = state
+                .glyphs
+                .iter()
+                .min_by_key(|(_, (_, last_used))| *last_used)
+                .map(|(key, _)| *key)
+            else {
+                break;
+            };
+            if let Some((evicted, _)) = state.glyphs.remove(&oldest) {
+                state.bytes -= evicted.len();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn glyph() -> Arc<RgbaImage> {
+        Arc::new(RgbaImage::new(2, 2))
+    }
+
+    fn key(index: usize, width: u32) -> GlyphKey {
+        GlyphKey::new(index, Dimension::new(width, 2), FilterType::Triangle)
+    }
+
+    #[test]
+    fn glyphs_are_cached_per_size() {
+        let cache = GlyphCache::new(MAX_CACHE_BYTES);
+        cache.insert(key(1, 2), glyph());
+        assert!(cache.get(&key(1, 2)).is_some());
+        assert!(cache.get(&key(1, 3)).is_none());
+        assert!(cache
+            .get(&GlyphKey::new(1, Dimension::new(2, 2), FilterType::Nearest))
+            .is_none());
+    }
+
+    #[test]
+    fn least_recently_used_glyph_is_evicted() {
+        // Room for two 2x2 glyphs
+        let cache = GlyphCache::new(32);
+        cache.insert(key(1, 2), glyph());
+        cache.insert(key(2, 2), glyph());
+        cache.get(&key(1, 2));
+        cache.insert(key(3, 2), glyph());
+
+        assert!(cache.get(&key(1, 2)).is_some());
+        assert!(cache.get(&key(2, 2)).is_none());
+        assert!(cache.get(&key(3, 2)).is_some());
+    }
+
+    #[test]
+    fn clones_share_the_cache() {
+        let cache = GlyphCache::new(MAX_CACHE_BYTES);
+        let clone = cache.clone();
+        std::thread::spawn(move || clone.insert(key(1, 2), glyph()))
+            .join()
+            .unwrap();
+        assert!(cache.get(&key(1, 2)).is_some());
+    }
+}
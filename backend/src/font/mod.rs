@@ -1,6 +1,7 @@
 mod dimensions;
 mod error;
 mod font_file;
+mod glyph_cache;
 
 pub use dimensions::{CharacterSizeClass, FontType};
 pub use error::FontFileError;
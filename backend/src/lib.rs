@@ -1,4 +1,3 @@
-#![allow(clippy::too_many_arguments)]
 #![allow(clippy::collapsible_else_if)]
 
 pub mod config;
@@ -6,6 +5,7 @@ pub mod ffmpeg;
 pub mod font;
 pub mod osd;
 pub mod overlay;
+pub mod queue;
 pub mod srt;
 pub mod util;
 
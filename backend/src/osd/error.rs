@@ -1,12 +1,48 @@
 use thiserror::Error;
 
+use super::GridSize;
+
 #[derive(Error, Debug)]
 pub enum OsdFileError {
     // #[error("Unknown FC firmware: {0}")]
     // UnknownFcFirmware(String),
+    #[error("OSD file is too short to contain a header")]
+    MissingHeader,
+
+    #[error("OSD file contains no frames")]
+    NoFrames,
+
+    #[error("Incomplete frame with index {index}, only {bytes} bytes")]
+    IncompleteFrame { index: u32, bytes: usize },
+
+    #[error("Frame with index {index} at {time_millis} ms comes before the previous frame at {previous_millis} ms")]
+    NonMonotonicTimestamp {
+        index: u32,
+        time_millis: u32,
+        previous_millis: u32,
+    },
+
+    #[error("Frame of {bytes} bytes is too short for the OSD grid")]
+    MalformedFrame { bytes: usize },
+
+    #[error("Glyph at {x},{y} is outside the {grid_size} OSD grid")]
+    GlyphOutsideGrid { x: u32, y: u32, grid_size: GridSize },
+
+    #[error("Can't write an OSD file over the file it is read from")]
+    SameInputAndOutput,
+
+    #[error("Unable to write OSD file")]
+    UnableToWriteFile {
+        #[source]
+        source: std::io::Error,
+    },
+
+    #[error("Unable to write JSON")]
+    UnableToWriteJson {
+        #[source]
+        source: serde_json::Error,
+    },
 
-    // #[error("Incomplete frame with index {index}")]
-    // IncompleteFrame { index: u32 },
     #[error("Malformed OSD file")]
     MalformedOsdFile {
         #[from]
@@ -0,0 +1,212 @@
+use std::{
+    fmt::Display,
+    io::{self, Write},
+};
+
+use serde::Serialize;
+
+use super::{
+    error::OsdFileError,
+    fc_firmware::FcFirmware,
+    format::OsdFormat,
+    frame::Frame,
+    glyph::GridSize,
+    osd_file::OsdFile,
+    telemetry::{TelemetryDecoder, TelemetrySample},
+};
+
+/// Readable dumps of an OSD file, for debugging and for attaching to bug reports.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum OsdExportFormat {
+    /// Every frame drawn as text on the OSD grid, using the [`Glyph`](super::glyph::Glyph) `Display` impl.
+    Text,
+    /// The file info and every frame with its timestamp and glyph positions.
+    Json,
+    /// The telemetry decoded from every frame, see [`OsdFile::telemetry`].
+    Csv,
+}
+
+impl OsdExportFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            OsdExportFormat::Text => "txt",
+            OsdExportFormat::Json => "json",
+            OsdExportFormat::Csv => "csv",
+        }
+    }
+}
+
+impl Display for OsdExportFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                OsdExportFormat::Text => "Text",
+                OsdExportFormat::Json => "JSON",
+                OsdExportFormat::Csv => "CSV",
+            }
+        )
+    }
+}
+
+#[derive(Serialize)]
+struct JsonInfo<'a> {
+    format: OsdFormat,
+    fc_firmware: &'a FcFirmware,
+    grid_size: &'a GridSize,
+    frame_count: u32,
+}
+
+impl OsdFile {
+    /// Writes the frames in `format`. The frames are streamed from disk like when rendering.
+    pub fn export(&self, format: OsdExportFormat, mut writer: impl Write) -> Result<(), OsdFileError> {
+        match format {
+            OsdExportFormat::Text => self.export_text(&mut writer),
+            OsdExportFormat::Json => self.export_json(&mut writer),
+            OsdExportFormat::Csv => self.export_csv(&mut writer),
+        }?;
+        writer.flush().map_err(write_error)
+    }
+
+    fn export_text(&self, writer: &mut impl Write) -> Result<(), OsdFileError> {
+        let border = format!("+{}+", "-".repeat(self.grid_size.width as usize));
+        for (index, frame) in self.frames()?.enumerate() {
+            let frame = frame?;
+            writeln!(writer, "Frame {} at {} ms", index, frame.time_millis).map_err(write_error)?;
+            writeln!(writer, "{}", border).map_err(write_error)?;
+            for row in text_rows(&frame, &self.grid_size) {
+                writeln!(writer, "|{}|", row).map_err(write_error)?;
+            }
+            writeln!(writer, "{}\n", border).map_err(write_error)?;
+        }
+        Ok(())
+    }
+
+    fn export_json(&self, writer: &mut impl Write) -> Result<(), OsdFileError> {
+        let info = JsonInfo {
+            format: self.format,
+            fc_firmware: &self.fc_firmware,
+            grid_size: &self.grid_size,
+            frame_count: self.frame_count,
+        };
+        // Written piece by piece so the frames don't have to be in memory at once
+        write!(writer, "{{\"info\":").map_err(write_error)?;
+        serde_json::to_writer(&mut *writer, &info).map_err(json_error)?;
+        write!(writer, ",\"frames\":[").map_err(write_error)?;
+        for (index, frame) in self.frames()?.enumerate() {
+            if index > 0 {
+                write!(writer, ",").map_err(write_error)?;
+            }
+            serde_json::to_writer(&mut *writer, &frame?).map_err(json_error)?;
+        }
+        writeln!(writer, "]}}").map_err(write_error)
+    }
+
+    fn export_csv(&self, writer: &mut impl Write) -> Result<(), OsdFileError> {
+        writeln!(
+            writer,
+            "time_millis,battery_voltage,altitude_m,speed_kmh,latitude,longitude,rssi,flight_time_s"
+        )
+        .map_err(write_error)?;
+        let decoder = TelemetryDecoder::new(&self.fc_firmware);
+        for frame in self.frames()? {
+            let TelemetrySample {
+                time_millis,
+                battery_voltage,
+                altitude,
+                speed,
+                latitude,
+                longitude,
+                rssi,
+                flight_time,
+            } = decoder.decode(&frame?);
+            writeln!(
+                writer,
+                "{},{},{},{},{},{},{},{}",
+                time_millis,
+                csv_value(battery_voltage),
+                csv_value(altitude),
+                csv_value(speed),
+                csv_value(latitude),
+                csv_value(longitude),
+                csv_value(rssi),
+                csv_value(flight_time.map(|duration| duration.as_secs())),
+            )
+            .map_err(write_error)?;
+        }
+        Ok(())
+    }
+}
+
+/// The rows of the grid as text, empty cells are spaces.
+fn text_rows(frame: &Frame, grid_size: &GridSize) -> Vec<String> {
+    let mut cells = vec![vec![" ".to_string(); grid_size.width as usize]; grid_size.height as usize];
+    for glyph in &frame.glyphs {
+        let (x, y) = (glyph.grid_position.x as usize, glyph.grid_position.y as usize);
+        if let Some(cell) = cells.get_mut(y).and_then(|row| row.get_mut(x)) {
+            *cell = glyph.to_string();
+        }
+    }
+    cells.into_iter().map(|row| row.concat()).collect()
+}
+
+fn csv_value(value: Option<impl Display>) -> String {
+    value.map(|value| value.to_string()).unwrap_or_default()
+}
+
+fn write_error(source: io::Error) -> OsdFileError {
+    OsdFileError::UnableToWriteFile { source }
+}
+
+fn json_error(source: serde_json::Error) -> OsdFileError {
+    OsdFileError::UnableToWriteJson { source }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osd::osd_file::tests::{osd_bytes, TempPath};
+
+    fn export(name: &str, format: OsdExportFormat) -> String {
+        let path = TempPath::new(name);
+        std::fs::write(&path.0, osd_bytes(&[0, 100])).unwrap();
+        let osd_file = OsdFile::open(path.0.clone()).unwrap();
+
+        let mut output = vec![];
+        osd_file.export(format, &mut output).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn frames_are_exported_as_text() {
+        let text = export("export-text", OsdExportFormat::Text);
+        let lines = text.lines().collect::<Vec<_>>();
+        assert_eq!(lines[0], "Frame 0 at 0 ms");
+        assert_eq!(lines[1], format!("+{}+", "-".repeat(53)));
+        assert_eq!(lines[2], format!("|A{}|", " ".repeat(52)));
+        assert!(text.contains("Frame 1 at 100 ms"));
+    }
+
+    #[test]
+    fn frames_are_exported_as_json() {
+        let json = export("export-json", OsdExportFormat::Json);
+        let value: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(value["info"]["fc_firmware"], "Betaflight");
+        assert_eq!(value["info"]["grid_size"]["width"], 53);
+
+        let frames: Vec<Frame> = serde_json::from_value(value["frames"].clone()).unwrap();
+        assert_eq!(frames.len(), 2);
+        assert_eq!(frames[1].time_millis, 100);
+        assert_eq!(frames[1].glyphs[0].index, 0x41);
+    }
+
+    #[test]
+    fn telemetry_is_exported_as_csv() {
+        let csv = export("export-csv", OsdExportFormat::Csv);
+        let lines = csv.lines().collect::<Vec<_>>();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("time_millis,"));
+        assert_eq!(lines[2], "100,,,,,,,");
+    }
+}
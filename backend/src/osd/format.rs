@@ -0,0 +1,45 @@
+use std::fmt::Display;
+
+use serde::Serialize;
+
+use super::{msp_osd, reader::HEADER_BYTES};
+
+/// Binary layout of an OSD recording, told apart by the first bytes of the file.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize)]
+pub enum OsdFormat {
+    /// Recorded by Walksnail Avatar goggles, the file starts with the FC firmware tag.
+    #[default]
+    Walksnail,
+    /// Recorded by msp-osd on DJI goggles and on HDZero with WTFOS, the file starts with `MSPOSD`.
+    MspOsd,
+}
+
+impl OsdFormat {
+    pub(super) fn detect(magic: &[u8]) -> Self {
+        if magic.starts_with(msp_osd::MAGIC) {
+            OsdFormat::MspOsd
+        } else {
+            OsdFormat::Walksnail
+        }
+    }
+
+    pub(super) fn header_bytes(&self) -> usize {
+        match self {
+            OsdFormat::Walksnail => HEADER_BYTES,
+            OsdFormat::MspOsd => msp_osd::HEADER_BYTES,
+        }
+    }
+}
+
+impl Display for OsdFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                OsdFormat::Walksnail => "Walksnail",
+                OsdFormat::MspOsd => "DJI / msp-osd",
+            }
+        )
+    }
+}
@@ -1,31 +1,43 @@
 use std::fmt::Debug;
 
+use serde::{Deserialize, Serialize};
+
 use super::{
     error::OsdFileError,
-    glyph::{Glyph, GridPosition},
-    OSD_GRID_WIDTH,
+    glyph::{Glyph, GridPosition, GridSize},
 };
 
 const TIMESTAMP_BYTES: usize = 4;
 const BYTES_PER_GLYPH: usize = 2;
 
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct Frame {
     pub time_millis: u32,
     pub glyphs: Vec<Glyph>,
 }
 
-impl TryFrom<&[u8]> for Frame {
-    type Error = OsdFileError;
+/// Size of a frame record: the timestamp followed by a glyph for every cell of the grid, row by row.
+pub(super) fn record_bytes(grid_size: &GridSize) -> usize {
+    TIMESTAMP_BYTES + (grid_size.width * grid_size.height) as usize * BYTES_PER_GLYPH
+}
 
-    fn try_from(value: &[u8]) -> Result<Self, Self::Error> {
-        let time_millis = u32::from_le_bytes(value[..TIMESTAMP_BYTES].try_into().unwrap());
-        let glyphs = value[TIMESTAMP_BYTES..]
-            .chunks(BYTES_PER_GLYPH)
+impl Frame {
+    /// Decodes a frame record of an OSD file recorded with this grid size.
+    pub fn parse(record: &[u8], grid_size: &GridSize) -> Result<Self, OsdFileError> {
+        if record.len() < record_bytes(grid_size) {
+            return Err(OsdFileError::MalformedFrame { bytes: record.len() });
+        }
+        let (timestamp_bytes, glyph_bytes) = record
+            .split_first_chunk::<TIMESTAMP_BYTES>()
+            .ok_or(OsdFileError::MalformedFrame { bytes: record.len() })?;
+        let time_millis = u32::from_le_bytes(*timestamp_bytes);
+        let glyphs = glyph_bytes
+            .chunks_exact(BYTES_PER_GLYPH)
+            .take((grid_size.width * grid_size.height) as usize)
             .enumerate()
             .filter_map(|(idx, glyph_bytes)| {
-                let x = idx % (OSD_GRID_WIDTH as usize);
-                let y = idx / (OSD_GRID_WIDTH as usize);
+                let x = idx % (grid_size.width as usize);
+                let y = idx / (grid_size.width as usize);
                 let bytes = [glyph_bytes[0], glyph_bytes[1]];
                 let index = u16::from_le_bytes(bytes);
                 if index == 0x00 || index == 0x20 {
@@ -44,4 +56,43 @@ impl TryFrom<&[u8]> for Frame {
             .collect();
         Ok(Self { time_millis, glyphs })
     }
+
+    /// A frame with `text` in the middle of the grid, drawn with the font's ASCII glyphs.
+    pub fn centered_text(text: &str, grid_size: &GridSize) -> Self {
+        let start_x = grid_size.width.saturating_sub(text.len() as u32) / 2;
+        let glyphs = text
+            .bytes()
+            .enumerate()
+            .filter(|(_, byte)| *byte != b' ')
+            .map(|(offset, byte)| Glyph {
+                index: byte as u16,
+                grid_position: GridPosition {
+                    x: start_x + offset as u32,
+                    y: grid_size.height / 2,
+                },
+            })
+            .filter(|glyph| glyph.grid_position.x < grid_size.width)
+            .collect();
+        Self { time_millis: 0, glyphs }
+    }
+
+    /// Encodes the frame as a record of an OSD file with this grid size. Cells without a glyph are written as 0x00, blank
+    /// cells that were stored as a space (0x20) aren't kept when parsing and come out as 0x00 as well.
+    pub fn to_record(&self, grid_size: &GridSize) -> Result<Vec<u8>, OsdFileError> {
+        let mut record = vec![0; record_bytes(grid_size)];
+        record[..TIMESTAMP_BYTES].copy_from_slice(&self.time_millis.to_le_bytes());
+        for glyph in &self.glyphs {
+            let GridPosition { x, y } = glyph.grid_position;
+            if x >= grid_size.width || y >= grid_size.height {
+                return Err(OsdFileError::GlyphOutsideGrid {
+                    x,
+                    y,
+                    grid_size: grid_size.clone(),
+                });
+            }
+            let offset = TIMESTAMP_BYTES + (y * grid_size.width + x) as usize * BYTES_PER_GLYPH;
+            record[offset..offset + BYTES_PER_GLYPH].copy_from_slice(&glyph.index.to_le_bytes());
+        }
+        Ok(record)
+    }
 }
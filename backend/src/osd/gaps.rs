@@ -0,0 +1,104 @@
+use std::{fmt::Display, time::Duration};
+
+use serde::{Deserialize, Serialize};
+
+/// Intervals between OSD frames this many times longer than the nominal frame interval are gaps in the recording.
+pub(super) const GAP_INTERVAL_FACTOR: u32 = 3;
+
+/// A hole in the OSD recording, e.g. while the air unit lost the link.
+#[derive(Debug, Clone, PartialEq)]
+pub struct OsdGap {
+    /// Index of the last frame before the gap.
+    pub frame_index: u32,
+    /// When the frame after [`OsdGap::frame_index`] was expected.
+    pub start: Duration,
+    /// Time of the first frame after the gap.
+    pub end: Duration,
+}
+
+impl OsdGap {
+    pub fn duration(&self) -> Duration {
+        self.end.saturating_sub(self.start)
+    }
+}
+
+impl Display for OsdGap {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{:.1} s to {:.1} s ({:.1} s without OSD)",
+            self.start.as_secs_f32(),
+            self.end.as_secs_f32(),
+            self.duration().as_secs_f32()
+        )
+    }
+}
+
+/// What the renderer shows while there are no OSD frames in a gap.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub enum GapBehavior {
+    /// Keep showing the last frame before the gap.
+    #[default]
+    Hold,
+    /// Show no OSD.
+    Blank,
+    /// Show a "NO OSD" marker in the middle of the grid.
+    NoOsdMarker,
+}
+
+impl Display for GapBehavior {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                GapBehavior::Hold => "Hold last frame",
+                GapBehavior::Blank => "Blank",
+                GapBehavior::NoOsdMarker => "\"NO OSD\" marker",
+            }
+        )
+    }
+}
+
+/// The nominal frame interval in milliseconds, the median interval between the frames, and the gaps between them.
+pub(super) fn find_gaps(timestamps: &[u32]) -> (u32, Vec<OsdGap>) {
+    let mut intervals = timestamps.windows(2).map(|w| w[1] - w[0]).collect::<Vec<_>>();
+    intervals.sort_unstable();
+    let interval_millis = intervals.get(intervals.len() / 2).copied().unwrap_or_default();
+    if interval_millis == 0 {
+        return (interval_millis, vec![]);
+    }
+
+    let gaps = timestamps
+        .windows(2)
+        .enumerate()
+        .filter(|(_, w)| w[1] - w[0] > interval_millis * GAP_INTERVAL_FACTOR)
+        .map(|(index, w)| OsdGap {
+            frame_index: index as u32,
+            start: Duration::from_millis((w[0] + interval_millis).into()),
+            end: Duration::from_millis(w[1].into()),
+        })
+        .collect();
+    (interval_millis, gaps)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn long_intervals_are_gaps() {
+        let timestamps = [0, 100, 200, 300, 1000, 1100, 1200, 1250, 1350];
+        let (interval_millis, gaps) = find_gaps(&timestamps);
+        assert_eq!(interval_millis, 100);
+        assert_eq!(
+            gaps,
+            [OsdGap {
+                frame_index: 3,
+                start: Duration::from_millis(400),
+                end: Duration::from_millis(1000),
+            }]
+        );
+        assert_eq!(gaps[0].duration(), Duration::from_millis(600));
+    }
+}
@@ -1,10 +1,13 @@
 use std::fmt::{Debug, Display};
 
-use crate::util::Coordinates;
+use serde::{Deserialize, Serialize};
+
+use crate::util::{Coordinates, Dimension};
 
 pub type GridPosition = Coordinates<u32>;
+pub type GridSize = Dimension<u32>;
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct Glyph {
     pub index: u16,
     pub grid_position: GridPosition,
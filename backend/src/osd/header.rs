@@ -0,0 +1,120 @@
+use super::{glyph::GridSize, reader::HEADER_BYTES};
+use crate::util::Dimension;
+
+const VERSION_BYTES: std::ops::Range<usize> = 4..6;
+const GRID_WIDTH_BYTE: usize = 6;
+const GRID_HEIGHT_BYTE: usize = 7;
+const FONT_WIDTH_BYTE: usize = 8;
+const FONT_HEIGHT_BYTE: usize = 9;
+const FONT_VARIANT_BYTES: std::ops::Range<usize> = 14..19;
+
+/// The header fields after the FC firmware tag: a format version, the OSD grid and glyph size the flight controller
+/// was configured for and the font variant.
+///
+/// The field offsets are the ones of the msp-osd header and haven't been confirmed on goggle recordings, so the fields
+/// are hints. Recordings that leave them zeroed get `None`, and the grid is checked against the frame records before
+/// it's used.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct OsdHeader {
+    pub version: u16,
+    pub grid_size: Option<GridSize>,
+    /// Size in pixels of the glyphs the goggles rendered with.
+    pub font_size: Option<Dimension<u32>>,
+    pub font_variant: Option<String>,
+}
+
+impl OsdHeader {
+    pub(super) fn parse(header_bytes: &[u8]) -> Self {
+        let version = u16::from_le_bytes([header_bytes[VERSION_BYTES.start], header_bytes[VERSION_BYTES.end - 1]]);
+        Self::from_fields(
+            version,
+            [header_bytes[GRID_WIDTH_BYTE], header_bytes[GRID_HEIGHT_BYTE]],
+            [header_bytes[FONT_WIDTH_BYTE], header_bytes[FONT_HEIGHT_BYTE]],
+            &header_bytes[FONT_VARIANT_BYTES],
+        )
+    }
+
+    /// Builds the header from the raw fields, read at the same offsets from Walksnail and msp-osd headers.
+    pub(super) fn from_fields(version: u16, grid_size: [u8; 2], font_size: [u8; 2], font_variant: &[u8]) -> Self {
+        let dimension = |[width, height]: [u8; 2]| {
+            (width > 0 && height > 0).then_some(Dimension {
+                width: width as u32,
+                height: height as u32,
+            })
+        };
+
+        let font_variant = std::str::from_utf8(font_variant)
+            .ok()
+            .map(|variant| variant.trim_end_matches('\0'))
+            .filter(|variant| !variant.is_empty() && variant.chars().all(|c| c.is_ascii_alphanumeric()))
+            .map(str::to_string);
+
+        Self {
+            version,
+            grid_size: dimension(grid_size),
+            font_size: dimension(font_size),
+            font_variant,
+        }
+    }
+
+    /// Encodes the header in the Walksnail layout, starting with the FC firmware tag.
+    pub(super) fn to_bytes(&self, fc_tag: &[u8]) -> [u8; HEADER_BYTES] {
+        let mut header_bytes = [0; HEADER_BYTES];
+        let tag_bytes = fc_tag.len().min(VERSION_BYTES.start);
+        header_bytes[..tag_bytes].copy_from_slice(&fc_tag[..tag_bytes]);
+        header_bytes[VERSION_BYTES].copy_from_slice(&self.version.to_le_bytes());
+        if let Some(grid_size) = &self.grid_size {
+            header_bytes[GRID_WIDTH_BYTE] = grid_size.width as u8;
+            header_bytes[GRID_HEIGHT_BYTE] = grid_size.height as u8;
+        }
+        if let Some(font_size) = &self.font_size {
+            header_bytes[FONT_WIDTH_BYTE] = font_size.width as u8;
+            header_bytes[FONT_HEIGHT_BYTE] = font_size.height as u8;
+        }
+        if let Some(font_variant) = &self.font_variant {
+            let variant_bytes = font_variant.len().min(FONT_VARIANT_BYTES.len());
+            header_bytes[FONT_VARIANT_BYTES.start..FONT_VARIANT_BYTES.start + variant_bytes]
+                .copy_from_slice(&font_variant.as_bytes()[..variant_bytes]);
+        }
+        header_bytes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn zeroed_header_has_no_hints() {
+        let mut header_bytes = b"BTFL".to_vec();
+        header_bytes.resize(HEADER_BYTES, 0);
+        assert_eq!(OsdHeader::parse(&header_bytes), OsdHeader::default());
+    }
+
+    #[test]
+    fn header_fields_are_decoded() {
+        let mut header_bytes = b"INAV".to_vec();
+        header_bytes.extend_from_slice(&[2, 0, 50, 18, 24, 36, 0, 0, 0, 0]);
+        header_bytes.extend_from_slice(b"INAV\0");
+        header_bytes.resize(HEADER_BYTES, 0);
+
+        let header = OsdHeader::parse(&header_bytes);
+        assert_eq!(header.version, 2);
+        assert_eq!(header.grid_size, Some(Dimension { width: 50, height: 18 }));
+        assert_eq!(header.font_size, Some(Dimension { width: 24, height: 36 }));
+        assert_eq!(header.font_variant.as_deref(), Some("INAV"));
+    }
+
+    #[test]
+    fn header_is_encoded_as_it_is_decoded() {
+        let header = OsdHeader {
+            version: 3,
+            grid_size: Some(Dimension { width: 60, height: 22 }),
+            font_size: Some(Dimension { width: 24, height: 36 }),
+            font_variant: Some("BTFL".to_string()),
+        };
+        let header_bytes = header.to_bytes(b"BTFL");
+        assert_eq!(&header_bytes[..4], b"BTFL");
+        assert_eq!(OsdHeader::parse(&header_bytes), header);
+    }
+}
@@ -1,13 +1,33 @@
 mod error;
+mod export;
 mod fc_firmware;
+mod format;
 mod frame;
+mod gaps;
 mod glyph;
+mod header;
+mod msp_osd;
 mod options;
 mod osd_file;
+mod reader;
+mod symbols;
+mod telemetry;
+mod timing;
+mod writer;
 
-pub const OSD_GRID_WIDTH: u32 = 53;
-pub const OSD_GRID_HEIGHT: u32 = 20;
+/// Betaflight's HD grid, used when the grid can't be detected from the OSD file.
+pub const DEFAULT_GRID_SIZE: GridSize = GridSize { width: 53, height: 20 };
 
+pub use error::OsdFileError;
+pub use export::OsdExportFormat;
+pub use format::OsdFormat;
 pub use frame::Frame;
+pub use gaps::{GapBehavior, OsdGap};
+pub use glyph::{Glyph, GridPosition, GridSize};
+pub use header::OsdHeader;
 pub use options::OsdOptions;
-pub use osd_file::OsdFile;
+pub use osd_file::{OsdFile, OsdRepair};
+pub use reader::OsdReader;
+pub use telemetry::{TelemetryDecoder, TelemetryKind, TelemetrySample};
+pub use timing::{TimingAnalysis, TimingDrift};
+pub use writer::{OsdEdit, OsdWriter};
@@ -0,0 +1,159 @@
+//! OSD recordings of msp-osd, which records the OSD on DJI goggles and on HDZero with WTFOS.
+//!
+//! The file starts with a header holding the grid and font the flight controller was configured for. Each frame is
+//! the index of the video frame it was recorded at and the size of msp-osd's whole character map, followed by the
+//! character map. The map is 60x22 and stored column by column, cells outside the configured grid stay empty.
+
+use std::ops::Range;
+
+use super::{
+    error::OsdFileError,
+    fc_firmware::FcFirmware,
+    frame::Frame,
+    glyph::{Glyph, GridPosition, GridSize},
+    header::OsdHeader,
+};
+
+pub(super) const MAGIC: &[u8] = b"MSPOSD\0";
+pub(super) const HEADER_BYTES: usize = 22;
+pub(super) const FRAME_HEADER_BYTES: usize = 8;
+
+const VERSION_BYTES: Range<usize> = 7..9;
+const GRID_WIDTH_BYTE: usize = 9;
+const GRID_HEIGHT_BYTE: usize = 10;
+const FONT_WIDTH_BYTE: usize = 11;
+const FONT_HEIGHT_BYTE: usize = 12;
+const FONT_VARIANT_BYTES: Range<usize> = 17..22;
+const FC_TAG_BYTES: usize = 4;
+
+const FRAME_INDEX_BYTES: Range<usize> = 0..4;
+const FRAME_SIZE_BYTES: Range<usize> = 4..8;
+const BYTES_PER_GLYPH: usize = 2;
+
+/// Size of msp-osd's character map, the grid used when the header doesn't have one.
+pub(super) const CHARACTER_MAP_SIZE: GridSize = GridSize { width: 60, height: 22 };
+
+/// Size of a frame record holding the whole character map.
+pub(super) const FULL_RECORD_BYTES: usize =
+    FRAME_HEADER_BYTES + (CHARACTER_MAP_SIZE.width * CHARACTER_MAP_SIZE.height) as usize * BYTES_PER_GLYPH;
+
+/// The goggles record at 60 fps, the frame index counts their video frames.
+const VIDEO_FRAMES_PER_SECOND: u32 = 60;
+
+/// Decodes the header. The font variant doubles as the FC firmware tag.
+pub(super) fn parse_header(header_bytes: &[u8]) -> (FcFirmware, OsdHeader) {
+    let version = u16::from_le_bytes([header_bytes[VERSION_BYTES.start], header_bytes[VERSION_BYTES.end - 1]]);
+    let header = OsdHeader::from_fields(
+        version,
+        [header_bytes[GRID_WIDTH_BYTE], header_bytes[GRID_HEIGHT_BYTE]],
+        [header_bytes[FONT_WIDTH_BYTE], header_bytes[FONT_HEIGHT_BYTE]],
+        &header_bytes[FONT_VARIANT_BYTES],
+    );
+    let fc_firmware = header
+        .font_variant
+        .as_deref()
+        .and_then(|variant| FcFirmware::try_from(variant).ok())
+        .unwrap_or(FcFirmware::Unknown);
+    (fc_firmware, header)
+}
+
+/// The FC firmware tag for a Walksnail header when converting the recording.
+pub(super) fn fc_tag(header: &OsdHeader) -> Vec<u8> {
+    header
+        .font_variant
+        .as_deref()
+        .map(|variant| variant.bytes().take(FC_TAG_BYTES).collect())
+        .unwrap_or_default()
+}
+
+/// Size of the frame records, from the header of the first frame.
+pub(super) fn record_bytes(frame_header: &[u8; FRAME_HEADER_BYTES]) -> Result<usize, OsdFileError> {
+    let cells = u32::from_le_bytes(frame_header[FRAME_SIZE_BYTES].try_into().unwrap()) as usize;
+    if cells == 0 || cells > (CHARACTER_MAP_SIZE.width * CHARACTER_MAP_SIZE.height) as usize {
+        return Err(OsdFileError::MalformedFrame {
+            bytes: FRAME_HEADER_BYTES,
+        });
+    }
+    Ok(FRAME_HEADER_BYTES + cells * BYTES_PER_GLYPH)
+}
+
+pub(super) fn time_millis(record: &[u8]) -> u32 {
+    let frame_index = u32::from_le_bytes(record[FRAME_INDEX_BYTES].try_into().unwrap());
+    (frame_index as u64 * 1000 / VIDEO_FRAMES_PER_SECOND as u64) as u32
+}
+
+/// Decodes a frame record, leaving out cells outside `grid_size`.
+pub(super) fn parse_frame(record: &[u8], grid_size: &GridSize) -> Result<Frame, OsdFileError> {
+    let (frame_header, glyph_bytes) = record
+        .split_first_chunk::<FRAME_HEADER_BYTES>()
+        .ok_or(OsdFileError::MalformedFrame { bytes: record.len() })?;
+    if record.len() < record_bytes(frame_header)? {
+        return Err(OsdFileError::MalformedFrame { bytes: record.len() });
+    }
+
+    let glyphs = glyph_bytes
+        .chunks_exact(BYTES_PER_GLYPH)
+        .enumerate()
+        .filter_map(|(idx, glyph_bytes)| {
+            let x = idx as u32 / CHARACTER_MAP_SIZE.height;
+            let y = idx as u32 % CHARACTER_MAP_SIZE.height;
+            let index = u16::from_le_bytes([glyph_bytes[0], glyph_bytes[1]]);
+            if index == 0x00 || index == 0x20 || x >= grid_size.width || y >= grid_size.height {
+                None
+            } else {
+                Some(Glyph {
+                    index,
+                    grid_position: GridPosition { x, y },
+                })
+            }
+        })
+        .collect();
+    Ok(Frame {
+        time_millis: time_millis(record),
+        glyphs,
+    })
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use super::*;
+
+    /// An msp-osd recording on a 53x20 grid with a glyph at 2,1 in each frame.
+    pub fn msp_osd_bytes(frame_indices: &[u32]) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend_from_slice(&3u16.to_le_bytes());
+        bytes.extend_from_slice(&[53, 20, 24, 36, 0, 0, 0, 0]);
+        bytes.extend_from_slice(b"BTFL\0");
+
+        let cells = CHARACTER_MAP_SIZE.width * CHARACTER_MAP_SIZE.height;
+        for frame_index in frame_indices {
+            let mut frame = frame_index.to_le_bytes().to_vec();
+            frame.extend_from_slice(&cells.to_le_bytes());
+            let mut character_map = vec![0; cells as usize * BYTES_PER_GLYPH];
+            let offset = (2 * CHARACTER_MAP_SIZE.height + 1) as usize * BYTES_PER_GLYPH;
+            character_map[offset] = 0x41;
+            frame.extend(character_map);
+            bytes.extend(frame);
+        }
+        bytes
+    }
+
+    #[test]
+    fn header_and_frame_are_decoded() {
+        let bytes = msp_osd_bytes(&[30]);
+        let (fc_firmware, header) = parse_header(&bytes[..HEADER_BYTES]);
+        assert_eq!(fc_firmware, FcFirmware::Betaflight);
+        assert_eq!(header.version, 3);
+        assert_eq!(header.grid_size, Some(GridSize { width: 53, height: 20 }));
+
+        let frame = parse_frame(&bytes[HEADER_BYTES..], &GridSize { width: 53, height: 20 }).unwrap();
+        assert_eq!(frame.time_millis, 500);
+        assert_eq!(
+            frame.glyphs,
+            [Glyph {
+                index: 0x41,
+                grid_position: GridPosition { x: 2, y: 1 }
+            }]
+        );
+    }
+}
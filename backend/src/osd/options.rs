@@ -1,8 +1,9 @@
-use std::collections::HashSet;
+use std::{collections::HashSet, time::Duration};
 
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
 
+use super::{GapBehavior, GridSize};
 use crate::{
     font::CharacterSizeClass,
     util::{Coordinates, Dimension},
@@ -10,6 +11,7 @@ use crate::{
 
 #[derive(Clone, Serialize, Deserialize, Derivative)]
 #[derivative(Default, Debug)]
+#[serde(default)]
 pub struct OsdOptions {
     pub position: Coordinates<i32>,
     #[derivative(Default(value = "false"))]
@@ -18,7 +20,16 @@ pub struct OsdOptions {
     #[derivative(Default(value = "1.0"))]
     #[serde(skip)]
     pub osd_playback_speed_factor: f32,
+    /// Speed factor proposed by the OSD timing analysis, used instead of the ratio of the durations with
+    /// `adjust_playback_speed`.
+    #[serde(skip)]
+    pub fitted_playback_speed_factor: Option<f32>,
     pub masked_grid_positions: HashSet<Coordinates<u32>>,
+    /// Grid the mask was drawn on, the mask is not applied to OSD files with a different grid. `None` applies the mask
+    /// to any grid.
+    pub mask_grid_size: Option<GridSize>,
+    /// What to show where frames are missing from the OSD recording.
+    pub gap_behavior: GapBehavior,
     #[derivative(Default(value = "0.0"))]
     #[serde(skip)]
     pub osd_playback_offset: f32,
@@ -29,11 +40,33 @@ pub struct OsdOptions {
 }
 
 impl OsdOptions {
-    pub fn get_mask(&self, position: &Coordinates<u32>) -> bool {
-        self.masked_grid_positions.contains(position)
+    /// Factor the OSD timestamps are scaled by to line up an OSD of `osd_duration` with a video of `video_duration`.
+    /// With "Adjust playback speed" it's the factor from the timing analysis, or the OSD is stretched to the video.
+    pub fn playback_speed_factor(&self, video_duration: Duration, osd_duration: Duration) -> f32 {
+        if !self.adjust_playback_speed {
+            return 1.0;
+        }
+        self.fitted_playback_speed_factor
+            .unwrap_or_else(|| video_duration.as_secs_f32() / osd_duration.as_secs_f32())
+    }
+
+    pub fn get_mask(&self, position: &Coordinates<u32>, grid_size: &GridSize) -> bool {
+        self.mask_applies_to(grid_size) && self.masked_grid_positions.contains(position)
     }
 
-    pub fn toggle_mask(&mut self, position: Coordinates<u32>) {
+    pub fn mask_applies_to(&self, grid_size: &GridSize) -> bool {
+        self.mask_grid_size
+            .as_ref()
+            .is_none_or(|mask_grid_size| mask_grid_size == grid_size)
+    }
+
+    /// Editing the mask on another grid than it was drawn on starts a new mask.
+    pub fn toggle_mask(&mut self, position: Coordinates<u32>, grid_size: &GridSize) {
+        if !self.mask_applies_to(grid_size) {
+            self.masked_grid_positions.clear();
+        }
+        self.mask_grid_size = Some(grid_size.clone());
+
         if self.masked_grid_positions.contains(&position) {
             self.masked_grid_positions.remove(&position);
         } else {
@@ -43,5 +76,6 @@ impl OsdOptions {
 
     pub fn reset_mask(&mut self) {
         self.masked_grid_positions.clear();
+        self.mask_grid_size = None;
     }
 }
@@ -1,49 +1,679 @@
-use std::{fs, path::PathBuf, time::Duration};
+use std::{
+    fmt::Display,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::Duration,
+};
 
 use derivative::Derivative;
 
-use super::{error::OsdFileError, fc_firmware::FcFirmware};
-use crate::osd::frame::Frame;
+use super::{
+    error::OsdFileError,
+    fc_firmware::FcFirmware,
+    format::OsdFormat,
+    gaps::{find_gaps, OsdGap, GAP_INTERVAL_FACTOR},
+    header::OsdHeader,
+    msp_osd,
+    reader::{frame_index_at, FrameLayout, FrameRecord, OsdReader, HEADER_BYTES},
+    telemetry::{TelemetryDecoder, TelemetrySample},
+    writer::{OsdEdit, OsdWriter},
+};
+use crate::osd::{
+    frame::{record_bytes, Frame},
+    GridSize, DEFAULT_GRID_SIZE,
+};
 
-const HEADER_BYTES: usize = 40;
 const FC_TYPE_BYTES: usize = 4;
-const FRAME_BYTES: usize = 2124;
 
-#[derive(Derivative)]
+/// Grids flight controllers are commonly configured for, tried in this order when detecting the grid from the size
+/// of the frame records.
+const KNOWN_GRID_SIZES: [GridSize; 4] = [
+    DEFAULT_GRID_SIZE,
+    GridSize { width: 60, height: 22 },
+    GridSize { width: 50, height: 18 },
+    GridSize { width: 30, height: 16 },
+];
+
+/// Summary of an OSD file. The frames stay on disk and are streamed with [`OsdFile::frames`].
+#[derive(Derivative, Clone)]
 #[derivative(Debug)]
 pub struct OsdFile {
     pub file_path: PathBuf,
+    pub format: OsdFormat,
     pub fc_firmware: FcFirmware,
+    pub header: OsdHeader,
+    /// Grid the OSD was recorded on, from the header or detected from the size of the frame records.
+    pub grid_size: GridSize,
     pub frame_count: u32,
     pub duration: Duration,
+    /// Nominal time between frames, the median interval.
+    pub frame_interval: Duration,
+    /// Where frames are missing, see [`OsdGap`].
+    pub gaps: Vec<OsdGap>,
+    /// What was dropped from a damaged file opened with [`OsdFile::open_with_recovery`].
+    pub repairs: Vec<OsdRepair>,
+    #[derivative(Debug = "ignore")]
+    records: Arc<[FrameRecord]>,
     #[derivative(Debug = "ignore")]
-    pub frames: Vec<Frame>,
+    layout: FrameLayout,
+    /// Walksnail header for writing the file back. Kept as is, not all header fields are decoded.
+    #[derivative(Debug = "ignore")]
+    header_bytes: [u8; HEADER_BYTES],
+}
+
+/// A damaged part of an OSD file that was left out when opening it with [`OsdFile::open_with_recovery`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum OsdRepair {
+    /// The last frame record was cut short, e.g. because the goggles lost power while recording.
+    TruncatedFrame { index: u32, bytes: usize },
+    /// The frame's timestamp is earlier than the one of the frame before it.
+    OutOfOrderFrame {
+        index: u32,
+        time_millis: u32,
+        previous_millis: u32,
+    },
+    /// The timestamps start over at this frame, e.g. after the goggles reconnected to the quad. The frames from here on
+    /// are moved to continue after the frame before it.
+    TimestampReset {
+        index: u32,
+        time_millis: u32,
+        previous_millis: u32,
+    },
+}
+
+impl Display for OsdRepair {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OsdRepair::TruncatedFrame { index, bytes } => {
+                write!(f, "Dropped incomplete frame {} ({} bytes)", index, bytes)
+            }
+            OsdRepair::OutOfOrderFrame {
+                index,
+                time_millis,
+                previous_millis,
+            } => write!(
+                f,
+                "Dropped frame {} at {} ms, it comes before the previous frame at {} ms",
+                index, time_millis, previous_millis
+            ),
+            OsdRepair::TimestampReset {
+                index,
+                time_millis,
+                previous_millis,
+            } => write!(
+                f,
+                "Timestamps start over at frame {} ({} ms after {} ms), the frames from there on were moved to follow \
+                 the frame before",
+                index, time_millis, previous_millis
+            ),
+        }
+    }
 }
 
 impl OsdFile {
+    /// Opens an OSD file, failing on truncated frames and timestamps that go back in time.
     #[tracing::instrument(ret, err)]
     pub fn open(path: PathBuf) -> Result<Self, OsdFileError> {
-        let mut bytes = fs::read(&path)?;
-        let header_bytes = bytes.drain(0..HEADER_BYTES).collect::<Vec<u8>>();
-        let fc_firmware = FcFirmware::try_from(&header_bytes[..FC_TYPE_BYTES])?;
+        Self::open_with(path, false)
+    }
 
-        let frames = bytes
-            .chunks(FRAME_BYTES)
-            .map(|frame_bytes| frame_bytes.try_into().unwrap())
-            .collect::<Vec<Frame>>();
+    /// Opens a possibly damaged OSD file, leaving out a truncated last frame and single frames with timestamps that go
+    /// back in time. When the timestamps start over the frames after it are moved to follow the ones before. What was
+    /// changed is listed in [`OsdFile::repairs`].
+    #[tracing::instrument(ret, err)]
+    pub fn open_with_recovery(path: PathBuf) -> Result<Self, OsdFileError> {
+        let osd_file = Self::open_with(path, true)?;
+        for repair in &osd_file.repairs {
+            tracing::warn!("{}: {}", osd_file.file_path.display(), repair);
+        }
+        Ok(osd_file)
+    }
 
-        let frame_interval = (frames.last().unwrap().time_millis - frames.first().unwrap().time_millis) as f32
-            / (frames.len() - 1) as f32;
+    fn open_with(path: PathBuf, recover: bool) -> Result<Self, OsdFileError> {
+        let file = File::open(&path)?;
+        let file_bytes = file.metadata()?.len();
+        let index = index_frames(BufReader::new(file), file_bytes, recover)?;
+        let timestamps = index.records.iter().map(|r| r.time_millis).collect::<Vec<_>>();
+        let (interval_millis, gaps) = find_gaps(&timestamps);
 
-        let duration = Duration::from_millis(frames.last().unwrap().time_millis.into())
-            + Duration::from_secs_f32(frame_interval / 1000.0);
+        let first_millis = index.records.first().map(|r| r.time_millis).unwrap_or_default();
+        let last_millis = index.records.last().map(|r| r.time_millis).unwrap_or_default();
+        let frame_interval = if index.records.len() > 1 {
+            (last_millis - first_millis) as f32 / (index.records.len() - 1) as f32
+        } else {
+            0.0
+        };
+        let duration = Duration::from_millis(last_millis.into()) + Duration::from_secs_f32(frame_interval / 1000.0);
 
         Ok(Self {
             file_path: path,
-            fc_firmware,
-            frame_count: frames.len() as u32,
+            format: index.layout.format,
+            fc_firmware: index.fc_firmware,
+            header: index.header,
+            grid_size: index.layout.grid_size.clone(),
+            frame_count: index.records.len() as u32,
             duration,
-            frames,
+            frame_interval: Duration::from_millis(interval_millis.into()),
+            gaps,
+            repairs: index.repairs,
+            records: index.records.into(),
+            layout: index.layout,
+            header_bytes: index.header_bytes,
         })
     }
+
+    /// Opens the file again to read the frames from the start.
+    pub fn frames(&self) -> Result<OsdReader<BufReader<File>>, OsdFileError> {
+        let file = File::open(&self.file_path)?;
+        Ok(OsdReader::new(
+            BufReader::new(file),
+            self.records.clone(),
+            self.layout.clone(),
+        ))
+    }
+
+    /// Reads a single frame, `None` if the index is past the last frame.
+    pub fn frame(&self, index: u32) -> Result<Option<Frame>, OsdFileError> {
+        self.frames()?.frame(index)
+    }
+
+    /// Index of the frame shown at `time`, the last frame at or before it. `None` before the first frame.
+    pub fn frame_index_at(&self, time: Duration) -> Option<u32> {
+        frame_index_at(&self.records, time)
+    }
+
+    /// Reads the frame shown at `time`, see [`OsdFile::frame_index_at`].
+    pub fn frame_at(&self, time: Duration) -> Result<Option<Frame>, OsdFileError> {
+        match self.frame_index_at(time) {
+            Some(index) => self.frame(index),
+            None => Ok(None),
+        }
+    }
+
+    pub(super) fn frame_timestamps(&self) -> Vec<u32> {
+        self.records.iter().map(|record| record.time_millis).collect()
+    }
+
+    /// Frames further apart than this have a gap between them, `None` when the file has no frame interval.
+    pub fn gap_threshold(&self) -> Option<Duration> {
+        (!self.frame_interval.is_zero()).then(|| self.frame_interval * GAP_INTERVAL_FACTOR)
+    }
+
+    /// Decodes the telemetry shown on the OSD, one sample per frame.
+    pub fn telemetry(&self) -> Result<Vec<TelemetrySample>, OsdFileError> {
+        let decoder = TelemetryDecoder::new(&self.fc_firmware);
+        self.frames()?
+            .map(|frame| frame.map(|frame| decoder.decode(&frame)))
+            .collect()
+    }
+
+    /// Writes the frames with `edit` applied to a new OSD file with the same header and opens it. The new file is
+    /// always in the Walksnail format, so this also converts msp-osd recordings.
+    #[tracing::instrument(skip(self), fields(file_path = ?self.file_path), err)]
+    pub fn save(&self, path: &Path, edit: &OsdEdit) -> Result<OsdFile, OsdFileError> {
+        let same_file = path
+            .canonicalize()
+            .is_ok_and(|path| self.file_path.canonicalize().is_ok_and(|p| p == path));
+        if path == self.file_path || same_file {
+            return Err(OsdFileError::SameInputAndOutput);
+        }
+        if !self.records.iter().any(|record| edit.keeps(record.time_millis)) {
+            return Err(OsdFileError::NoFrames);
+        }
+
+        let file = File::create(path).map_err(|source| OsdFileError::UnableToWriteFile { source })?;
+        let mut writer = OsdWriter::new(BufWriter::new(file), &self.header_bytes, self.layout.grid_size.clone())?;
+        for frame in self.frames()? {
+            if let Some(frame) = edit.apply(frame?) {
+                writer.write_frame(&frame)?;
+            }
+        }
+        writer.finish()?;
+
+        OsdFile::open(path.to_path_buf())
+    }
+}
+
+#[derive(Debug)]
+struct FrameIndex {
+    header_bytes: [u8; HEADER_BYTES],
+    fc_firmware: FcFirmware,
+    header: OsdHeader,
+    layout: FrameLayout,
+    records: Vec<FrameRecord>,
+    repairs: Vec<OsdRepair>,
+}
+
+/// Reads through the file once to find the frames and their timestamps, without keeping the glyphs.
+fn index_frames(mut reader: impl Read + Seek, file_bytes: u64, recover: bool) -> Result<FrameIndex, OsdFileError> {
+    let mut magic = [0; msp_osd::MAGIC.len()];
+    if file_bytes < magic.len() as u64 {
+        return Err(OsdFileError::MissingHeader);
+    }
+    reader.read_exact(&mut magic)?;
+    let format = OsdFormat::detect(&magic);
+    if file_bytes < format.header_bytes() as u64 {
+        return Err(OsdFileError::MissingHeader);
+    }
+    let mut format_header_bytes = magic.to_vec();
+    format_header_bytes.resize(format.header_bytes(), 0);
+    reader.read_exact(&mut format_header_bytes[magic.len()..])?;
+    let frame_bytes = file_bytes - format.header_bytes() as u64;
+
+    let (header_bytes, fc_firmware, header, layout) = match format {
+        OsdFormat::Walksnail => {
+            let mut header_bytes = [0; HEADER_BYTES];
+            header_bytes.copy_from_slice(&format_header_bytes);
+            let fc_firmware = FcFirmware::try_from(&header_bytes[..FC_TYPE_BYTES])?;
+            let header = OsdHeader::parse(&header_bytes);
+            let grid_size = detect_grid_size(&header, frame_bytes);
+            (header_bytes, fc_firmware, header, FrameLayout::walksnail(grid_size))
+        }
+        OsdFormat::MspOsd => {
+            let (fc_firmware, header) = msp_osd::parse_header(&format_header_bytes);
+            let grid_size = header.grid_size.clone().unwrap_or(msp_osd::CHARACTER_MAP_SIZE);
+            let layout = FrameLayout {
+                format,
+                grid_size,
+                record_bytes: msp_osd_record_bytes(&mut reader, frame_bytes)?,
+            };
+            (header.to_bytes(&msp_osd::fc_tag(&header)), fc_firmware, header, layout)
+        }
+    };
+
+    let record_bytes = layout.record_bytes;
+    let complete_frames = (frame_bytes / record_bytes as u64) as u32;
+    let trailing_bytes = (frame_bytes % record_bytes as u64) as usize;
+
+    let mut repairs = vec![];
+    if trailing_bytes > 0 {
+        let index = complete_frames;
+        if !recover {
+            return Err(OsdFileError::IncompleteFrame {
+                index,
+                bytes: trailing_bytes,
+            });
+        }
+        repairs.push(OsdRepair::TruncatedFrame {
+            index,
+            bytes: trailing_bytes,
+        });
+    }
+
+    let mut timestamps = Vec::with_capacity(complete_frames as usize);
+    let mut buffer = vec![0; record_bytes];
+    for _ in 0..complete_frames {
+        reader.read_exact(&mut buffer)?;
+        timestamps.push(layout.time_millis(&buffer));
+    }
+
+    let mut records: Vec<FrameRecord> = Vec::with_capacity(timestamps.len());
+    // Added to the timestamps after a reset so they continue from the frames before it
+    let mut rebase_millis = 0;
+    for (record, &file_millis) in (0..).zip(&timestamps) {
+        let time_millis = file_millis + rebase_millis;
+        let previous_millis = records.last().map(|r| r.time_millis).unwrap_or_default();
+        if time_millis < previous_millis {
+            if !recover {
+                return Err(OsdFileError::NonMonotonicTimestamp {
+                    index: record,
+                    time_millis,
+                    previous_millis,
+                });
+            }
+            // A single frame out of order is dropped, when the next frame goes on from the earlier time as well the
+            // timestamps started over
+            let next_millis = timestamps.get(record as usize + 1).map(|&next| next + rebase_millis);
+            if next_millis.is_some_and(|next| next < previous_millis && next >= time_millis) {
+                let interval_millis = match records.as_slice() {
+                    [.., before, previous] => previous.time_millis - before.time_millis,
+                    _ => 0,
+                };
+                rebase_millis += previous_millis + interval_millis - time_millis;
+                repairs.push(OsdRepair::TimestampReset {
+                    index: record,
+                    time_millis: file_millis,
+                    previous_millis,
+                });
+                records.push(FrameRecord {
+                    record,
+                    time_millis: previous_millis + interval_millis,
+                });
+            } else {
+                repairs.push(OsdRepair::OutOfOrderFrame {
+                    index: record,
+                    time_millis,
+                    previous_millis,
+                });
+            }
+            continue;
+        }
+
+        records.push(FrameRecord { record, time_millis });
+    }
+
+    if records.is_empty() {
+        return Err(OsdFileError::NoFrames);
+    }
+
+    // Keep the repairs in file order
+    repairs.sort_by_key(|repair| match repair {
+        OsdRepair::TruncatedFrame { index, .. }
+        | OsdRepair::OutOfOrderFrame { index, .. }
+        | OsdRepair::TimestampReset { index, .. } => *index,
+    });
+
+    Ok(FrameIndex {
+        header_bytes,
+        fc_firmware,
+        header,
+        layout,
+        records,
+        repairs,
+    })
+}
+
+/// msp-osd records the size of its character map with every frame, it's read from the first one. A file without a
+/// complete frame header gets the size of a full 60x22 map.
+fn msp_osd_record_bytes(mut reader: impl Read + Seek, frame_bytes: u64) -> Result<usize, OsdFileError> {
+    let mut frame_header = [0; msp_osd::FRAME_HEADER_BYTES];
+    if frame_bytes < frame_header.len() as u64 {
+        return Ok(msp_osd::FULL_RECORD_BYTES);
+    }
+    reader.read_exact(&mut frame_header)?;
+    reader.seek(SeekFrom::Current(-(frame_header.len() as i64)))?;
+    msp_osd::record_bytes(&frame_header)
+}
+
+/// The grid from the header if it's a known grid and the frame records fit it, or fit no known grid at all like in a
+/// truncated recording. Otherwise, e.g. in older files without it or when the header is misread, the grid is detected
+/// from the size of the frame records if exactly one known grid fits them. Files that fit no grid are taken to be
+/// truncated recordings on the default grid, as are the ones that fit several.
+fn detect_grid_size(header: &OsdHeader, frame_bytes: u64) -> GridSize {
+    let fits = |grid_size: &GridSize| frame_bytes > 0 && frame_bytes % record_bytes(grid_size) as u64 == 0;
+    let fitting = KNOWN_GRID_SIZES
+        .iter()
+        .filter(|grid_size| fits(grid_size))
+        .collect::<Vec<_>>();
+
+    if let Some(header_grid_size) = &header.grid_size {
+        if KNOWN_GRID_SIZES.contains(header_grid_size) && (fits(header_grid_size) || fitting.is_empty()) {
+            return header_grid_size.clone();
+        }
+        tracing::warn!(
+            "OSD header grid {}x{} doesn't match the frames, detecting the grid instead",
+            header_grid_size.width,
+            header_grid_size.height
+        );
+    }
+    match fitting.as_slice() {
+        [grid_size] => (*grid_size).clone(),
+        _ => DEFAULT_GRID_SIZE,
+    }
+}
+
+#[cfg(test)]
+pub(super) mod tests {
+    use std::io::Cursor;
+
+    use claims::assert_matches;
+
+    use super::*;
+    use crate::osd::{glyph::GridPosition, msp_osd::tests::msp_osd_bytes};
+
+    /// An OSD file with one glyph in the top left corner of each frame.
+    pub fn osd_bytes(timestamps: &[u32]) -> Vec<u8> {
+        osd_bytes_with_grid(timestamps, &DEFAULT_GRID_SIZE)
+    }
+
+    fn osd_bytes_with_grid(timestamps: &[u32], grid_size: &GridSize) -> Vec<u8> {
+        let mut bytes = b"BTFL".to_vec();
+        bytes.resize(HEADER_BYTES, 0);
+        for timestamp in timestamps {
+            let mut frame = timestamp.to_le_bytes().to_vec();
+            frame.extend_from_slice(&[0x41, 0x00]);
+            frame.resize(record_bytes(grid_size), 0);
+            bytes.extend(frame);
+        }
+        bytes
+    }
+
+    fn index(bytes: Vec<u8>, recover: bool) -> Result<FrameIndex, OsdFileError> {
+        let file_bytes = bytes.len() as u64;
+        index_frames(Cursor::new(bytes), file_bytes, recover)
+    }
+
+    #[test]
+    fn truncated_frame_is_dropped_when_recovering() {
+        let mut bytes = osd_bytes(&[0, 100, 200]);
+        bytes.truncate(bytes.len() - 1000);
+
+        assert_matches!(
+            index(bytes.clone(), false),
+            Err(OsdFileError::IncompleteFrame { index: 2, bytes: 1124 })
+        );
+
+        let index = index(bytes, true).unwrap();
+        assert_eq!(index.records.len(), 2);
+        assert_eq!(index.repairs, [OsdRepair::TruncatedFrame { index: 2, bytes: 1124 }]);
+    }
+
+    #[test]
+    fn out_of_order_frames_are_dropped_when_recovering() {
+        let bytes = osd_bytes(&[0, 100, 50, 200]);
+
+        assert_matches!(
+            index(bytes.clone(), false),
+            Err(OsdFileError::NonMonotonicTimestamp { index: 2, .. })
+        );
+
+        let index = index(bytes, true).unwrap();
+        let timestamps = index.records.iter().map(|r| r.time_millis).collect::<Vec<_>>();
+        assert_eq!(timestamps, [0, 100, 200]);
+        assert_eq!(
+            index.repairs,
+            [OsdRepair::OutOfOrderFrame {
+                index: 2,
+                time_millis: 50,
+                previous_millis: 100
+            }]
+        );
+    }
+
+    #[test]
+    fn frames_after_a_timestamp_reset_are_kept_when_recovering() {
+        let bytes = osd_bytes(&[0, 100, 200, 300, 0, 100, 200, 150, 300]);
+
+        assert_matches!(
+            index(bytes.clone(), false),
+            Err(OsdFileError::NonMonotonicTimestamp { index: 4, .. })
+        );
+
+        let index = index(bytes, true).unwrap();
+        let timestamps = index.records.iter().map(|r| r.time_millis).collect::<Vec<_>>();
+        assert_eq!(timestamps, [0, 100, 200, 300, 400, 500, 600, 700]);
+        assert_eq!(index.records[7].record, 8);
+        assert_eq!(
+            index.repairs,
+            [
+                OsdRepair::TimestampReset {
+                    index: 4,
+                    time_millis: 0,
+                    previous_millis: 300
+                },
+                OsdRepair::OutOfOrderFrame {
+                    index: 7,
+                    time_millis: 550,
+                    previous_millis: 600
+                }
+            ]
+        );
+    }
+
+    #[test]
+    fn file_without_frames_is_an_error() {
+        assert_matches!(index(vec![0; 10], true), Err(OsdFileError::MissingHeader));
+        assert_matches!(index(osd_bytes(&[]), true), Err(OsdFileError::NoFrames));
+    }
+
+    #[test]
+    fn grid_size_is_detected_from_record_size() {
+        let sd_grid = GridSize { width: 30, height: 16 };
+        let sd_index = index(osd_bytes_with_grid(&[0, 100], &sd_grid), false).unwrap();
+        assert_eq!(sd_index.layout.grid_size, sd_grid);
+        assert_eq!(sd_index.records.len(), 2);
+
+        let hd_index = index(osd_bytes(&[0, 100]), false).unwrap();
+        assert_eq!(hd_index.layout.grid_size, DEFAULT_GRID_SIZE);
+    }
+
+    #[test]
+    fn grid_size_from_header_is_checked_against_the_records() {
+        let sd_grid = GridSize { width: 30, height: 16 };
+        let with_header_grid = |grid_size: &GridSize, [width, height]: [u8; 2]| {
+            let mut bytes = osd_bytes_with_grid(&[0, 100], grid_size);
+            bytes[6] = width;
+            bytes[7] = height;
+            index(bytes, false).unwrap()
+        };
+
+        assert_eq!(with_header_grid(&sd_grid, [30, 16]).layout.grid_size, sd_grid);
+        // A header grid the records don't fit or that isn't a known grid is ignored
+        assert_eq!(
+            with_header_grid(&DEFAULT_GRID_SIZE, [30, 16]).layout.grid_size,
+            DEFAULT_GRID_SIZE
+        );
+        let index = with_header_grid(&sd_grid, [200, 3]);
+        assert_eq!(index.layout.grid_size, sd_grid);
+        assert_eq!(index.records.len(), 2);
+    }
+
+    #[test]
+    fn grid_size_of_truncated_file_is_from_header_or_default() {
+        let mut bytes = osd_bytes(&[0, 100, 200]);
+        bytes.truncate(bytes.len() - 10);
+        let index_without_grid = index(bytes, true).unwrap();
+        assert_eq!(index_without_grid.layout.grid_size, DEFAULT_GRID_SIZE);
+        assert_eq!(
+            index_without_grid.repairs,
+            [OsdRepair::TruncatedFrame { index: 2, bytes: 2114 }]
+        );
+
+        // No known grid fits the cut records, the header grid is kept
+        let sd_grid = GridSize { width: 30, height: 16 };
+        let mut bytes = osd_bytes_with_grid(&[0, 100, 200], &sd_grid);
+        bytes[6] = 30;
+        bytes[7] = 16;
+        bytes.truncate(bytes.len() - 10);
+        let index_with_grid = index(bytes, true).unwrap();
+        assert_eq!(index_with_grid.layout.grid_size, sd_grid);
+        assert_eq!(index_with_grid.records.len(), 2);
+        assert_eq!(
+            index_with_grid.repairs,
+            [OsdRepair::TruncatedFrame { index: 2, bytes: 954 }]
+        );
+    }
+
+    /// Path in the temp dir that is removed when dropped.
+    pub struct TempPath(pub PathBuf);
+
+    impl TempPath {
+        pub fn new(name: &str) -> Self {
+            Self(std::env::temp_dir().join(format!("{}-{}.osd", name, std::process::id())))
+        }
+    }
+
+    impl Drop for TempPath {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn all_frames(osd_file: &OsdFile) -> Vec<Frame> {
+        osd_file.frames().unwrap().collect::<Result<_, _>>().unwrap()
+    }
+
+    #[test]
+    fn saved_file_reads_back_the_same() {
+        let input = TempPath::new("round-trip-input");
+        let output = TempPath::new("round-trip-output");
+        // Blank cells stored as a space are written back as empty cells
+        let timestamps = [0, 100, 200];
+        let mut bytes = osd_bytes(&timestamps);
+        let record_len = record_bytes(&DEFAULT_GRID_SIZE);
+        for record in bytes[HEADER_BYTES..].chunks_exact_mut(record_len) {
+            record[8] = 0x20;
+            record[record_len - 2] = 0x20;
+        }
+        std::fs::write(&input.0, bytes).unwrap();
+
+        let osd_file = OsdFile::open(input.0.clone()).unwrap();
+        let saved = osd_file.save(&output.0, &OsdEdit::default()).unwrap();
+
+        assert_eq!(saved.fc_firmware, osd_file.fc_firmware);
+        assert_eq!(saved.header, osd_file.header);
+        assert_eq!(saved.grid_size, osd_file.grid_size);
+        assert_eq!(saved.frame_count, osd_file.frame_count);
+        assert_eq!(all_frames(&saved), all_frames(&osd_file));
+        assert_eq!(all_frames(&saved)[0].glyphs.len(), 1);
+        assert_eq!(std::fs::read(&output.0).unwrap(), osd_bytes(&timestamps));
+    }
+
+    #[test]
+    fn saved_file_is_trimmed_shifted_and_masked() {
+        let input = TempPath::new("edit-input");
+        let output = TempPath::new("edit-output");
+        std::fs::write(&input.0, osd_bytes(&[0, 100, 200, 300])).unwrap();
+        let osd_file = OsdFile::open(input.0.clone()).unwrap();
+
+        let edit = OsdEdit {
+            start: Some(Duration::from_millis(100)),
+            end: Some(Duration::from_millis(300)),
+            shift_millis: -100,
+            masked_grid_positions: [GridPosition { x: 0, y: 0 }].into(),
+        };
+        let saved = osd_file.save(&output.0, &edit).unwrap();
+
+        let frames = all_frames(&saved);
+        let timestamps = frames.iter().map(|frame| frame.time_millis).collect::<Vec<_>>();
+        assert_eq!(timestamps, [0, 100]);
+        assert!(frames.iter().all(|frame| frame.glyphs.is_empty()));
+
+        assert_matches!(osd_file.save(&input.0, &edit), Err(OsdFileError::SameInputAndOutput));
+        let everything_trimmed = OsdEdit {
+            start: Some(Duration::from_secs(10)),
+            ..Default::default()
+        };
+        assert_matches!(
+            osd_file.save(&output.0, &everything_trimmed),
+            Err(OsdFileError::NoFrames)
+        );
+    }
+
+    #[test]
+    fn msp_osd_recording_is_detected() {
+        let index = index(msp_osd_bytes(&[0, 6, 12]), false).unwrap();
+        assert_eq!(index.layout.format, OsdFormat::MspOsd);
+        assert_eq!(index.fc_firmware, FcFirmware::Betaflight);
+        assert_eq!(index.layout.grid_size, DEFAULT_GRID_SIZE);
+        let timestamps = index.records.iter().map(|r| r.time_millis).collect::<Vec<_>>();
+        assert_eq!(timestamps, [0, 100, 200]);
+    }
+
+    #[test]
+    fn msp_osd_recording_is_saved_as_walksnail() {
+        let input = TempPath::new("msp-osd-input");
+        let output = TempPath::new("msp-osd-output");
+        std::fs::write(&input.0, msp_osd_bytes(&[0, 6, 12])).unwrap();
+
+        let osd_file = OsdFile::open(input.0.clone()).unwrap();
+        let saved = osd_file.save(&output.0, &OsdEdit::default()).unwrap();
+
+        assert_eq!(saved.format, OsdFormat::Walksnail);
+        assert_eq!(saved.fc_firmware, FcFirmware::Betaflight);
+        assert_eq!(saved.header, osd_file.header);
+        assert_eq!(all_frames(&saved), all_frames(&osd_file));
+    }
 }
@@ -0,0 +1,256 @@
+use std::{
+    io::{Read, Seek, SeekFrom},
+    sync::Arc,
+    time::Duration,
+};
+
+use super::{
+    error::OsdFileError,
+    format::OsdFormat,
+    frame::{record_bytes, Frame},
+    glyph::GridSize,
+    msp_osd,
+};
+
+pub(super) const HEADER_BYTES: usize = 40;
+
+/// Where a frame is stored in the OSD file and its timestamp, so frames can be found without decoding them.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) struct FrameRecord {
+    /// Index of the frame record in the file, frames that were dropped while opening the file leave a hole.
+    pub record: u32,
+    pub time_millis: u32,
+}
+
+/// Index of the frame shown at `time`, the last frame at or before it. `None` before the first frame.
+pub(super) fn frame_index_at(records: &[FrameRecord], time: Duration) -> Option<u32> {
+    let time_millis = time.as_millis();
+    let frames_shown = records.partition_point(|record| u128::from(record.time_millis) <= time_millis);
+    frames_shown.checked_sub(1).map(|index| index as u32)
+}
+
+/// How the frame records of an OSD file are stored.
+#[derive(Debug, Clone, PartialEq)]
+pub(super) struct FrameLayout {
+    pub format: OsdFormat,
+    pub grid_size: GridSize,
+    /// Every frame record of a file has the same size.
+    pub record_bytes: usize,
+}
+
+impl FrameLayout {
+    pub fn walksnail(grid_size: GridSize) -> Self {
+        Self {
+            format: OsdFormat::Walksnail,
+            record_bytes: record_bytes(&grid_size),
+            grid_size,
+        }
+    }
+
+    fn offset(&self, record: &FrameRecord) -> u64 {
+        self.format.header_bytes() as u64 + record.record as u64 * self.record_bytes as u64
+    }
+
+    pub fn time_millis(&self, record: &[u8]) -> u32 {
+        match self.format {
+            OsdFormat::Walksnail => u32::from_le_bytes([record[0], record[1], record[2], record[3]]),
+            OsdFormat::MspOsd => msp_osd::time_millis(record),
+        }
+    }
+
+    pub fn parse(&self, record: &[u8]) -> Result<Frame, OsdFileError> {
+        match self.format {
+            OsdFormat::Walksnail => Frame::parse(record, &self.grid_size),
+            OsdFormat::MspOsd => msp_osd::parse_frame(record, &self.grid_size),
+        }
+    }
+}
+
+/// Reads the frames of an OSD file one at a time instead of loading the whole file into memory. Frames of every
+/// [`OsdFormat`] are decoded to the same [`Frame`]s.
+///
+/// Created with [`OsdFile::frames`](super::OsdFile::frames). Iterating yields the frames in order starting at the
+/// current position, [`OsdReader::seek_to_frame`] and [`OsdReader::frame`] give random access.
+pub struct OsdReader<R> {
+    reader: R,
+    records: Arc<[FrameRecord]>,
+    layout: FrameLayout,
+    next_index: usize,
+    /// Record the underlying reader is positioned at, `None` when unknown.
+    position: Option<u32>,
+    buffer: Vec<u8>,
+}
+
+impl<R: Read + Seek> OsdReader<R> {
+    pub(super) fn new(reader: R, records: Arc<[FrameRecord]>, layout: FrameLayout) -> Self {
+        Self {
+            reader,
+            records,
+            buffer: vec![0; layout.record_bytes],
+            layout,
+            next_index: 0,
+            position: None,
+        }
+    }
+
+    pub fn frame_count(&self) -> u32 {
+        self.records.len() as u32
+    }
+
+    /// The next call to `next` returns the frame with this index.
+    pub fn seek_to_frame(&mut self, index: u32) {
+        self.next_index = index as usize;
+    }
+
+    /// Reads the frame with this index, `None` if the index is past the last frame. Iteration continues with the
+    /// frame after it.
+    pub fn frame(&mut self, index: u32) -> Result<Option<Frame>, OsdFileError> {
+        self.seek_to_frame(index);
+        self.next().transpose()
+    }
+
+    /// Index of the frame shown at `time`, the last frame at or before it. `None` before the first frame.
+    pub fn frame_index_at(&self, time: Duration) -> Option<u32> {
+        frame_index_at(&self.records, time)
+    }
+
+    /// Reads the frame shown at `time`, see [`OsdReader::frame_index_at`].
+    pub fn frame_at(&mut self, time: Duration) -> Result<Option<Frame>, OsdFileError> {
+        match self.frame_index_at(time) {
+            Some(index) => self.frame(index),
+            None => Ok(None),
+        }
+    }
+
+    fn read_record(&mut self, record: FrameRecord) -> Result<Frame, OsdFileError> {
+        if self.position != Some(record.record) {
+            self.position = None;
+            self.reader.seek(SeekFrom::Start(self.layout.offset(&record)))?;
+        }
+        self.reader.read_exact(&mut self.buffer)?;
+        self.position = Some(record.record + 1);
+        let mut frame = self.layout.parse(&self.buffer)?;
+        // Differs from the file after a timestamp reset
+        frame.time_millis = record.time_millis;
+        Ok(frame)
+    }
+}
+
+impl<R: Read + Seek> Iterator for OsdReader<R> {
+    type Item = Result<Frame, OsdFileError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let record = *self.records.get(self.next_index)?;
+        self.next_index += 1;
+
+        let frame = self.read_record(record);
+        if frame.is_err() {
+            // Don't keep returning the same error
+            self.next_index = self.records.len();
+            self.position = None;
+        }
+        Some(frame)
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let remaining = self.records.len().saturating_sub(self.next_index);
+        (remaining, Some(remaining))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+    use crate::osd::{msp_osd::tests::msp_osd_bytes, osd_file::tests::osd_bytes, DEFAULT_GRID_SIZE};
+
+    fn records(records: &[(u32, u32)]) -> Arc<[FrameRecord]> {
+        records
+            .iter()
+            .map(|&(record, time_millis)| FrameRecord { record, time_millis })
+            .collect()
+    }
+
+    #[test]
+    fn frames_are_read_in_order() {
+        let bytes = osd_bytes(&[0, 100, 200]);
+        let reader = OsdReader::new(
+            Cursor::new(bytes),
+            records(&[(0, 0), (1, 100), (2, 200)]),
+            FrameLayout::walksnail(DEFAULT_GRID_SIZE),
+        );
+        assert_eq!(reader.frame_count(), 3);
+
+        let frames = reader.collect::<Result<Vec<_>, _>>().unwrap();
+        let timestamps = frames.iter().map(|frame| frame.time_millis).collect::<Vec<_>>();
+        assert_eq!(timestamps, [0, 100, 200]);
+        assert_eq!(frames[0].glyphs.len(), 1);
+    }
+
+    #[test]
+    fn dropped_records_are_skipped() {
+        let bytes = osd_bytes(&[0, 50, 100, 200]);
+        let mut reader = OsdReader::new(
+            Cursor::new(bytes),
+            records(&[(0, 0), (2, 100), (3, 200)]),
+            FrameLayout::walksnail(DEFAULT_GRID_SIZE),
+        );
+
+        assert_eq!(reader.frame(1).unwrap().unwrap().time_millis, 100);
+        assert_eq!(reader.next().unwrap().unwrap().time_millis, 200);
+        assert!(reader.next().is_none());
+    }
+
+    #[test]
+    fn msp_osd_frames_are_read() {
+        let bytes = msp_osd_bytes(&[0, 6, 12]);
+        let layout = FrameLayout {
+            format: OsdFormat::MspOsd,
+            grid_size: GridSize { width: 53, height: 20 },
+            record_bytes: (bytes.len() - msp_osd::HEADER_BYTES) / 3,
+        };
+        let mut reader = OsdReader::new(Cursor::new(bytes), records(&[(0, 0), (1, 100), (2, 200)]), layout);
+
+        let frame = reader.frame(2).unwrap().unwrap();
+        assert_eq!(frame.time_millis, 200);
+        assert_eq!(
+            frame.glyphs[0].grid_position,
+            crate::osd::glyph::GridPosition { x: 2, y: 1 }
+        );
+    }
+
+    #[test]
+    fn frames_are_found_by_time() {
+        let bytes = osd_bytes(&[100, 200, 300]);
+        let mut reader = OsdReader::new(
+            Cursor::new(bytes),
+            records(&[(0, 100), (1, 200), (2, 300)]),
+            FrameLayout::walksnail(DEFAULT_GRID_SIZE),
+        );
+
+        assert_eq!(reader.frame_index_at(Duration::from_millis(50)), None);
+        assert_eq!(reader.frame_index_at(Duration::from_millis(100)), Some(0));
+        assert_eq!(reader.frame_index_at(Duration::from_millis(299)), Some(1));
+        assert_eq!(reader.frame_index_at(Duration::from_secs(60)), Some(2));
+
+        // Backwards after reading forwards
+        assert_eq!(
+            reader
+                .frame_at(Duration::from_millis(250))
+                .unwrap()
+                .unwrap()
+                .time_millis,
+            200
+        );
+        assert_eq!(
+            reader
+                .frame_at(Duration::from_millis(120))
+                .unwrap()
+                .unwrap()
+                .time_millis,
+            100
+        );
+        assert!(reader.frame_at(Duration::ZERO).unwrap().is_none());
+    }
+}
@@ -0,0 +1,84 @@
+//! Where the flight controller firmwares put their symbols in the OSD font, for the elements the telemetry decoder
+//! recognizes.
+
+use std::ops::RangeInclusive;
+
+use super::{fc_firmware::FcFirmware, telemetry::TelemetryKind};
+
+const FEET_TO_METERS: f32 = 0.3048;
+const MPH_TO_KMH: f32 = 1.609344;
+
+/// How an OSD element is drawn: an optional symbol in front of the value and the unit symbol after it.
+pub(super) struct Element {
+    pub kind: TelemetryKind,
+    /// Symbols in front of the value, e.g. the battery icons. `None` when only the unit identifies the element.
+    pub prefix: Option<RangeInclusive<u16>>,
+    /// Unit symbols after the value and the factor that converts the value to the unit of [`TelemetryKind`]. Empty
+    /// when the value isn't followed by a unit.
+    pub units: &'static [(u16, f32)],
+}
+
+const fn element(kind: TelemetryKind, prefix: Option<RangeInclusive<u16>>, units: &'static [(u16, f32)]) -> Element {
+    Element { kind, prefix, units }
+}
+
+/// Betaflight's `osd/symbols.h`.
+const BETAFLIGHT: &[Element] = &[
+    element(TelemetryKind::BatteryVoltage, Some(0x90..=0x97), &[(0x06, 1.0)]),
+    element(
+        TelemetryKind::Altitude,
+        Some(0x7F..=0x7F),
+        &[(0x0C, 1.0), (0x0F, FEET_TO_METERS)],
+    ),
+    element(TelemetryKind::Speed, None, &[(0x9E, 1.0), (0x9D, MPH_TO_KMH)]),
+    element(TelemetryKind::Latitude, Some(0x89..=0x89), &[]),
+    element(TelemetryKind::Longitude, Some(0x98..=0x98), &[]),
+    element(TelemetryKind::Rssi, Some(0x01..=0x01), &[]),
+    element(TelemetryKind::FlightTime, Some(0x9C..=0x9C), &[]),
+];
+
+/// INAV's `drivers/osd_symbols.h`.
+const INAV: &[Element] = &[
+    element(TelemetryKind::BatteryVoltage, Some(0x62..=0x68), &[(0x1F, 1.0)]),
+    element(
+        TelemetryKind::Altitude,
+        None,
+        &[
+            (0x76, 1.0),
+            (0x77, 1000.0),
+            (0x78, FEET_TO_METERS),
+            (0x79, 1000.0 * FEET_TO_METERS),
+        ],
+    ),
+    element(TelemetryKind::Speed, None, &[(0xA1, 1.0), (0xA2, MPH_TO_KMH)]),
+    element(TelemetryKind::Latitude, Some(0x03..=0x03), &[]),
+    element(TelemetryKind::Longitude, Some(0x04..=0x04), &[]),
+    element(TelemetryKind::Rssi, Some(0x01..=0x01), &[]),
+    element(TelemetryKind::FlightTime, Some(0x9C..=0x9C), &[]),
+];
+
+/// ArduPilot's symbol lookup table in `AP_OSD_Backend.h`. Ground speed is drawn after the direction arrow.
+const ARDUPILOT: &[Element] = &[
+    element(TelemetryKind::BatteryVoltage, Some(0x90..=0x96), &[(0x06, 1.0)]),
+    element(TelemetryKind::Altitude, None, &[(0xB1, 1.0), (0xB3, FEET_TO_METERS)]),
+    element(
+        TelemetryKind::Speed,
+        Some(0x60..=0x6F),
+        &[(0xA1, 1.0), (0xB0, MPH_TO_KMH)],
+    ),
+    element(TelemetryKind::Latitude, Some(0xA6..=0xA6), &[]),
+    element(TelemetryKind::Longitude, Some(0xA7..=0xA7), &[]),
+    element(TelemetryKind::Rssi, Some(0x01..=0x01), &[]),
+    element(TelemetryKind::FlightTime, Some(0x9C..=0x9C), &[]),
+];
+
+/// The elements of `fc_firmware`'s OSD. KISS draws its elements with Betaflight's symbols, an unknown firmware has
+/// no elements.
+pub(super) fn elements(fc_firmware: &FcFirmware) -> &'static [Element] {
+    match fc_firmware {
+        FcFirmware::Betaflight | FcFirmware::Kiss | FcFirmware::KissUltra => BETAFLIGHT,
+        FcFirmware::Inav => INAV,
+        FcFirmware::ArduPilot => ARDUPILOT,
+        FcFirmware::Unknown => &[],
+    }
+}
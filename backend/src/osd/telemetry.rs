@@ -0,0 +1,298 @@
+use std::{collections::BTreeMap, ops::RangeInclusive, time::Duration};
+
+use super::{
+    fc_firmware::FcFirmware,
+    frame::Frame,
+    symbols::{self, Element},
+};
+
+/// The OSD elements the telemetry decoder recognizes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TelemetryKind {
+    /// Volts, either the pack or the average cell voltage depending on the OSD layout.
+    BatteryVoltage,
+    /// Meters.
+    Altitude,
+    /// Kilometers per hour.
+    Speed,
+    Latitude,
+    Longitude,
+    /// As shown on the OSD, a percentage or dBm depending on the flight controller settings.
+    Rssi,
+    FlightTime,
+}
+
+/// Telemetry read from one OSD frame. A field is `None` when its element isn't on the OSD.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct TelemetrySample {
+    pub time_millis: u32,
+    pub battery_voltage: Option<f32>,
+    pub altitude: Option<f32>,
+    pub speed: Option<f32>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    pub rssi: Option<f32>,
+    pub flight_time: Option<Duration>,
+}
+
+/// Reads telemetry from the text of OSD frames, using the symbols of the flight controller firmware to find the
+/// elements. Values are parsed from the ASCII glyphs between the element's symbols.
+pub struct TelemetryDecoder {
+    elements: &'static [Element],
+}
+
+impl TelemetryDecoder {
+    pub fn new(fc_firmware: &FcFirmware) -> Self {
+        Self {
+            elements: symbols::elements(fc_firmware),
+        }
+    }
+
+    pub fn decode(&self, frame: &Frame) -> TelemetrySample {
+        let mut sample = TelemetrySample {
+            time_millis: frame.time_millis,
+            ..Default::default()
+        };
+
+        let rows = rows(frame);
+        for element in self.elements {
+            let Some((value, scale)) = rows.values().find_map(|row| find_value(row, element)) else {
+                continue;
+            };
+            match element.kind {
+                TelemetryKind::BatteryVoltage => sample.battery_voltage = parse_scaled(&value, scale),
+                TelemetryKind::Altitude => sample.altitude = parse_scaled(&value, scale),
+                TelemetryKind::Speed => sample.speed = parse_scaled(&value, scale),
+                TelemetryKind::Latitude => sample.latitude = value.parse().ok(),
+                TelemetryKind::Longitude => sample.longitude = value.parse().ok(),
+                TelemetryKind::Rssi => sample.rssi = parse_scaled(&value, scale),
+                TelemetryKind::FlightTime => sample.flight_time = parse_duration(&value),
+            }
+        }
+        sample
+    }
+}
+
+/// The glyph indices of each row of the frame, 0 for empty cells.
+fn rows(frame: &Frame) -> BTreeMap<u32, Vec<u16>> {
+    let mut rows = BTreeMap::<u32, Vec<u16>>::new();
+    for glyph in &frame.glyphs {
+        let row = rows.entry(glyph.grid_position.y).or_default();
+        let x = glyph.grid_position.x as usize;
+        if row.len() <= x {
+            row.resize(x + 1, 0);
+        }
+        row[x] = glyph.index;
+    }
+    rows
+}
+
+fn is_value_glyph(index: u16) -> bool {
+    matches!(char::from_u32(index as u32), Some('0'..='9' | '.' | '-' | ':'))
+}
+
+fn is_blank(index: u16) -> bool {
+    index == 0 || index == b' ' as u16
+}
+
+fn text(glyphs: &[u16]) -> String {
+    glyphs
+        .iter()
+        .filter_map(|&index| char::from_u32(index as u32))
+        .collect()
+}
+
+/// The first value of the element in the row and the factor for its unit. Blanks between the prefix symbol and the
+/// value are skipped, numbers are often padded.
+fn find_value(row: &[u16], element: &Element) -> Option<(String, f32)> {
+    let matches_prefix = |prefix: &RangeInclusive<u16>, value_start: usize| {
+        let before = row[..value_start].iter().rposition(|&index| !is_blank(index));
+        before.is_some_and(|before| prefix.contains(&row[before]))
+    };
+
+    if element.units.is_empty() {
+        let prefix = element.prefix.as_ref()?;
+        return row.iter().enumerate().find_map(|(position, index)| {
+            if !prefix.contains(index) {
+                return None;
+            }
+            let rest = &row[position + 1..];
+            let value_start = rest.iter().position(|&index| !is_blank(index))?;
+            let value_len = rest[value_start..]
+                .iter()
+                .take_while(|&&index| is_value_glyph(index))
+                .count();
+            (value_len > 0).then(|| (text(&rest[value_start..value_start + value_len]), 1.0))
+        });
+    }
+
+    row.iter().enumerate().find_map(|(position, index)| {
+        let &(_, scale) = element.units.iter().find(|(unit, _)| unit == index)?;
+        let value_len = row[..position]
+            .iter()
+            .rev()
+            .take_while(|&&index| is_value_glyph(index))
+            .count();
+        let value_start = position - value_len;
+        if value_len == 0
+            || element
+                .prefix
+                .as_ref()
+                .is_some_and(|prefix| !matches_prefix(prefix, value_start))
+        {
+            return None;
+        }
+        Some((text(&row[value_start..position]), scale))
+    })
+}
+
+fn parse_scaled(value: &str, scale: f32) -> Option<f32> {
+    value.parse::<f32>().ok().map(|value| value * scale)
+}
+
+/// Parses `mm:ss` or `hh:mm:ss`.
+fn parse_duration(value: &str) -> Option<Duration> {
+    let parts = value
+        .split(':')
+        .map(|part| part.parse::<u64>().ok())
+        .collect::<Option<Vec<_>>>()?;
+    (parts.len() >= 2).then(|| Duration::from_secs(parts.iter().fold(0, |seconds, part| seconds * 60 + part)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::osd::glyph::{Glyph, GridPosition};
+
+    /// A frame with the rows written from the left edge, symbols are written as `\u{..}` escapes.
+    fn frame(rows: &[&str]) -> Frame {
+        let glyphs = rows
+            .iter()
+            .enumerate()
+            .flat_map(|(y, row)| {
+                row.chars()
+                    .enumerate()
+                    .filter(|(_, c)| *c != ' ')
+                    .map(move |(x, c)| Glyph {
+                        index: c as u16,
+                        grid_position: GridPosition {
+                            x: x as u32,
+                            y: y as u32,
+                        },
+                    })
+            })
+            .collect();
+        Frame {
+            time_millis: 1000,
+            glyphs,
+        }
+    }
+
+    fn assert_close(value: Option<f32>, expected: f32) {
+        let value = value.unwrap();
+        assert!((value - expected).abs() < 0.01, "{} != {}", value, expected);
+    }
+
+    #[test]
+    fn betaflight_elements_are_decoded() {
+        let frame = frame(&[
+            "\u{90}16.80\u{06}     \u{7F}12.3\u{0C}",
+            "\u{01}99          \u{9C}02:34",
+            "  45\u{9E}",
+            "\u{89}-33.8688000  \u{98}151.2093000",
+        ]);
+        let sample = TelemetryDecoder::new(&FcFirmware::Betaflight).decode(&frame);
+
+        assert_eq!(sample.time_millis, 1000);
+        assert_close(sample.battery_voltage, 16.8);
+        assert_close(sample.altitude, 12.3);
+        assert_close(sample.speed, 45.0);
+        assert_close(sample.rssi, 99.0);
+        assert_eq!(sample.latitude, Some(-33.8688));
+        assert_eq!(sample.longitude, Some(151.2093));
+        assert_eq!(sample.flight_time, Some(Duration::from_secs(154)));
+    }
+
+    #[test]
+    fn telemetry_is_decoded_from_an_osd_file() {
+        // Synthetic, not a goggle recording: a Betaflight layout on the HD grid in the Walksnail file format, written
+        // by tests/fixtures/create_betaflight_hd_osd.py
+        let path = std::path::PathBuf::from(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/betaflight_hd.osd");
+        let osd_file = crate::osd::OsdFile::open(path).unwrap();
+        assert_eq!(osd_file.fc_firmware, FcFirmware::Betaflight);
+
+        let samples = osd_file.telemetry().unwrap();
+        assert_eq!(
+            samples.iter().map(|sample| sample.time_millis).collect::<Vec<_>>(),
+            [0, 100, 200]
+        );
+        for (sample, (voltage, altitude, speed, rssi)) in samples.iter().zip([
+            (16.8, 12.3, 45.0, 99.0),
+            (16.76, 12.5, 47.0, 98.0),
+            (16.71, 12.8, 50.0, 98.0),
+        ]) {
+            assert_close(sample.battery_voltage, voltage);
+            assert_close(sample.altitude, altitude);
+            assert_close(sample.speed, speed);
+            assert_close(sample.rssi, rssi);
+            assert_eq!(sample.latitude, Some(-33.8688));
+            assert_eq!(sample.longitude, Some(151.2093));
+        }
+        assert_eq!(
+            samples.iter().map(|sample| sample.flight_time).collect::<Vec<_>>(),
+            [154, 154, 155].map(|secs| Some(Duration::from_secs(secs)))
+        );
+    }
+
+    #[test]
+    fn inav_elements_are_decoded() {
+        let frame = frame(&[
+            "\u{62}16.8\u{1F}   120\u{76}",
+            " 30\u{A2}",
+            "\u{03}47.3769000 \u{04}8.5417000",
+            "\u{01}87  \u{9C}1:10:05",
+        ]);
+        let sample = TelemetryDecoder::new(&FcFirmware::Inav).decode(&frame);
+
+        assert_close(sample.battery_voltage, 16.8);
+        assert_close(sample.altitude, 120.0);
+        assert_close(sample.speed, 48.28);
+        assert_eq!(sample.latitude, Some(47.3769));
+        assert_eq!(sample.longitude, Some(8.5417));
+        assert_close(sample.rssi, 87.0);
+        assert_eq!(sample.flight_time, Some(Duration::from_secs(4205)));
+    }
+
+    #[test]
+    fn ardupilot_elements_are_decoded() {
+        let frame = frame(&[
+            "\u{92}15.2\u{06}",
+            "\u{70}\u{60}  42\u{A1}",
+            "  33\u{B3}",
+            "\u{A6}-12.345678 \u{A7} 98.765432",
+            "\u{9C}  3:07",
+        ]);
+        let sample = TelemetryDecoder::new(&FcFirmware::ArduPilot).decode(&frame);
+
+        assert_close(sample.battery_voltage, 15.2);
+        assert_close(sample.speed, 42.0);
+        assert_close(sample.altitude, 10.06);
+        assert_eq!(sample.latitude, Some(-12.345678));
+        assert_eq!(sample.longitude, Some(98.765432));
+        assert_eq!(sample.rssi, None);
+        assert_eq!(sample.flight_time, Some(Duration::from_secs(187)));
+    }
+
+    #[test]
+    fn unknown_firmware_has_no_telemetry() {
+        let frame = frame(&["\u{90}16.80\u{06}"]);
+        let sample = TelemetryDecoder::new(&FcFirmware::Unknown).decode(&frame);
+        assert_eq!(
+            sample,
+            TelemetrySample {
+                time_millis: 1000,
+                ..Default::default()
+            }
+        );
+    }
+}
@@ -0,0 +1,297 @@
+use std::{fmt::Display, time::Duration};
+
+use super::{gaps::find_gaps, options::OsdOptions, osd_file::OsdFile};
+use crate::ffmpeg::VideoInfo;
+
+/// The OSD and video durations may differ by this much before they are considered out of sync, in video frames.
+const SYNC_TOLERANCE_FRAMES: f32 = 2.0;
+/// How much the goggle clocks are expected to drift apart at most, relative to the video duration. Larger differences
+/// are more likely a part of the OSD recording that is missing.
+const MAX_CLOCK_DRIFT: f32 = 0.02;
+
+/// How the OSD timestamps relate to the video.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TimingDrift {
+    /// The OSD and video durations match.
+    Aligned,
+    /// The OSD starts later than the video, it is delayed by [`TimingAnalysis::osd_playback_offset`].
+    ConstantOffset,
+    /// The OSD timestamps run at a different speed than the video and are scaled by
+    /// [`TimingAnalysis::osd_playback_speed_factor`].
+    LinearDrift,
+    /// The durations match but frames are missing in places, the OSD freezes there.
+    Gaps,
+}
+
+impl Display for TimingDrift {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}",
+            match self {
+                TimingDrift::Aligned => "In sync",
+                TimingDrift::ConstantOffset => "Constant offset",
+                TimingDrift::LinearDrift => "Linear drift",
+                TimingDrift::Gaps => "Gaps in OSD",
+            }
+        )
+    }
+}
+
+/// Proposed OSD timing for a video, see [`OsdFile::analyze_timing`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct TimingAnalysis {
+    pub drift: TimingDrift,
+    pub osd_playback_offset: f32,
+    pub osd_playback_speed_factor: f32,
+    /// How likely the proposal is right, from 0 to 1.
+    pub confidence: f32,
+    /// Typical time between OSD frames.
+    pub osd_frame_interval: Duration,
+    pub gap_count: usize,
+    /// Time without OSD frames in the gaps.
+    pub gap_duration: Duration,
+}
+
+impl TimingAnalysis {
+    /// Sets the playback options to the proposed values. Linear drift is corrected with "Adjust playback speed", which
+    /// then renders with the fitted speed factor.
+    pub fn apply(&self, osd_options: &mut OsdOptions) {
+        let linear_drift = self.drift == TimingDrift::LinearDrift;
+        osd_options.adjust_playback_speed = linear_drift;
+        osd_options.fitted_playback_speed_factor = linear_drift.then_some(self.osd_playback_speed_factor);
+        osd_options.osd_playback_offset = self.osd_playback_offset;
+    }
+}
+
+impl OsdFile {
+    /// Compares the OSD timestamps with the duration and frame rate of the video and proposes a playback offset and
+    /// speed factor that line them up.
+    ///
+    /// A line is fitted through the timestamps against the frame numbers, its slope is the OSD frame interval and it
+    /// ends where the OSD ends. An OSD that ends within a couple of video frames of the video is in sync. When the
+    /// difference is small enough to be the clocks drifting apart the speed is corrected, otherwise the OSD is shifted:
+    /// a shorter OSD is assumed to have started late, a longer one early. The confidence is lower the further the
+    /// timestamps are from the line, the more of the OSD is missing in gaps and the less the OSD and video overlap
+    /// after shifting.
+    pub fn analyze_timing(&self, video_info: &VideoInfo) -> TimingAnalysis {
+        analyze(&self.frame_timestamps(), video_info.duration, video_info.frame_rate)
+    }
+}
+
+/// Least squares line through the OSD timestamps against the frame numbers they'd have without the gaps.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct TimestampFit {
+    /// Where the line starts, the time of frame 0.
+    start_secs: f32,
+    /// Slope of the line.
+    interval_secs: f32,
+    /// Number of frames including the ones missing in gaps.
+    frame_count: u32,
+    /// Root mean square distance of the timestamps from the line, in frame intervals.
+    residual_frames: f32,
+}
+
+impl TimestampFit {
+    /// End of the last frame.
+    fn end_secs(&self) -> f32 {
+        self.start_secs + self.interval_secs * self.frame_count as f32
+    }
+}
+
+/// `None` with fewer than two frames.
+fn fit_timestamps(timestamps: &[u32], interval_millis: u32) -> Option<TimestampFit> {
+    if timestamps.len() < 2 || interval_millis == 0 {
+        return None;
+    }
+    // Frames missing in a gap still count, so the gap doesn't bend the line
+    let mut frame_number = 0;
+    let points = (0..timestamps.len())
+        .map(|i| {
+            if i > 0 {
+                let interval = timestamps[i] - timestamps[i - 1];
+                frame_number += ((interval as f64 / interval_millis as f64).round() as u32).max(1);
+            }
+            (frame_number as f64, timestamps[i] as f64 / 1000.0)
+        })
+        .collect::<Vec<_>>();
+
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|(x, _)| x).sum::<f64>() / n;
+    let mean_y = points.iter().map(|(_, y)| y).sum::<f64>() / n;
+    let covariance = points.iter().map(|(x, y)| (x - mean_x) * (y - mean_y)).sum::<f64>();
+    let variance = points.iter().map(|(x, _)| (x - mean_x).powi(2)).sum::<f64>();
+    let slope = covariance / variance;
+    let intercept = mean_y - slope * mean_x;
+    let residual = (points
+        .iter()
+        .map(|(x, y)| (y - intercept - slope * x).powi(2))
+        .sum::<f64>()
+        / n)
+        .sqrt();
+
+    (slope > 0.0).then(|| TimestampFit {
+        start_secs: intercept as f32,
+        interval_secs: slope as f32,
+        frame_count: frame_number + 1,
+        residual_frames: (residual / slope) as f32,
+    })
+}
+
+fn analyze(timestamps: &[u32], video_duration: Duration, video_frame_rate: f32) -> TimingAnalysis {
+    let (interval_millis, gaps) = find_gaps(timestamps);
+    let gap_millis = gaps.iter().map(|gap| gap.duration().as_millis() as u32).sum::<u32>();
+    let video_secs = video_duration.as_secs_f32();
+    let video_frame_secs = 1.0 / video_frame_rate.max(1.0);
+
+    let analysis = |drift, osd_playback_offset, osd_playback_speed_factor, confidence| TimingAnalysis {
+        drift,
+        osd_playback_offset,
+        osd_playback_speed_factor,
+        confidence,
+        osd_frame_interval: Duration::from_millis(interval_millis.into()),
+        gap_count: gaps.len(),
+        gap_duration: Duration::from_millis(gap_millis.into()),
+    };
+    let Some(fit) = fit_timestamps(timestamps, interval_millis) else {
+        return analysis(TimingDrift::Aligned, 0.0, 1.0, 0.0);
+    };
+
+    // A start within a video frame of zero is the start of the video, a later one is already in the timestamps
+    let start_secs = if fit.start_secs.abs() <= video_frame_secs {
+        0.0
+    } else {
+        fit.start_secs
+    };
+    let osd_secs = fit.end_secs() - start_secs;
+    let osd_end_secs = fit.end_secs();
+    let difference = video_secs - osd_end_secs;
+    let tolerance = (SYNC_TOLERANCE_FRAMES * video_frame_secs).max(2.0 * fit.interval_secs);
+
+    let (drift, osd_playback_offset, osd_playback_speed_factor, overlap) = if difference.abs() <= tolerance {
+        let drift = if gaps.is_empty() {
+            TimingDrift::Aligned
+        } else {
+            TimingDrift::Gaps
+        };
+        (drift, 0.0, 1.0, 1.0)
+    } else if difference.abs() > video_secs * MAX_CLOCK_DRIFT {
+        // A shorter OSD is shifted to end with the video, a longer one to start with it. It could also be missing the
+        // other end, the less the two overlap the less likely the shift is right.
+        let overlap = (osd_secs.min(video_secs) / osd_secs.max(video_secs)).clamp(0.0, 1.0);
+        (TimingDrift::ConstantOffset, difference, 1.0, overlap)
+    } else {
+        (TimingDrift::LinearDrift, 0.0, video_secs / osd_end_secs, 1.0)
+    };
+
+    let missing = gap_millis as f32 / 1000.0 / osd_secs;
+    let fit_quality = 1.0 / (1.0 + fit.residual_frames);
+    analysis(
+        drift,
+        osd_playback_offset,
+        osd_playback_speed_factor,
+        fit_quality * overlap * (1.0 - missing).clamp(0.2, 1.0),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn timestamps(count: u32, interval: u32) -> Vec<u32> {
+        (0..count).map(|i| i * interval).collect()
+    }
+
+    #[test]
+    fn matching_durations_are_aligned() {
+        let analysis = analyze(&timestamps(600, 100), Duration::from_secs(60), 60.0);
+        assert_eq!(analysis.drift, TimingDrift::Aligned);
+        assert_eq!(analysis.osd_playback_offset, 0.0);
+        assert_eq!(analysis.osd_playback_speed_factor, 1.0);
+        assert_eq!(analysis.confidence, 1.0);
+        assert_eq!(analysis.osd_frame_interval, Duration::from_millis(100));
+    }
+
+    #[test]
+    fn late_osd_is_a_constant_offset() {
+        let analysis = analyze(&timestamps(500, 100), Duration::from_secs(60), 60.0);
+        assert_eq!(analysis.drift, TimingDrift::ConstantOffset);
+        assert!((analysis.osd_playback_offset - 10.0).abs() < 0.01);
+        assert_eq!(analysis.osd_playback_speed_factor, 1.0);
+    }
+
+    #[test]
+    fn slow_osd_clock_is_a_linear_drift() {
+        let analysis = analyze(&timestamps(600, 101), Duration::from_secs(60), 60.0);
+        assert_eq!(analysis.drift, TimingDrift::LinearDrift);
+        assert_eq!(analysis.osd_playback_offset, 0.0);
+        assert!((analysis.osd_playback_speed_factor - 60.0 / 60.6).abs() < 0.001);
+        assert!(analysis.confidence > 0.99);
+    }
+
+    #[test]
+    fn long_osd_is_a_constant_offset() {
+        let analysis = analyze(&timestamps(700, 100), Duration::from_secs(60), 60.0);
+        assert_eq!(analysis.drift, TimingDrift::ConstantOffset);
+        assert!((analysis.osd_playback_offset + 10.0).abs() < 0.01);
+        assert_eq!(analysis.osd_playback_speed_factor, 1.0);
+        assert!((analysis.confidence - 60.0 / 70.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn fast_osd_clock_is_a_linear_drift() {
+        let analysis = analyze(&timestamps(600, 99), Duration::from_secs(59), 60.0);
+        assert_eq!(analysis.drift, TimingDrift::LinearDrift);
+        assert!((analysis.osd_playback_speed_factor - 59.0 / 59.4).abs() < 0.001);
+    }
+
+    #[test]
+    fn late_osd_timestamps_are_in_sync() {
+        let timestamps = (0..500).map(|i| 10_000 + i * 100).collect::<Vec<_>>();
+        let analysis = analyze(&timestamps, Duration::from_secs(60), 60.0);
+        assert_eq!(analysis.drift, TimingDrift::Aligned);
+        assert_eq!(analysis.osd_playback_offset, 0.0);
+    }
+
+    #[test]
+    fn jittery_timestamps_lower_the_confidence() {
+        let timestamps = (0..600).map(|i| i * 100 + i % 2 * 40).collect::<Vec<_>>();
+        let analysis = analyze(&timestamps, Duration::from_secs(60), 60.0);
+        assert_eq!(analysis.drift, TimingDrift::Aligned);
+        assert!(analysis.confidence < 0.9);
+    }
+
+    #[test]
+    fn single_frame_has_no_proposal() {
+        let analysis = analyze(&[0], Duration::from_secs(60), 60.0);
+        assert_eq!(analysis.confidence, 0.0);
+    }
+
+    #[test]
+    fn missing_frames_are_gaps() {
+        let mut timestamps = timestamps(600, 100);
+        timestamps.retain(|&t| !(10_000..15_000).contains(&t));
+        let analysis = analyze(&timestamps, Duration::from_secs(60), 60.0);
+        assert_eq!(analysis.drift, TimingDrift::Gaps);
+        assert_eq!(analysis.gap_count, 1);
+        assert_eq!(analysis.gap_duration, Duration::from_millis(5000));
+        assert!(analysis.confidence < 1.0);
+    }
+
+    #[test]
+    fn applying_drift_enables_playback_speed_adjustment() {
+        let analysis = analyze(&timestamps(600, 101), Duration::from_secs(60), 60.0);
+        let mut osd_options = OsdOptions::default();
+        analysis.apply(&mut osd_options);
+        assert!(osd_options.adjust_playback_speed);
+
+        // The fitted factor is rendered, not the ratio of the durations
+        let factor = osd_options.playback_speed_factor(Duration::from_secs(60), Duration::from_secs(61));
+        assert_eq!(factor, analysis.osd_playback_speed_factor);
+
+        let analysis = analyze(&timestamps(600, 100), Duration::from_secs(60), 60.0);
+        analysis.apply(&mut osd_options);
+        assert!(!osd_options.adjust_playback_speed);
+        assert_eq!(osd_options.fitted_playback_speed_factor, None);
+    }
+}
@@ -0,0 +1,69 @@
+use std::{collections::HashSet, io::Write, time::Duration};
+
+use super::{
+    error::OsdFileError,
+    frame::Frame,
+    glyph::{GridPosition, GridSize},
+    reader::HEADER_BYTES,
+};
+
+/// Writes OSD frames in the Walksnail `.osd` format.
+pub struct OsdWriter<W: Write> {
+    writer: W,
+    grid_size: GridSize,
+}
+
+impl<W: Write> OsdWriter<W> {
+    /// Writes the header. `header_bytes` are usually those of the file the frames were read from so the firmware and
+    /// fields that aren't decoded are kept.
+    pub fn new(mut writer: W, header_bytes: &[u8; HEADER_BYTES], grid_size: GridSize) -> Result<Self, OsdFileError> {
+        writer.write_all(header_bytes).map_err(write_error)?;
+        Ok(Self { writer, grid_size })
+    }
+
+    pub fn write_frame(&mut self, frame: &Frame) -> Result<(), OsdFileError> {
+        let record = frame.to_record(&self.grid_size)?;
+        self.writer.write_all(&record).map_err(write_error)
+    }
+
+    pub fn finish(mut self) -> Result<W, OsdFileError> {
+        self.writer.flush().map_err(write_error)?;
+        Ok(self.writer)
+    }
+}
+
+fn write_error(source: std::io::Error) -> OsdFileError {
+    OsdFileError::UnableToWriteFile { source }
+}
+
+/// Changes made to the frames when saving an OSD file with [`OsdFile::save`](super::OsdFile::save).
+#[derive(Debug, Clone, Default)]
+pub struct OsdEdit {
+    /// Frames before this time are left out.
+    pub start: Option<Duration>,
+    /// Frames at or after this time are left out.
+    pub end: Option<Duration>,
+    /// Added to the timestamps of the frames that are kept. Timestamps that would become negative are set to zero.
+    pub shift_millis: i64,
+    /// Cells that are blanked in every frame.
+    pub masked_grid_positions: HashSet<GridPosition>,
+}
+
+impl OsdEdit {
+    pub(super) fn keeps(&self, time_millis: u32) -> bool {
+        let time = Duration::from_millis(time_millis.into());
+        self.start.is_none_or(|start| time >= start) && self.end.is_none_or(|end| time < end)
+    }
+
+    /// The edited frame, `None` if the frame is trimmed.
+    pub(super) fn apply(&self, mut frame: Frame) -> Option<Frame> {
+        if !self.keeps(frame.time_millis) {
+            return None;
+        }
+        frame.time_millis = (frame.time_millis as i64 + self.shift_millis).clamp(0, u32::MAX as i64) as u32;
+        frame
+            .glyphs
+            .retain(|glyph| !self.masked_grid_positions.contains(&glyph.grid_position));
+        Some(frame)
+    }
+}
@@ -0,0 +1,137 @@
+use crossbeam_channel::Sender;
+use ffmpeg_sidecar::event::OutputVideoFrame;
+use image::{Rgba, RgbaImage};
+use rusttype::Font;
+
+use super::{
+    default_srt_font, iter::OverlayFrame, layer::LayerCache, overlay_osd, overlay_srt_data, overlay_srt_debug_data,
+    selector::SelectedOverlay, yuv420p_frame_size, YUV_PIX_FMT,
+};
+use crate::{
+    ffmpeg::{FromFfmpegMessage, RenderError, RenderJob},
+    font::FontFile,
+    osd::{self, GridSize, OsdOptions, DEFAULT_GRID_SIZE},
+    srt::{SrtFrame, SrtOptions},
+};
+
+/// Draws the OSD and SRT data on the video frames of a render. Every overlay worker has its own clone, the clones share
+/// the drawn layers.
+#[derive(Clone)]
+pub struct Compositor {
+    osd_grid_size: GridSize,
+    font_file: FontFile,
+    osd_options: OsdOptions,
+    srt_options: SrtOptions,
+    srt_font: Font<'static>,
+    chroma_key: Option<Rgba<u8>>,
+    layers: LayerCache,
+}
+
+impl Compositor {
+    /// `osd_options` are the options of the job with the playback speed factor for this video filled in.
+    pub fn new(job: &RenderJob, osd_options: &OsdOptions) -> Self {
+        let render_settings = job.render_settings();
+        let chroma_key = render_settings
+            .use_chroma_key
+            .then_some(render_settings.chroma_key)
+            .map(|c| {
+                Rgba([
+                    (c[0] * 255.0) as u8,
+                    (c[1] * 255.0) as u8,
+                    (c[2] * 255.0) as u8,
+                    (c[3] * 255.0) as u8,
+                ])
+            });
+        Self {
+            osd_grid_size: job
+                .osd_file()
+                .map(|osd_file| osd_file.grid_size.clone())
+                .unwrap_or(DEFAULT_GRID_SIZE),
+            font_file: job.font_file().clone(),
+            osd_options: osd_options.clone(),
+            srt_options: job.srt_options().clone(),
+            srt_font: default_srt_font(),
+            chroma_key,
+            layers: LayerCache::default(),
+        }
+    }
+
+    /// Draws the overlay on the video frame. A frame that doesn't match its size is reported and left out.
+    pub fn composite(
+        &self,
+        overlay_frame: OverlayFrame,
+        ffmpeg_sender: &Sender<FromFfmpegMessage>,
+    ) -> Option<OutputVideoFrame> {
+        let OverlayFrame {
+            mut video_frame,
+            overlay:
+                SelectedOverlay {
+                    layer_id,
+                    osd_frame,
+                    srt_frame,
+                },
+            ..
+        } = overlay_frame;
+        let layer = self
+            .layers
+            .get_or_draw(layer_id, video_frame.width, video_frame.height, |layer| {
+                self.draw_layer(layer, &osd_frame, srt_frame.as_ref())
+            });
+
+        // Decoded to the encoder's native format, blended straight into the luma and chroma planes
+        if video_frame.pix_fmt == YUV_PIX_FMT {
+            let (width, height, bytes) = (video_frame.width, video_frame.height, video_frame.data.len());
+            if bytes != yuv420p_frame_size(width, height) {
+                let error = RenderError::UnexpectedFrameSize { width, height, bytes };
+                ffmpeg_sender.send(FromFfmpegMessage::Error(error)).ok();
+                return None;
+            }
+            layer.blend_onto_yuv(width, height, &mut video_frame.data);
+            return Some(video_frame);
+        }
+
+        let mut frame_image = if let Some(chroma_key) = self.chroma_key {
+            // this should support alpha
+            RgbaImage::from_pixel(video_frame.width, video_frame.height, chroma_key)
+        } else {
+            let (width, height, bytes) = (video_frame.width, video_frame.height, video_frame.data.len());
+            match RgbaImage::from_raw(width, height, std::mem::take(&mut video_frame.data)) {
+                Some(frame_image) => frame_image,
+                None => {
+                    let error = RenderError::UnexpectedFrameSize { width, height, bytes };
+                    ffmpeg_sender.send(FromFfmpegMessage::Error(error)).ok();
+                    return None;
+                }
+            }
+        };
+
+        layer.blend_onto(&mut frame_image);
+        video_frame.data = frame_image.into_raw();
+        Some(video_frame)
+    }
+
+    /// Draws the OSD and SRT frame on a transparent layer the size of the video.
+    pub fn draw_layer(&self, layer: &mut RgbaImage, osd_frame: &osd::Frame, srt_frame: Option<&SrtFrame>) {
+        if !self.osd_options.no_osd {
+            overlay_osd(
+                layer,
+                osd_frame,
+                &self.osd_grid_size,
+                &self.font_file,
+                &self.osd_options,
+            );
+        }
+
+        if !self.srt_options.no_srt {
+            if let Some(frame) = srt_frame {
+                if let Some(srt_data) = &frame.data {
+                    overlay_srt_data(layer, srt_data, &self.srt_font, &self.srt_options);
+                }
+
+                if let Some(srt_debug_data) = &frame.debug_data {
+                    overlay_srt_debug_data(layer, srt_debug_data, &self.srt_font, &self.srt_options);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,234 @@
+use std::{
+    collections::VecDeque,
+    ops::Range,
+    sync::{Arc, Mutex, MutexGuard, OnceLock},
+};
+
+use image::RgbaImage;
+
+use super::yuv::YuvLayer;
+
+/// How many layers the overlay workers share. They work on consecutive video frames, which show only a few different
+/// OSD and SRT frames. At 4K a layer is 33 MB.
+const MAX_CACHED_LAYERS: usize = 8;
+const MAX_SPARE_IMAGES: usize = 2;
+/// Bytes of a layer that are checked for visible pixels at once, 16 pixels.
+const SCAN_BLOCK_BYTES: usize = 64;
+
+/// The OSD and SRT data of a video frame drawn on a transparent image. It is drawn once and blended on every video frame
+/// that shows the same data.
+pub struct OverlayLayer {
+    image: RgbaImage,
+    /// Runs of visible pixels, the transparent parts of the layer are skipped when blending.
+    spans: Vec<Span>,
+    /// The layer converted for yuv420p frames, once it's blended on one.
+    yuv: OnceLock<YuvLayer>,
+}
+
+/// Consecutive pixels of a layer that are either all opaque or all partly transparent, as a range of bytes.
+#[derive(Debug, PartialEq)]
+struct Span {
+    bytes: Range<usize>,
+    opaque: bool,
+}
+
+impl OverlayLayer {
+    pub fn new(image: RgbaImage) -> Self {
+        let mut spans: Vec<Span> = vec![];
+        for (block_index, block) in image.chunks(SCAN_BLOCK_BYTES).enumerate() {
+            // Most of a layer is empty, skip it a block at a time
+            if block == &[0; SCAN_BLOCK_BYTES][..block.len()] {
+                continue;
+            }
+            for (index, pixel) in block.chunks_exact(4).enumerate() {
+                let opaque = match pixel[3] {
+                    0 => continue,
+                    255 => true,
+                    _ => false,
+                };
+                let start = block_index * SCAN_BLOCK_BYTES + index * 4;
+                match spans.last_mut() {
+                    Some(span) if span.bytes.end == start && span.opaque == opaque => span.bytes.end += 4,
+                    _ => spans.push(Span {
+                        bytes: start..start + 4,
+                        opaque,
+                    }),
+                }
+            }
+        }
+        Self {
+            image,
+            spans,
+            yuv: OnceLock::new(),
+        }
+    }
+
+    /// Blends the layer over `frame`. Frames that aren't the size of the layer are left as they are.
+    pub fn blend_onto(&self, frame: &mut RgbaImage) {
+        if frame.dimensions() != self.image.dimensions() {
+            return;
+        }
+        let (layer, frame): (&[u8], &mut [u8]) = (&self.image, frame);
+        for span in &self.spans {
+            let top = &layer[span.bytes.clone()];
+            let bottom = &mut frame[span.bytes.clone()];
+            if span.opaque {
+                bottom.copy_from_slice(top);
+            } else {
+                for (top, bottom) in top.chunks_exact(4).zip(bottom.chunks_exact_mut(4)) {
+                    blend_pixel(top, bottom);
+                }
+            }
+        }
+    }
+
+    /// Blends the layer over a yuv420p `frame`. Frames that aren't the size of the layer are left as they are.
+    pub fn blend_onto_yuv(&self, width: u32, height: u32, frame: &mut [u8]) {
+        self.yuv
+            .get_or_init(|| {
+                let pixels = self.spans.iter().map(|span| span.bytes.start / 4..span.bytes.end / 4);
+                YuvLayer::new(&self.image, pixels)
+            })
+            .blend_onto(width, height, frame);
+    }
+}
+
+/// Source over blending of a partly transparent pixel.
+fn blend_pixel(top: &[u8], bottom: &mut [u8]) {
+    // Video frames are opaque
+    if bottom[3] == 255 {
+        let alpha = top[3] as u32;
+        for channel in 0..3 {
+            bottom[channel] =
+                ((top[channel] as u32 * alpha + bottom[channel] as u32 * (255 - alpha) + 127) / 255) as u8;
+        }
+        return;
+    }
+
+    let top_alpha = top[3] as f32 / 255.0;
+    let bottom_alpha = bottom[3] as f32 / 255.0 * (1.0 - top_alpha);
+    let alpha = top_alpha + bottom_alpha;
+    for channel in 0..3 {
+        let color = (top[channel] as f32 * top_alpha + bottom[channel] as f32 * bottom_alpha) / alpha;
+        bottom[channel] = color.round() as u8;
+    }
+    bottom[3] = (alpha * 255.0).round() as u8;
+}
+
+#[derive(Default)]
+struct CacheState {
+    layers: VecDeque<(u64, Arc<OverlayLayer>)>,
+    /// Images of evicted layers. Drawing on an image that is already in memory is a lot faster than on a new one.
+    spare_images: Vec<RgbaImage>,
+}
+
+/// The layers drawn most recently, by layer id. Shared by the overlay workers of a render.
+#[derive(Clone, Default)]
+pub struct LayerCache {
+    state: Arc<Mutex<CacheState>>,
+}
+
+impl LayerCache {
+    /// The layer with `id`. If it isn't cached `draw` draws it on a transparent image of `width` x `height`. Workers
+    /// that miss the same layer at once both draw it, the lock isn't held while drawing.
+    pub fn get_or_draw(
+        &self,
+        id: u64,
+        width: u32,
+        height: u32,
+        draw: impl FnOnce(&mut RgbaImage),
+    ) -> Arc<OverlayLayer> {
+        let spare_image = {
+            let mut state = self.lock();
+            if let Some((_, layer)) = state.layers.iter().find(|(layer_id, _)| *layer_id == id) {
+                return layer.clone();
+            }
+            state.spare_images.pop()
+        };
+
+        let mut image = match spare_image {
+            Some(mut image) if image.dimensions() == (width, height) => {
+                image.fill(0);
+                image
+            }
+            _ => RgbaImage::new(width, height),
+        };
+        draw(&mut image);
+        let layer = Arc::new(OverlayLayer::new(image));
+
+        let mut state = self.lock();
+        if !state.layers.iter().any(|(layer_id, _)| *layer_id == id) {
+            if state.layers.len() >= MAX_CACHED_LAYERS {
+                let evicted = state.layers.pop_front().and_then(|(_, layer)| Arc::into_inner(layer));
+                if let Some(evicted) = evicted.filter(|_| state.spare_images.len() < MAX_SPARE_IMAGES) {
+                    state.spare_images.push(evicted.image);
+                }
+            }
+            state.layers.push_back((id, layer.clone()));
+        }
+        layer
+    }
+
+    fn lock(&self) -> MutexGuard<'_, CacheState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn layer_is_blended_over_frame() {
+        let mut layer = RgbaImage::new(4, 4);
+        layer.put_pixel(1, 1, Rgba([255, 0, 0, 255]));
+        layer.put_pixel(2, 2, Rgba([255, 255, 255, 128]));
+        layer.put_pixel(3, 1, Rgba([255, 0, 0, 255]));
+        layer.put_pixel(0, 2, Rgba([255, 0, 0, 255]));
+        let layer = OverlayLayer::new(layer);
+        assert_eq!(
+            layer.spans,
+            [
+                Span {
+                    bytes: 20..24,
+                    opaque: true
+                },
+                Span {
+                    bytes: 28..36,
+                    opaque: true
+                },
+                Span {
+                    bytes: 40..44,
+                    opaque: false
+                },
+            ]
+        );
+
+        let mut frame = RgbaImage::from_pixel(4, 4, Rgba([0, 0, 0, 255]));
+        layer.blend_onto(&mut frame);
+        assert_eq!(frame.get_pixel(0, 0), &Rgba([0, 0, 0, 255]));
+        assert_eq!(frame.get_pixel(1, 1), &Rgba([255, 0, 0, 255]));
+        assert_eq!(frame.get_pixel(2, 2), &Rgba([128, 128, 128, 255]));
+    }
+
+    #[test]
+    fn cached_layer_is_not_drawn_again() {
+        let cache = LayerCache::default();
+        cache.get_or_draw(1, 1, 1, |image| image.put_pixel(0, 0, Rgba([255; 4])));
+        cache.get_or_draw(1, 1, 1, |_| panic!("layer 1 is cached"));
+        for id in 2..=MAX_CACHED_LAYERS as u64 + 1 {
+            cache.get_or_draw(id, 1, 1, |image| image.put_pixel(0, 0, Rgba([255; 4])));
+        }
+
+        let mut drawn = false;
+        cache.get_or_draw(1, 1, 1, |image| {
+            // The image of an evicted layer is reused
+            assert_eq!(image.get_pixel(0, 0), &Rgba([0; 4]));
+            assert_eq!(cache.lock().spare_images.len(), 0);
+            drawn = true;
+        });
+        assert!(drawn);
+    }
+}
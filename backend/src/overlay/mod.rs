@@ -1,7 +1,15 @@
+mod compositor;
 mod iter;
+mod layer;
 mod osd;
+mod selector;
 mod srt;
+mod yuv;
 
-pub use iter::FrameOverlayIter;
+pub use compositor::Compositor;
+pub use iter::{FrameOverlayIter, OverlayFrame};
+pub use layer::{LayerCache, OverlayLayer};
 pub use osd::{get_ideal_character_size, overlay_osd};
-pub use srt::{overlay_srt_data, overlay_srt_debug_data};
+pub use selector::{OverlaySelector, SelectedOverlay};
+pub use srt::{default_srt_font, overlay_srt_data, overlay_srt_debug_data};
+pub use yuv::{rgba_to_yuva420p, yuv420p_frame_size, yuv420p_to_rgba, YUVA_PIX_FMT, YUV_PIX_FMT};
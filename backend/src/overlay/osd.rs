@@ -4,7 +4,7 @@ use image::{imageops::overlay, RgbaImage};
 
 use crate::{
     font::{self},
-    osd::{self, OsdOptions},
+    osd::{self, GridSize, OsdOptions},
     util::Dimension,
 };
 
@@ -59,9 +59,9 @@ fn fast_overlay(bottom: &mut RgbaImage, top: &RgbaImage, x: i64, y: i64) {
     }
 }
 
-pub fn get_ideal_character_size(frame_width: u32, frame_height: u32) -> Dimension<u32> {
-    let char_height = frame_height / osd::OSD_GRID_HEIGHT;
-    let char_width = frame_width / osd::OSD_GRID_WIDTH;
+pub fn get_ideal_character_size(frame_width: u32, frame_height: u32, grid_size: &GridSize) -> Dimension<u32> {
+    let char_height = frame_height / grid_size.height;
+    let char_width = frame_width / grid_size.width;
     Dimension {
         width: char_width,
         height: char_height,
@@ -69,20 +69,25 @@ pub fn get_ideal_character_size(frame_width: u32, frame_height: u32) -> Dimensio
 }
 
 #[inline]
-pub fn overlay_osd(image: &mut RgbaImage, osd_frame: &osd::Frame, font: &font::FontFile, osd_options: &OsdOptions) {
-    // TODO: check if this can be run in parallel
+pub fn overlay_osd(
+    image: &mut RgbaImage,
+    osd_frame: &osd::Frame,
+    grid_size: &GridSize,
+    font: &font::FontFile,
+    osd_options: &OsdOptions,
+) {
     let character_size_class = osd_options
         .character_size_class
         .clone()
         .unwrap_or(font::CharacterSizeClass::Normal);
 
-    let char_desired_size = get_ideal_character_size(image.width(), image.height());
+    let char_desired_size = get_ideal_character_size(image.width(), image.height(), grid_size);
 
     let _start = Instant::now();
     let mut _rendered_chars = 0;
 
     for character in &osd_frame.glyphs {
-        if character.index == 0 || osd_options.get_mask(&character.grid_position) {
+        if character.index == 0 || osd_options.get_mask(&character.grid_position, grid_size) {
             continue;
         }
 
@@ -97,14 +102,13 @@ pub fn overlay_osd(image: &mut RgbaImage, osd_frame: &osd::Frame, font: &font::F
             // INFO
             // HD OSD defaults to a 53 column x 20 row grid of OSD elements.
             // When the VTX is online BetaFlight will query via MSP Displayport to determine the optimum grid size and may update the grid to match what is supported by the digital VTX system
-            const ROW_COUNT: u32 = 20;
-            const COL_COUNT: u32 = 53;
+            // The grid the flight controller used is taken from the OSD file header.
 
             // Important: integer division here.
-            let single_glyph_x_offset = image.width() / COL_COUNT;
-            let single_glyph_y_offset = image.height() / ROW_COUNT;
-            let remainder_x_offset = image.width() % COL_COUNT / 2;
-            let remainder_y_offset = image.height() % ROW_COUNT / 2;
+            let single_glyph_x_offset = image.width() / grid_size.width;
+            let single_glyph_y_offset = image.height() / grid_size.height;
+            let remainder_x_offset = image.width() % grid_size.width / 2;
+            let remainder_y_offset = image.height() % grid_size.height / 2;
 
             let x_raw = remainder_x_offset + grid_position.x * single_glyph_x_offset;
             let y_raw = remainder_y_offset + grid_position.y * single_glyph_y_offset;
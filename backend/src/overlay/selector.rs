@@ -0,0 +1,160 @@
+use std::{fs::File, io::BufReader, sync::Arc, time::Duration};
+
+use crossbeam_channel::Sender;
+
+use crate::{
+    ffmpeg::{FromFfmpegMessage, RenderError, RenderJob},
+    osd::{self, GapBehavior, OsdOptions, OsdReader, DEFAULT_GRID_SIZE},
+    srt::{SrtFile, SrtFrame},
+};
+
+/// The OSD and SRT data shown at a point of the video.
+pub struct SelectedOverlay {
+    /// Changes whenever the OSD or SRT frame changes, points with the same layer id show the same overlay.
+    pub layer_id: u64,
+    pub osd_frame: Arc<osd::Frame>,
+    pub srt_frame: Option<SrtFrame>,
+}
+
+/// Looks up the OSD and SRT data shown at points of the video, applying the playback offset and speed and the
+/// behavior in OSD gaps. The points have to be in video order, the OSD frames are streamed from disk.
+pub struct OverlaySelector {
+    osd_frames: Option<OsdReader<BufReader<File>>>,
+    /// Index of [`OverlaySelector::current_osd_frame`], `None` before the first OSD frame.
+    current_osd_index: Option<u32>,
+    current_osd_frame: Arc<osd::Frame>,
+    /// Seconds of OSD time after a frame before the OSD is considered to have a gap.
+    osd_gap_threshold: Option<f32>,
+    no_osd_marker: Arc<osd::Frame>,
+    blank_osd_frame: Arc<osd::Frame>,
+    srt_file: Option<SrtFile>,
+    osd_options: OsdOptions,
+    /// The OSD and SRT frame of the current layer.
+    layer: (Arc<osd::Frame>, Option<usize>),
+    layer_id: u64,
+    ffmpeg_sender: Sender<FromFfmpegMessage>,
+}
+
+impl OverlaySelector {
+    /// `osd_options` are the options of the job with the playback speed factor for this video filled in. The OSD frames
+    /// are streamed from `osd_frames`, `None` renders without OSD frames. Read errors are sent to `ffmpeg_sender`.
+    pub fn new(
+        job: &RenderJob,
+        osd_frames: Option<OsdReader<BufReader<File>>>,
+        osd_options: &OsdOptions,
+        ffmpeg_sender: Sender<FromFfmpegMessage>,
+    ) -> Self {
+        let osd_grid_size = job
+            .osd_file()
+            .map(|osd_file| osd_file.grid_size.clone())
+            .unwrap_or(DEFAULT_GRID_SIZE);
+        Self {
+            osd_frames,
+            current_osd_index: None,
+            current_osd_frame: Arc::default(),
+            osd_gap_threshold: job
+                .osd_file()
+                .and_then(|osd_file| osd_file.gap_threshold())
+                .map(|threshold| threshold.as_secs_f32()),
+            no_osd_marker: Arc::new(osd::Frame::centered_text("NO OSD", &osd_grid_size)),
+            blank_osd_frame: Arc::default(),
+            srt_file: job.srt_file().cloned(),
+            osd_options: osd_options.clone(),
+            layer: Default::default(),
+            layer_id: 0,
+            ffmpeg_sender,
+        }
+    }
+
+    /// The overlay shown `video_secs` into the video.
+    pub fn select(&mut self, video_secs: f32) -> SelectedOverlay {
+        // Look up the OSD frame shown at this point of the video, it's only read from disk when it changes
+        let osd_secs = osd_secs_at(video_secs, &self.osd_options);
+        let osd_index = (osd_secs >= 0.0)
+            .then(|| Duration::from_secs_f32(osd_secs))
+            .and_then(|osd_time| self.osd_frames.as_ref()?.frame_index_at(osd_time));
+        if osd_index != self.current_osd_index {
+            self.current_osd_frame = Arc::new(
+                osd_index
+                    .and_then(|index| read_osd_frame(&mut self.osd_frames, index, &self.ffmpeg_sender))
+                    .unwrap_or_default(),
+            );
+            self.current_osd_index = osd_index;
+        }
+
+        let osd_frame = match self.osd_options.gap_behavior {
+            GapBehavior::Hold => &self.current_osd_frame,
+            _ if !in_osd_gap(
+                osd_secs,
+                self.osd_frames.as_ref(),
+                self.current_osd_index,
+                &self.current_osd_frame,
+                self.osd_gap_threshold,
+            ) =>
+            {
+                &self.current_osd_frame
+            }
+            GapBehavior::Blank => &self.blank_osd_frame,
+            GapBehavior::NoOsdMarker => &self.no_osd_marker,
+        };
+
+        let srt_index = self
+            .srt_file
+            .as_ref()
+            .and_then(|srt_file| srt_file.frame_index_at(Duration::from_secs_f32(video_secs.max(0.0))));
+
+        if !Arc::ptr_eq(osd_frame, &self.layer.0) || srt_index != self.layer.1 {
+            self.layer = (osd_frame.clone(), srt_index);
+            self.layer_id += 1;
+        }
+
+        SelectedOverlay {
+            layer_id: self.layer_id,
+            osd_frame: osd_frame.clone(),
+            srt_frame: self
+                .srt_file
+                .as_ref()
+                .zip(srt_index)
+                .map(|(srt_file, index)| srt_file.frames[index].clone()),
+        }
+    }
+}
+
+/// The OSD time shown at this point of the video, negative before the OSD starts.
+fn osd_secs_at(video_secs: f32, osd_options: &OsdOptions) -> f32 {
+    video_secs / osd_options.osd_playback_speed_factor - osd_options.osd_playback_offset
+}
+
+/// Whether the OSD is past the point where the frame after the current one was expected. There is no gap before the
+/// first or after the last frame.
+fn in_osd_gap(
+    osd_secs: f32,
+    osd_frames: Option<&OsdReader<BufReader<File>>>,
+    current_osd_index: Option<u32>,
+    current_osd_frame: &osd::Frame,
+    osd_gap_threshold: Option<f32>,
+) -> bool {
+    let (Some(osd_frames), Some(index), Some(threshold)) = (osd_frames, current_osd_index, osd_gap_threshold) else {
+        return false;
+    };
+    index + 1 < osd_frames.frame_count() && osd_secs > current_osd_frame.time_millis as f32 / 1000.0 + threshold
+}
+
+/// Reads an OSD frame from disk. A read error is reported as a warning and ends the OSD, the video keeps rendering.
+fn read_osd_frame(
+    osd_frames: &mut Option<OsdReader<BufReader<File>>>,
+    index: u32,
+    ffmpeg_sender: &Sender<FromFfmpegMessage>,
+) -> Option<osd::Frame> {
+    match osd_frames.as_mut()?.frame(index).transpose()? {
+        Ok(frame) => Some(frame),
+        Err(e) => {
+            tracing::warn!("Failed to read OSD frame: {}", e);
+            *osd_frames = None;
+            ffmpeg_sender
+                .send(FromFfmpegMessage::Warning(RenderError::OsdFile { source: e }))
+                .ok();
+            None
+        }
+    }
+}
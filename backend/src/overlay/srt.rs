@@ -4,6 +4,12 @@ use rusttype::{point, Font, Scale};
 
 use crate::srt::{SrtDebugFrameData, SrtFrameData, SrtOptions};
 
+/// Monospace font used to draw the SRT data, bundled with the app so renders look the same on every machine.
+pub fn default_srt_font() -> Font<'static> {
+    Font::try_from_bytes(include_bytes!("../../../resources/fonts/AzeretMono-Regular.ttf"))
+        .expect("Bundled SRT font is valid")
+}
+
 /// Fast, adaptive buffered overlay using font metrics and no hardcoded values
 pub fn overlay_srt_buffered(image: &mut RgbaImage, srt_string: &str, font: &rusttype::Font, srt_options: &SrtOptions) {
     let image_dimensions = image.dimensions();
@@ -0,0 +1,252 @@
+use image::RgbaImage;
+
+/// Pixel format of decoded frames that are blended without converting them to RGBA.
+pub const YUV_PIX_FMT: &str = "yuv420p";
+
+/// Number of bytes of a yuv420p frame, a luma plane and two chroma planes of half the width and height.
+pub fn yuv420p_frame_size(width: u32, height: u32) -> usize {
+    let (chroma_width, chroma_height) = (width.div_ceil(2) as usize, height.div_ceil(2) as usize);
+    width as usize * height as usize + 2 * chroma_width * chroma_height
+}
+
+/// A layer converted to the planes of a yuv420p frame. Only the visible pixels are kept, as runs of consecutive
+/// pixels of a plane.
+pub struct YuvLayer {
+    width: u32,
+    height: u32,
+    /// Luma and alpha of the visible pixels.
+    luma: Vec<Run<[u8; 2]>>,
+    /// Chroma and alpha of every 2x2 block with visible pixels.
+    chroma: Vec<Run<[u8; 3]>>,
+}
+
+struct Run<T> {
+    start: usize,
+    values: Vec<T>,
+}
+
+impl YuvLayer {
+    /// Converts the `pixels` of `image`, given as ranges of pixel indices in row order.
+    pub(super) fn new(image: &RgbaImage, pixels: impl Iterator<Item = std::ops::Range<usize>>) -> Self {
+        let (width, height) = image.dimensions();
+        let chroma_width = width.div_ceil(2) as usize;
+        let rgba: &[u8] = image;
+
+        let mut luma: Vec<Run<[u8; 2]>> = vec![];
+        // Alpha weighted chroma of the visible pixels by chroma index, summed up per block below
+        let mut chroma_samples = vec![];
+        for range in pixels {
+            for index in range {
+                let pixel = &rgba[index * 4..index * 4 + 4];
+                let (y, u, v) = rgb_to_yuv(pixel[0], pixel[1], pixel[2]);
+                let alpha = pixel[3];
+                push_value(&mut luma, index, [y, alpha]);
+
+                let (x, row) = (index % width as usize, index / width as usize);
+                let chroma_index = row / 2 * chroma_width + x / 2;
+                chroma_samples.push((
+                    chroma_index,
+                    [u as u32 * alpha as u32, v as u32 * alpha as u32, alpha as u32],
+                ));
+            }
+        }
+
+        chroma_samples.sort_unstable_by_key(|(index, _)| *index);
+        let mut chroma: Vec<Run<[u8; 3]>> = vec![];
+        let mut samples = chroma_samples.into_iter().peekable();
+        while let Some((index, mut sum)) = samples.next() {
+            while let Some((_, next)) = samples.next_if(|(next_index, _)| *next_index == index) {
+                sum = [sum[0] + next[0], sum[1] + next[1], sum[2] + next[2]];
+            }
+            // Blocks at the right and bottom edge of frames with an odd size have fewer pixels
+            let (x, row) = (index % chroma_width, index / chroma_width);
+            let block_pixels = (width as usize - x * 2).min(2) as u32 * (height as usize - row * 2).min(2) as u32;
+            let alpha = (sum[2] + block_pixels / 2) / block_pixels;
+            push_value(
+                &mut chroma,
+                index,
+                [
+                    ((sum[0] + sum[2] / 2) / sum[2]) as u8,
+                    ((sum[1] + sum[2] / 2) / sum[2]) as u8,
+                    alpha as u8,
+                ],
+            );
+        }
+
+        Self {
+            width,
+            height,
+            luma,
+            chroma,
+        }
+    }
+
+    /// Blends the layer over a yuv420p frame. Frames that aren't the size of the layer are left as they are.
+    pub fn blend_onto(&self, width: u32, height: u32, frame: &mut [u8]) {
+        if (width, height) != (self.width, self.height) || frame.len() != yuv420p_frame_size(width, height) {
+            return;
+        }
+        let chroma_size = width.div_ceil(2) as usize * height.div_ceil(2) as usize;
+        let (luma_plane, chroma_planes) = frame.split_at_mut(width as usize * height as usize);
+        let (u_plane, v_plane) = chroma_planes.split_at_mut(chroma_size);
+
+        for run in &self.luma {
+            let bottom = &mut luma_plane[run.start..run.start + run.values.len()];
+            for ([y, alpha], bottom) in run.values.iter().zip(bottom) {
+                *bottom = blend(*y, *alpha, *bottom);
+            }
+        }
+        for run in &self.chroma {
+            let range = run.start..run.start + run.values.len();
+            for ([u, v, alpha], (bottom_u, bottom_v)) in run
+                .values
+                .iter()
+                .zip(u_plane[range.clone()].iter_mut().zip(&mut v_plane[range]))
+            {
+                *bottom_u = blend(*u, *alpha, *bottom_u);
+                *bottom_v = blend(*v, *alpha, *bottom_v);
+            }
+        }
+    }
+}
+
+/// Appends a value for the pixel at `index`, continuing the last run if it ends right before it.
+fn push_value<T>(runs: &mut Vec<Run<T>>, index: usize, value: T) {
+    match runs.last_mut() {
+        Some(run) if run.start + run.values.len() == index => run.values.push(value),
+        _ => runs.push(Run {
+            start: index,
+            values: vec![value],
+        }),
+    }
+}
+
+fn blend(top: u8, alpha: u8, bottom: u8) -> u8 {
+    let alpha = alpha as u32;
+    ((top as u32 * alpha + bottom as u32 * (255 - alpha) + 127) / 255) as u8
+}
+
+/// BT.601 limited range, the conversion ffmpeg uses for untagged RGB input. The overlay looks the same as when the
+/// frames were converted to RGBA and back.
+fn rgb_to_yuv(r: u8, g: u8, b: u8) -> (u8, u8, u8) {
+    let (r, g, b) = (r as i32, g as i32, b as i32);
+    let y = ((66 * r + 129 * g + 25 * b + 128) >> 8) + 16;
+    let u = ((-38 * r - 74 * g + 112 * b + 128) >> 8) + 128;
+    let v = ((112 * r - 94 * g - 18 * b + 128) >> 8) + 128;
+    (y as u8, u as u8, v as u8)
+}
+
+/// Pixel format of the overlay layers piped to ffmpeg, a yuv420p frame followed by a full resolution alpha plane.
+pub const YUVA_PIX_FMT: &str = "yuva420p";
+
+/// Converts a layer to a yuva420p frame for ffmpeg's overlay filter. The chroma of a 2x2 block is the average of its
+/// pixels weighted by their alpha like in [`YuvLayer`], fully transparent blocks are neutral.
+pub fn rgba_to_yuva420p(image: &RgbaImage) -> Vec<u8> {
+    let (width, height) = image.dimensions();
+    let luma_size = width as usize * height as usize;
+    let chroma_width = width.div_ceil(2) as usize;
+    let chroma_size = chroma_width * height.div_ceil(2) as usize;
+    let mut frame = vec![0; yuv420p_frame_size(width, height) + luma_size];
+    let (luma_plane, planes) = frame.split_at_mut(luma_size);
+    let (u_plane, planes) = planes.split_at_mut(chroma_size);
+    let (v_plane, alpha_plane) = planes.split_at_mut(chroma_size);
+
+    // Alpha weighted chroma and the alpha of each block
+    let mut chroma_sums = vec![[0u32; 3]; chroma_size];
+    for (index, pixel) in image.pixels().enumerate() {
+        let [r, g, b, alpha] = pixel.0;
+        let (y, u, v) = rgb_to_yuv(r, g, b);
+        luma_plane[index] = y;
+        alpha_plane[index] = alpha;
+
+        let (x, row) = (index % width as usize, index / width as usize);
+        let sum = &mut chroma_sums[row / 2 * chroma_width + x / 2];
+        *sum = [
+            sum[0] + u as u32 * alpha as u32,
+            sum[1] + v as u32 * alpha as u32,
+            sum[2] + alpha as u32,
+        ];
+    }
+    for (index, [u, v, alpha]) in chroma_sums.into_iter().enumerate() {
+        let average = |sum: u32| (sum + alpha / 2).checked_div(alpha).map_or(128, |value| value as u8);
+        (u_plane[index], v_plane[index]) = (average(u), average(v));
+    }
+    frame
+}
+
+/// Converts a yuv420p frame to RGBA, e.g. to show it in the live view.
+pub fn yuv420p_to_rgba(width: u32, height: u32, frame: &[u8]) -> Option<RgbaImage> {
+    if frame.len() != yuv420p_frame_size(width, height) {
+        return None;
+    }
+    let chroma_width = width.div_ceil(2) as usize;
+    let chroma_size = chroma_width * height.div_ceil(2) as usize;
+    let (luma_plane, chroma_planes) = frame.split_at(width as usize * height as usize);
+    let (u_plane, v_plane) = chroma_planes.split_at(chroma_size);
+
+    Some(RgbaImage::from_fn(width, height, |x, y| {
+        let chroma_index = y as usize / 2 * chroma_width + x as usize / 2;
+        let c = luma_plane[(y * width + x) as usize] as i32 - 16;
+        let d = u_plane[chroma_index] as i32 - 128;
+        let e = v_plane[chroma_index] as i32 - 128;
+        let clamp = |value: i32| (value >> 8).clamp(0, 255) as u8;
+        image::Rgba([
+            clamp(298 * c + 409 * e + 128),
+            clamp(298 * c - 100 * d - 208 * e + 128),
+            clamp(298 * c + 516 * d + 128),
+            255,
+        ])
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::Rgba;
+
+    use super::*;
+
+    #[test]
+    fn layer_is_blended_into_planes() {
+        let mut image = RgbaImage::new(4, 2);
+        image.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+        image.put_pixel(1, 0, Rgba([255, 255, 255, 255]));
+        image.put_pixel(0, 1, Rgba([255, 255, 255, 255]));
+        image.put_pixel(1, 1, Rgba([255, 255, 255, 255]));
+        image.put_pixel(3, 1, Rgba([255, 0, 0, 128]));
+        let layer = YuvLayer::new(&image, [0..2, 4..6, 7..8].into_iter());
+
+        // Black frame
+        let mut frame = [&[16; 8][..], &[128; 4]].concat();
+        layer.blend_onto(4, 2, &mut frame);
+        assert_eq!(frame[..8], [235, 235, 16, 16, 235, 235, 16, 49]);
+        // The white block keeps neutral chroma, a quarter of the second block is half covered in red
+        assert_eq!(frame[8..], [128, 123, 128, 142]);
+
+        let rgba = yuv420p_to_rgba(4, 2, &frame).unwrap();
+        assert_eq!(rgba.get_pixel(0, 0), &Rgba([255, 255, 255, 255]));
+        assert_eq!(rgba.get_pixel(1, 1), &Rgba([255, 255, 255, 255]));
+    }
+
+    #[test]
+    fn layer_is_converted_to_yuva420p() {
+        let mut image = RgbaImage::new(4, 2);
+        image.put_pixel(0, 0, Rgba([255, 255, 255, 255]));
+        image.put_pixel(3, 1, Rgba([255, 0, 0, 128]));
+        let frame = rgba_to_yuva420p(&image);
+
+        assert_eq!(frame.len(), yuv420p_frame_size(4, 2) + 8);
+        assert_eq!(frame[..8], [235, 16, 16, 16, 16, 16, 16, 82]);
+        // Only the visible pixels count for the chroma of a block
+        assert_eq!(frame[8..12], [128, 90, 128, 240]);
+        assert_eq!(frame[12..], [255, 0, 0, 0, 0, 0, 0, 128]);
+    }
+
+    #[test]
+    fn frames_of_other_sizes_are_left_alone() {
+        let layer = YuvLayer::new(&RgbaImage::from_pixel(2, 2, Rgba([255; 4])), [0..4].into_iter());
+        let mut frame = vec![16; yuv420p_frame_size(4, 2)];
+        layer.blend_onto(4, 2, &mut frame);
+        assert!(frame.iter().all(|value| *value == 16));
+        assert_eq!(yuv420p_frame_size(3, 3), 9 + 2 * 4);
+    }
+}
@@ -0,0 +1,74 @@
+use std::{path::PathBuf, time::Duration};
+
+use crate::{
+    ffmpeg::{Encoder, RenderJob, RenderJobBuilder, RenderSettings},
+    osd::OsdOptions,
+    srt::SrtOptions,
+    util::{default_output_video_path, matching_file_with_extension},
+};
+
+/// One video in the render queue together with the OSD and SRT files that belong to it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct QueueJob {
+    pub input_video: PathBuf,
+    pub osd_file: Option<PathBuf>,
+    pub srt_file: Option<PathBuf>,
+    pub output_video: PathBuf,
+}
+
+impl QueueJob {
+    /// Creates a job for `input_video`, picking up the `.osd` and `.srt` files with the same name if they exist.
+    pub fn from_video(input_video: PathBuf) -> Self {
+        let existing_file =
+            |extension| Some(matching_file_with_extension(&input_video, extension)).filter(|f| f.exists());
+        Self {
+            osd_file: existing_file("osd"),
+            srt_file: existing_file("srt"),
+            output_video: default_output_video_path(&input_video),
+            input_video,
+        }
+    }
+
+    pub fn render_job(&self, settings: &QueueSettings) -> RenderJobBuilder {
+        RenderJob::builder()
+            .input_video(self.input_video.clone())
+            .output_video(self.output_video.clone())
+            .osd_file(self.osd_file.clone())
+            .srt_file(self.srt_file.clone())
+            .font_file(settings.font_file.clone())
+            .encoder(settings.encoder.clone())
+            .osd_options(settings.osd_options.clone())
+            .srt_options(settings.srt_options.clone())
+            .render_settings(settings.render_settings.clone())
+    }
+}
+
+/// Settings shared by all jobs in the queue.
+#[derive(Debug, Clone)]
+pub struct QueueSettings {
+    pub ffmpeg_path: PathBuf,
+    pub ffprobe_path: PathBuf,
+    pub font_file: PathBuf,
+    pub osd_options: OsdOptions,
+    pub srt_options: SrtOptions,
+    pub render_settings: RenderSettings,
+    pub encoder: Encoder,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum JobStatus {
+    Pending,
+    Running {
+        progress_pct: f32,
+        time_remaining: Option<Duration>,
+    },
+    Completed,
+    Failed(String),
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(self, JobStatus::Completed | JobStatus::Failed(_) | JobStatus::Cancelled)
+    }
+}
@@ -0,0 +1,5 @@
+mod job;
+mod render_queue;
+
+pub use job::{JobStatus, QueueJob, QueueSettings};
+pub use render_queue::{QueueEvent, RenderQueue};
@@ -0,0 +1,337 @@
+use std::{
+    collections::HashSet,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use crossbeam_channel::{Receiver, Sender};
+
+use super::{JobStatus, QueueJob, QueueSettings};
+use crate::{
+    ffmpeg::{start_video_render, wait_for_render, FromFfmpegMessage, RenderOutcome, VideoInfo},
+    util::error_with_source,
+};
+
+/// Status update for the job at `job_index` in the list the queue was started with.
+#[derive(Debug, Clone)]
+pub struct QueueEvent {
+    pub job_index: usize,
+    pub status: JobStatus,
+}
+
+#[derive(Clone, Default)]
+struct Cancellation {
+    all: Arc<AtomicBool>,
+    jobs: Arc<Mutex<HashSet<usize>>>,
+}
+
+impl Cancellation {
+    fn is_cancelled(&self, job_index: usize) -> bool {
+        self.all.load(Ordering::Relaxed) || self.jobs.lock().unwrap().contains(&job_index)
+    }
+}
+
+/// Renders one job of the queue and returns how it ended.
+type JobRunner = fn(usize, &QueueJob, &QueueSettings, &Sender<QueueEvent>, &Cancellation) -> JobStatus;
+
+/// Renders a list of videos with the same settings, running up to `concurrency` renders at the same time.
+///
+/// A job that fails is reported and the queue moves on to the next one. The events channel disconnects when every job
+/// has finished.
+pub struct RenderQueue {
+    events_rx: Receiver<QueueEvent>,
+    cancellation: Cancellation,
+}
+
+impl RenderQueue {
+    #[tracing::instrument(skip(settings))]
+    pub fn start(jobs: Vec<QueueJob>, settings: QueueSettings, concurrency: usize) -> Self {
+        Self::start_with(jobs, settings, concurrency, run_job)
+    }
+
+    /// Runs every job with `run_job`, the tests replace the render with it.
+    fn start_with(jobs: Vec<QueueJob>, settings: QueueSettings, concurrency: usize, run_job: JobRunner) -> Self {
+        let (events_tx, events_rx) = crossbeam_channel::unbounded();
+        let (jobs_tx, jobs_rx) = crossbeam_channel::unbounded();
+        let cancellation = Cancellation::default();
+
+        for (job_index, job) in jobs.into_iter().enumerate() {
+            events_tx
+                .send(QueueEvent {
+                    job_index,
+                    status: JobStatus::Pending,
+                })
+                .ok();
+            jobs_tx.send((job_index, job)).ok();
+        }
+        drop(jobs_tx);
+
+        for worker in 0..concurrency.max(1) {
+            let jobs_rx = jobs_rx.clone();
+            let events_tx = events_tx.clone();
+            let cancellation = cancellation.clone();
+            let settings = settings.clone();
+            thread::Builder::new()
+                .name(format!("Render queue worker {}", worker))
+                .spawn(move || {
+                    for (job_index, job) in jobs_rx {
+                        let status = if cancellation.is_cancelled(job_index) {
+                            JobStatus::Cancelled
+                        } else {
+                            tracing::info_span!("Render queue job", job_index)
+                                .in_scope(|| run_job(job_index, &job, &settings, &events_tx, &cancellation))
+                        };
+                        events_tx.send(QueueEvent { job_index, status }).ok();
+                    }
+                })
+                .expect("Failed to spawn render queue worker thread");
+        }
+
+        Self {
+            events_rx,
+            cancellation,
+        }
+    }
+
+    pub fn events(&self) -> &Receiver<QueueEvent> {
+        &self.events_rx
+    }
+
+    /// Stops the job if it is running or skips it if it hasn't started yet.
+    pub fn cancel(&self, job_index: usize) {
+        self.cancellation.jobs.lock().unwrap().insert(job_index);
+    }
+
+    pub fn cancel_all(&self) {
+        self.cancellation.all.store(true, Ordering::Relaxed);
+    }
+}
+
+fn run_job(
+    job_index: usize,
+    job: &QueueJob,
+    settings: &QueueSettings,
+    events_tx: &Sender<QueueEvent>,
+    cancellation: &Cancellation,
+) -> JobStatus {
+    tracing::info!("Rendering {:?} to {:?}", job.input_video, job.output_video);
+    let send_progress = |progress_pct, time_remaining| {
+        events_tx
+            .send(QueueEvent {
+                job_index,
+                status: JobStatus::Running {
+                    progress_pct,
+                    time_remaining,
+                },
+            })
+            .ok();
+    };
+    send_progress(0.0, None);
+
+    let job = match job.render_job(settings).build() {
+        Ok(job) => job,
+        Err(e) => return JobStatus::Failed(error_with_source(&e)),
+    };
+
+    let video_info = match VideoInfo::get(job.input_video(), &settings.ffprobe_path) {
+        Ok(video_info) => video_info,
+        Err(e) => return JobStatus::Failed(format!("Failed to read video file: {}", error_with_source(&e))),
+    };
+
+    let (to_ffmpeg_tx, from_ffmpeg_rx, _) = match start_video_render(&settings.ffmpeg_path, &job, &video_info) {
+        Ok(channels) => channels,
+        Err(e) => return JobStatus::Failed(format!("Failed to start video render: {}", error_with_source(&e))),
+    };
+
+    let total_frames = job.frame_count(&video_info) as f32;
+    let outcome = wait_for_render(
+        &to_ffmpeg_tx,
+        &from_ffmpeg_rx,
+        || cancellation.is_cancelled(job_index),
+        |message| match message {
+            FromFfmpegMessage::DecoderProgress(p) => {
+                let frame = p.frame as f32;
+                let time_remaining_secs = (total_frames - frame) / p.fps;
+                let time_remaining = if time_remaining_secs.is_finite() && time_remaining_secs.is_sign_positive() {
+                    Some(Duration::from_secs_f32(time_remaining_secs))
+                } else {
+                    None
+                };
+                send_progress((frame / total_frames).min(1.0), time_remaining);
+            }
+            FromFfmpegMessage::Warning(e) => tracing::warn!("{}", error_with_source(e)),
+            _ => {}
+        },
+    );
+
+    match outcome {
+        RenderOutcome::Completed => JobStatus::Completed,
+        RenderOutcome::Cancelled => JobStatus::Cancelled,
+        RenderOutcome::Failed(e) => JobStatus::Failed(error_with_source(&e)),
+        RenderOutcome::Incomplete => JobStatus::Failed("ffmpeg stopped before the end of the video".into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+    use crate::{
+        ffmpeg::{Codec, Encoder},
+        osd::OsdOptions,
+        srt::SrtOptions,
+    };
+
+    fn settings() -> QueueSettings {
+        QueueSettings {
+            ffmpeg_path: "ffmpeg".into(),
+            ffprobe_path: "ffprobe".into(),
+            font_file: "missing-font.png".into(),
+            osd_options: OsdOptions::default(),
+            srt_options: SrtOptions::default(),
+            render_settings: Default::default(),
+            encoder: Encoder {
+                name: "libx264".to_string(),
+                codec: Codec::H264,
+                hardware: false,
+                detected: true,
+                constant_quality_args: Some(vec!["-crf".to_string(), "23".to_string()]),
+                extra_args: vec![],
+            },
+        }
+    }
+
+    fn jobs(count: usize) -> Vec<QueueJob> {
+        (0..count)
+            .map(|index| QueueJob::from_video(PathBuf::from(format!("missing-video-{index}.mp4"))))
+            .collect()
+    }
+
+    fn events(queue: &RenderQueue) -> Vec<(usize, JobStatus)> {
+        queue
+            .events()
+            .iter()
+            .map(|event| (event.job_index, event.status))
+            .collect()
+    }
+
+    fn running() -> JobStatus {
+        JobStatus::Running {
+            progress_pct: 0.0,
+            time_remaining: None,
+        }
+    }
+
+    /// Job 0 fails, the others complete.
+    fn fail_first_job(
+        job_index: usize,
+        _: &QueueJob,
+        _: &QueueSettings,
+        events_tx: &Sender<QueueEvent>,
+        _: &Cancellation,
+    ) -> JobStatus {
+        events_tx
+            .send(QueueEvent {
+                job_index,
+                status: running(),
+            })
+            .ok();
+        if job_index == 0 {
+            JobStatus::Failed("Broken video".into())
+        } else {
+            JobStatus::Completed
+        }
+    }
+
+    /// Job 0 runs until it's cancelled, the others complete.
+    fn wait_for_cancel_of_first_job(
+        job_index: usize,
+        _: &QueueJob,
+        _: &QueueSettings,
+        events_tx: &Sender<QueueEvent>,
+        cancellation: &Cancellation,
+    ) -> JobStatus {
+        events_tx
+            .send(QueueEvent {
+                job_index,
+                status: running(),
+            })
+            .ok();
+        if job_index > 0 {
+            return JobStatus::Completed;
+        }
+        while !cancellation.is_cancelled(job_index) {
+            thread::sleep(Duration::from_millis(1));
+        }
+        JobStatus::Cancelled
+    }
+
+    /// Waits for the running event of job 0.
+    fn wait_until_first_job_runs(queue: &RenderQueue) -> Vec<(usize, JobStatus)> {
+        let mut events = vec![];
+        for event in queue.events() {
+            events.push((event.job_index, event.status.clone()));
+            if event.job_index == 0 && event.status == running() {
+                break;
+            }
+        }
+        events
+    }
+
+    #[test]
+    fn jobs_run_in_order_after_a_failure() {
+        let queue = RenderQueue::start_with(jobs(2), settings(), 1, fail_first_job);
+        assert_eq!(
+            events(&queue),
+            [
+                (0, JobStatus::Pending),
+                (1, JobStatus::Pending),
+                (0, running()),
+                (0, JobStatus::Failed("Broken video".into())),
+                (1, running()),
+                (1, JobStatus::Completed),
+            ]
+        );
+    }
+
+    #[test]
+    fn cancelled_job_stops_and_the_next_one_runs() {
+        let queue = RenderQueue::start_with(jobs(2), settings(), 1, wait_for_cancel_of_first_job);
+        wait_until_first_job_runs(&queue);
+        queue.cancel(0);
+        assert_eq!(
+            events(&queue),
+            [(0, JobStatus::Cancelled), (1, running()), (1, JobStatus::Completed)]
+        );
+    }
+
+    #[test]
+    fn cancelling_all_skips_the_pending_jobs() {
+        let queue = RenderQueue::start_with(jobs(3), settings(), 1, wait_for_cancel_of_first_job);
+        let started = wait_until_first_job_runs(&queue);
+        assert_eq!(started.len(), 4);
+        queue.cancel_all();
+        assert_eq!(
+            events(&queue),
+            [
+                (0, JobStatus::Cancelled),
+                (1, JobStatus::Cancelled),
+                (2, JobStatus::Cancelled)
+            ]
+        );
+    }
+
+    #[test]
+    fn job_that_cant_be_built_fails() {
+        let queue = RenderQueue::start(jobs(1), settings(), 1);
+        let events = events(&queue);
+        assert_eq!(events[..2], [(0, JobStatus::Pending), (0, running())]);
+        assert!(matches!(&events[2], (0, JobStatus::Failed(error)) if error.starts_with("Failed to load font file")));
+        assert_eq!(events.len(), 3);
+    }
+}
@@ -3,6 +3,7 @@ mod frame;
 mod options;
 mod srt_file;
 
+pub use error::SrtFileError;
 pub use frame::{SrtDebugFrameData, SrtFrame, SrtFrameData};
 pub use options::SrtOptions;
 pub use srt_file::SrtFile;
@@ -3,6 +3,7 @@ use serde::{Deserialize, Serialize};
 use crate::util::Coordinates;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(default)]
 pub struct SrtOptions {
     pub position: Coordinates<f32>,
     pub scale: f32,
@@ -8,7 +8,7 @@ use super::{
     SrtFrameData,
 };
 
-#[derive(Derivative)]
+#[derive(Derivative, Clone)]
 #[derivative(Debug)]
 pub struct SrtFile {
     pub file_path: PathBuf,
@@ -60,4 +60,63 @@ impl SrtFile {
             frames: srt_frames,
         })
     }
+
+    /// The frame shown at `time`, the last frame that starts at or before it. The first frame is also shown before it
+    /// starts, so the video doesn't begin without SRT data. `None` only when the file has no frames.
+    pub fn frame_at(&self, time: Duration) -> Option<&SrtFrame> {
+        self.frame_index_at(time).map(|index| &self.frames[index])
+    }
+
+    /// Index of [`SrtFile::frame_at`] in [`SrtFile::frames`].
+    pub fn frame_index_at(&self, time: Duration) -> Option<usize> {
+        let time_secs = time.as_secs_f32();
+        let frames_shown = self.frames.partition_point(|frame| frame.start_time_secs <= time_secs);
+        (!self.frames.is_empty()).then(|| frames_shown.saturating_sub(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn srt_file(start_times_secs: &[f32]) -> SrtFile {
+        SrtFile {
+            file_path: PathBuf::new(),
+            has_distance: false,
+            has_debug: false,
+            duration: Duration::from_secs_f32(start_times_secs.last().unwrap_or(&0.0) + 0.1),
+            frames: start_times_secs
+                .iter()
+                .map(|&start_time_secs| SrtFrame {
+                    start_time_secs,
+                    end_time_secs: start_time_secs + 0.1,
+                    data: None,
+                    debug_data: None,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn frames_are_found_by_time() {
+        let srt_file = srt_file(&[0.5, 0.6, 0.7]);
+
+        // The first frame is shown from the start of the video
+        assert_eq!(srt_file.frame_index_at(Duration::ZERO), Some(0));
+        assert_eq!(srt_file.frame_index_at(Duration::from_millis(499)), Some(0));
+
+        assert_eq!(srt_file.frame_index_at(Duration::from_millis(500)), Some(0));
+        assert_eq!(srt_file.frame_index_at(Duration::from_millis(600)), Some(1));
+        assert_eq!(srt_file.frame_index_at(Duration::from_millis(699)), Some(1));
+
+        // The last frame stays after its end
+        assert_eq!(srt_file.frame_index_at(Duration::from_millis(700)), Some(2));
+        assert_eq!(srt_file.frame_index_at(Duration::from_secs(60)), Some(2));
+        assert_eq!(srt_file.frame_at(Duration::from_secs(60)).unwrap().start_time_secs, 0.7);
+    }
+
+    #[test]
+    fn file_without_frames_shows_nothing() {
+        assert_eq!(srt_file(&[]).frame_index_at(Duration::ZERO), None);
+    }
 }
@@ -1,4 +1,7 @@
-use std::fmt::Display;
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
 
 use derivative::Derivative;
 use serde::{Deserialize, Serialize};
@@ -66,3 +69,32 @@ pub fn command_to_cli(cmd: &std::process::Command) -> String {
         .collect();
     format!("{} {}", cmd.get_program().to_string_lossy(), cli_args.join(" "))
 }
+
+/// Path of the file next to `path` with the same name but a different extension, e.g. the `.osd` or `.srt` file that
+/// the goggles record alongside a video.
+#[tracing::instrument(ret, level = "info")]
+pub fn matching_file_with_extension(path: &PathBuf, extention: &str) -> PathBuf {
+    let file_name = path.file_stem().unwrap();
+    let parent = path.parent().unwrap();
+    parent.join(file_name).with_extension(extention)
+}
+
+/// Default path of the rendered video: `<name>_with_osd.mp4` in the same folder as the input video.
+pub fn default_output_video_path(input_video: &Path) -> PathBuf {
+    let file_stem = input_video
+        .file_stem()
+        .map_or("file".to_string(), |s| s.to_string_lossy().to_string());
+    input_video.with_file_name(format!("{}_with_osd.mp4", file_stem))
+}
+
+/// Message of `error` followed by the messages of the errors that caused it, for showing errors to users.
+pub fn error_with_source(error: &dyn std::error::Error) -> String {
+    let mut message = error.to_string();
+    let mut source = error.source();
+    while let Some(error) = source {
+        message.push_str(": ");
+        message.push_str(&error.to_string());
+        source = error.source();
+    }
+    message
+}
@@ -0,0 +1,58 @@
+#!/usr/bin/env python3
+# Writes betaflight_hd.osd, a synthetic OSD recording for the telemetry tests. It is not a goggle recording: the
+# header only sets the FC firmware tag, version, grid and font size at the offsets OsdHeader reads, and the frames hold
+# a Betaflight layout on the 53x20 HD grid. Frames are a little-endian u32 timestamp in milliseconds followed by the
+# u16 glyph indices row by row, blank cells are stored as spaces like the goggles do.
+#
+# Run from this directory: python3 create_betaflight_hd_osd.py
+
+import struct
+
+GRID_WIDTH, GRID_HEIGHT = 53, 20
+
+# Betaflight symbols, see backend/src/osd/symbols.rs
+VOLTAGE, VOLT = "\x90", "\x06"
+ALTITUDE, METER = "\x7f", "\x0c"
+RSSI = "\x01"
+FLIGHT_TIME = "\x9c"
+KMH = "\x9e"
+LATITUDE, LONGITUDE = "\x89", "\x98"
+
+# Timestamp, voltage, altitude, speed, RSSI and flight time of each frame
+FRAMES = [
+    (0, "16.80", "12.3", "45", "99", "02:34"),
+    (100, "16.76", "12.5", "47", "98", "02:34"),
+    (200, "16.71", "12.8", "50", "98", "02:35"),
+]
+
+
+def header():
+    header = bytearray(40)
+    header[0:4] = b"BTFL"
+    header[4:6] = struct.pack("<H", 1)
+    header[6], header[7] = GRID_WIDTH, GRID_HEIGHT
+    header[8], header[9] = 24, 36
+    return header
+
+
+def frame(timestamp, voltage, altitude, speed, rssi, flight_time):
+    cells = [ord(" ")] * (GRID_WIDTH * GRID_HEIGHT)
+
+    def put(x, y, text):
+        for i, c in enumerate(text):
+            cells[y * GRID_WIDTH + x + i] = ord(c)
+
+    put(2, 1, VOLTAGE + voltage + VOLT)
+    put(40, 1, ALTITUDE + altitude + METER)
+    put(2, 17, RSSI + rssi)
+    put(44, 17, FLIGHT_TIME + flight_time)
+    put(24, 18, " " + speed + KMH)
+    put(2, 18, LATITUDE + "-33.8688000")
+    put(38, 18, LONGITUDE + "151.2093000")
+    return struct.pack("<I", timestamp) + struct.pack(f"<{len(cells)}H", *cells)
+
+
+with open("betaflight_hd.osd", "wb") as file:
+    file.write(header())
+    for values in FRAMES:
+        file.write(frame(*values))
@@ -0,0 +1,511 @@
+use std::path::PathBuf;
+
+use backend::{
+    ffmpeg::{
+        CropRect, OutputResolution, RenderMode as BackendRenderMode, RenderSettings,
+        ScalingFilter as BackendScalingFilter,
+    },
+    font::CharacterSizeClass,
+    osd::{GapBehavior, OsdOptions},
+    srt::SrtOptions,
+    util::Coordinates,
+};
+use clap::{Args, Parser, ValueEnum};
+
+/// Render the flight controller OSD and SRT data from the Walksnail Avatar HD FPV system on top of the goggle or VRX
+/// recording, without the graphical interface.
+#[derive(Debug, Parser)]
+#[command(name = "walksnail-osd-cli", version, about)]
+pub struct Cli {
+    /// Input video file
+    pub video: Option<PathBuf>,
+
+    /// Output video file [default: <VIDEO>_with_osd.mp4 next to the input video]
+    #[arg(short, long)]
+    pub output: Option<PathBuf>,
+
+    /// OSD file [default: the .osd file next to the input video]
+    #[arg(long)]
+    pub osd: Option<PathBuf>,
+
+    /// SRT file [default: the .srt file next to the input video, if there is one]
+    #[arg(long)]
+    pub srt: Option<PathBuf>,
+
+    /// OSD font file (.png)
+    #[arg(long)]
+    pub font: Option<PathBuf>,
+
+    /// Job file (.ron or .json) with the files and options of a render. Flags override the values from the job file
+    #[arg(long)]
+    pub job: Option<PathBuf>,
+
+    /// Start rendering at this time of the video, in seconds or as MM:SS
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    pub start: Option<f32>,
+
+    /// Stop rendering at this time of the video, in seconds or as MM:SS
+    #[arg(long, value_name = "TIME", value_parser = parse_time)]
+    pub end: Option<f32>,
+
+    /// Name of the ffmpeg encoder, e.g. libx264 [default: the preferred encoder detected on this system]
+    #[arg(short, long)]
+    pub encoder: Option<String>,
+
+    /// Print the encoders detected on this system and exit
+    #[arg(long)]
+    pub list_encoders: bool,
+
+    /// Path of the ffmpeg executable
+    #[arg(long, default_value = "ffmpeg")]
+    pub ffmpeg: PathBuf,
+
+    /// Path of the ffprobe executable
+    #[arg(long, default_value = "ffprobe")]
+    pub ffprobe: PathBuf,
+
+    /// Also print informational log messages
+    #[arg(short, long)]
+    pub verbose: bool,
+
+    #[command(flatten)]
+    pub osd_options: OsdArgs,
+
+    #[command(flatten)]
+    pub srt_options: SrtArgs,
+
+    #[command(flatten)]
+    pub render_settings: RenderArgs,
+}
+
+#[derive(Debug, Args)]
+#[command(next_help_heading = "OSD options")]
+pub struct OsdArgs {
+    /// Horizontal position of the OSD (pixels from the left edge of the video)
+    #[arg(long, allow_negative_numbers = true)]
+    pub osd_x: Option<i32>,
+
+    /// Vertical position of the OSD (pixels from the top of the video)
+    #[arg(long, allow_negative_numbers = true)]
+    pub osd_y: Option<i32>,
+
+    /// Do not render the OSD
+    #[arg(long)]
+    pub no_osd: bool,
+
+    /// Start rendering the OSD from this many seconds into the video
+    #[arg(long)]
+    pub osd_offset: Option<f32>,
+
+    /// Stretch the OSD to the duration of the video to correct for wrong OSD timestamps in <=32.37.10 firmwares
+    #[arg(long)]
+    pub adjust_playback_speed: bool,
+
+    /// What to show where frames are missing from the OSD file
+    #[arg(long, value_enum)]
+    pub osd_gaps: Option<OsdGaps>,
+
+    /// Size of the OSD characters
+    #[arg(long, value_enum)]
+    pub character_size: Option<CharacterSize>,
+
+    /// Grid position "X,Y" of an OSD element that should not be rendered. Can be given multiple times
+    #[arg(long = "mask", value_name = "X,Y", value_parser = parse_grid_position)]
+    pub masks: Vec<Coordinates<u32>>,
+}
+
+#[derive(Debug, Args)]
+#[command(next_help_heading = "SRT options")]
+pub struct SrtArgs {
+    /// Horizontal position of the SRT data (% of the video width from the left edge)
+    #[arg(long)]
+    pub srt_x: Option<f32>,
+
+    /// Vertical position of the SRT data (% of the video height from the top edge)
+    #[arg(long)]
+    pub srt_y: Option<f32>,
+
+    /// Font size of the SRT data
+    #[arg(long)]
+    pub srt_scale: Option<f32>,
+
+    /// Do not render the SRT data
+    #[arg(long)]
+    pub no_srt: bool,
+}
+
+#[derive(Debug, Args)]
+#[command(next_help_heading = "Rendering options")]
+pub struct RenderArgs {
+    /// Target bitrate of the rendered video in Mbps, used when constant quality mode is off
+    #[arg(long)]
+    pub bitrate: Option<u32>,
+
+    /// Use the constant quality mode of the encoder instead of a target bitrate
+    #[arg(long, value_name = "true|false")]
+    pub constant_quality: Option<bool>,
+
+    /// Upscale the output video to 1440p, same as --resolution 1440p
+    #[arg(long)]
+    pub upscale: bool,
+
+    /// Scale the output video to fit this resolution: 720p, 1080p, 1440p, 2160p, source or WIDTHxHEIGHT
+    #[arg(long, value_name = "RESOLUTION", value_parser = parse_resolution)]
+    pub resolution: Option<OutputResolution>,
+
+    /// Filter used to scale the video
+    #[arg(long, value_enum)]
+    pub scaling_filter: Option<ScalingFilter>,
+
+    /// Keep only this rectangle "X,Y,WIDTH,HEIGHT" of the input video, in pixels of the input video
+    #[arg(long, value_name = "X,Y,WIDTH,HEIGHT", value_parser = parse_crop)]
+    pub crop: Option<CropRect>,
+
+    /// Letterbox or pillarbox the video to exactly the output resolution with bars of this color ("#RRGGBB" or
+    /// "#RRGGBBAA")
+    #[arg(long, value_name = "COLOR", value_parser = parse_color)]
+    pub pad: Option<[f32; 4]>,
+
+    /// Rescale the output video to 4:3 aspect ratio
+    #[arg(long)]
+    pub rescale_to_4x3: bool,
+
+    /// Render on a chroma key background of this color ("#RRGGBB" or "#RRGGBBAA") instead of the input video
+    #[arg(long, value_name = "COLOR", value_parser = parse_color)]
+    pub chroma_key: Option<[f32; 4]>,
+
+    /// How the OSD and SRT data are put on the video
+    #[arg(long, value_enum)]
+    pub render_mode: Option<RenderMode>,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum CharacterSize {
+    Xs,
+    S,
+    Normal,
+    L,
+    Xl,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum OsdGaps {
+    /// Keep showing the last frame before the gap
+    Hold,
+    /// Show no OSD
+    Blank,
+    /// Show a "NO OSD" marker
+    Marker,
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum ScalingFilter {
+    Bilinear,
+    Bicubic,
+    Lanczos,
+    /// Nearest neighbor
+    Neighbor,
+}
+
+impl From<ScalingFilter> for BackendScalingFilter {
+    fn from(value: ScalingFilter) -> Self {
+        match value {
+            ScalingFilter::Bilinear => BackendScalingFilter::Bilinear,
+            ScalingFilter::Bicubic => BackendScalingFilter::Bicubic,
+            ScalingFilter::Lanczos => BackendScalingFilter::Lanczos,
+            ScalingFilter::Neighbor => BackendScalingFilter::Neighbor,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+pub enum RenderMode {
+    /// Draw the overlay on every decoded frame and pipe the frames to the encoder
+    Composite,
+    /// Draw the overlay only when it changes and let ffmpeg overlay it on the video
+    FilterGraph,
+}
+
+impl From<RenderMode> for BackendRenderMode {
+    fn from(value: RenderMode) -> Self {
+        match value {
+            RenderMode::Composite => BackendRenderMode::Composite,
+            RenderMode::FilterGraph => BackendRenderMode::FilterGraph,
+        }
+    }
+}
+
+impl From<OsdGaps> for GapBehavior {
+    fn from(value: OsdGaps) -> Self {
+        match value {
+            OsdGaps::Hold => GapBehavior::Hold,
+            OsdGaps::Blank => GapBehavior::Blank,
+            OsdGaps::Marker => GapBehavior::NoOsdMarker,
+        }
+    }
+}
+
+impl From<CharacterSize> for CharacterSizeClass {
+    fn from(value: CharacterSize) -> Self {
+        match value {
+            CharacterSize::Xs => CharacterSizeClass::XSmall,
+            CharacterSize::S => CharacterSizeClass::Small,
+            CharacterSize::Normal => CharacterSizeClass::Normal,
+            CharacterSize::L => CharacterSizeClass::Large,
+            CharacterSize::Xl => CharacterSizeClass::XLarge,
+        }
+    }
+}
+
+impl OsdArgs {
+    pub fn apply(&self, options: &mut OsdOptions) {
+        if let Some(x) = self.osd_x {
+            options.position.x = x;
+        }
+        if let Some(y) = self.osd_y {
+            options.position.y = y;
+        }
+        options.no_osd |= self.no_osd;
+        if let Some(offset) = self.osd_offset {
+            options.osd_playback_offset = offset;
+        }
+        options.adjust_playback_speed |= self.adjust_playback_speed;
+        if let Some(osd_gaps) = self.osd_gaps {
+            options.gap_behavior = osd_gaps.into();
+        }
+        if let Some(character_size) = self.character_size {
+            options.character_size_class = Some(character_size.into());
+        }
+        options.masked_grid_positions.extend(self.masks.iter().copied());
+    }
+}
+
+impl SrtArgs {
+    pub fn apply(&self, options: &mut SrtOptions) {
+        if let Some(x) = self.srt_x {
+            options.position.x = x;
+        }
+        if let Some(y) = self.srt_y {
+            options.position.y = y;
+        }
+        if let Some(scale) = self.srt_scale {
+            options.scale = scale;
+        }
+        options.no_srt |= self.no_srt;
+    }
+}
+
+impl RenderArgs {
+    pub fn apply(&self, settings: &mut RenderSettings) {
+        if let Some(bitrate) = self.bitrate {
+            settings.bitrate_mbps = bitrate;
+        }
+        if let Some(constant_quality) = self.constant_quality {
+            settings.keep_quality = constant_quality;
+        }
+        let geometry = &mut settings.output_geometry;
+        if self.upscale {
+            geometry.resolution = OutputResolution::P1440;
+        }
+        if let Some(resolution) = self.resolution {
+            geometry.resolution = resolution;
+        }
+        if let Some(scaling_filter) = self.scaling_filter {
+            geometry.scaling_filter = scaling_filter.into();
+        }
+        if let Some(crop) = self.crop {
+            geometry.crop = Some(crop);
+        }
+        if let Some(pad_color) = self.pad {
+            geometry.pad = true;
+            geometry.pad_color = pad_color;
+        }
+        settings.rescale_to_4x3_aspect |= self.rescale_to_4x3;
+        if let Some(chroma_key) = self.chroma_key {
+            settings.use_chroma_key = true;
+            settings.chroma_key = chroma_key;
+        }
+        if let Some(render_mode) = self.render_mode {
+            settings.render_mode = render_mode.into();
+        }
+    }
+}
+
+fn parse_grid_position(value: &str) -> Result<Coordinates<u32>, String> {
+    let (x, y) = value
+        .split_once(',')
+        .ok_or_else(|| format!("expected \"X,Y\", got \"{value}\""))?;
+    let x = x.trim().parse().map_err(|_| format!("invalid column \"{x}\""))?;
+    let y = y.trim().parse().map_err(|_| format!("invalid row \"{y}\""))?;
+    Ok(Coordinates::new(x, y))
+}
+
+fn parse_time(value: &str) -> Result<f32, String> {
+    let invalid = || format!("expected seconds or \"MM:SS\", got \"{value}\"");
+    let secs = value
+        .split(':')
+        .try_fold(0.0, |secs, part| {
+            part.trim().parse::<f32>().map(|part| secs * 60.0 + part)
+        })
+        .map_err(|_| invalid())?;
+    if secs.is_finite() && secs >= 0.0 {
+        Ok(secs)
+    } else {
+        Err(invalid())
+    }
+}
+
+fn parse_resolution(value: &str) -> Result<OutputResolution, String> {
+    match value.to_ascii_lowercase().as_str() {
+        "source" => Ok(OutputResolution::Source),
+        "720p" => Ok(OutputResolution::P720),
+        "1080p" => Ok(OutputResolution::P1080),
+        "1440p" => Ok(OutputResolution::P1440),
+        "2160p" | "4k" => Ok(OutputResolution::P2160),
+        other => {
+            let (width, height) = other.split_once('x').ok_or_else(|| {
+                format!("expected 720p, 1080p, 1440p, 2160p, source or WIDTHxHEIGHT, got \"{value}\"")
+            })?;
+            let width = width.trim().parse().map_err(|_| format!("invalid width \"{width}\""))?;
+            let height = height
+                .trim()
+                .parse()
+                .map_err(|_| format!("invalid height \"{height}\""))?;
+            Ok(OutputResolution::Custom { width, height })
+        }
+    }
+}
+
+fn parse_crop(value: &str) -> Result<CropRect, String> {
+    let parts = value
+        .split(',')
+        .map(|part| {
+            part.trim()
+                .parse::<u32>()
+                .map_err(|_| format!("invalid number \"{part}\""))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    match parts[..] {
+        [x, y, width, height] => Ok(CropRect { x, y, width, height }),
+        _ => Err(format!("expected \"X,Y,WIDTH,HEIGHT\", got \"{value}\"")),
+    }
+}
+
+fn parse_color(value: &str) -> Result<[f32; 4], String> {
+    let hex = value.trim_start_matches('#');
+    if !hex.is_ascii() || (hex.len() != 6 && hex.len() != 8) {
+        return Err(format!("expected \"#RRGGBB\" or \"#RRGGBBAA\", got \"{value}\""));
+    }
+
+    let mut color = [1.0; 4];
+    for (channel, component) in color.iter_mut().zip(hex.as_bytes().chunks(2)) {
+        let component = std::str::from_utf8(component).unwrap();
+        let component = u8::from_str_radix(component, 16).map_err(|_| format!("invalid color \"{value}\""))?;
+        *channel = component as f32 / 255.0;
+    }
+    Ok(color)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn grid_positions_are_parsed() {
+        for (value, expected) in [
+            ("3,4", Ok(Coordinates::new(3, 4))),
+            (" 52 , 19 ", Ok(Coordinates::new(52, 19))),
+            ("3", Err("expected \"X,Y\", got \"3\"")),
+            ("a,4", Err("invalid column \"a\"")),
+            ("3,-1", Err("invalid row \"-1\"")),
+        ] {
+            assert_eq!(parse_grid_position(value), expected.map_err(str::to_string), "{value}");
+        }
+    }
+
+    #[test]
+    fn times_are_parsed() {
+        for (value, expected) in [
+            ("0", Some(0.0)),
+            ("12.5", Some(12.5)),
+            ("1:30", Some(90.0)),
+            ("01:02:03", Some(3723.0)),
+            ("", None),
+            ("abc", None),
+            ("1:xx", None),
+            ("-3", None),
+            ("inf", None),
+        ] {
+            assert_eq!(parse_time(value).ok(), expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn resolutions_are_parsed() {
+        for (value, expected) in [
+            ("source", Some(OutputResolution::Source)),
+            ("720p", Some(OutputResolution::P720)),
+            ("1080P", Some(OutputResolution::P1080)),
+            ("1440p", Some(OutputResolution::P1440)),
+            ("4K", Some(OutputResolution::P2160)),
+            (
+                "1920x1080",
+                Some(OutputResolution::Custom {
+                    width: 1920,
+                    height: 1080,
+                }),
+            ),
+            ("480p", None),
+            ("1920x", None),
+            ("x1080", None),
+            ("1920x-1080", None),
+        ] {
+            assert_eq!(parse_resolution(value).ok(), expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn crops_are_parsed() {
+        for (value, expected) in [
+            (
+                "240,0,1440,1080",
+                Some(CropRect {
+                    x: 240,
+                    y: 0,
+                    width: 1440,
+                    height: 1080,
+                }),
+            ),
+            (
+                " 0, 10 ,20,30",
+                Some(CropRect {
+                    x: 0,
+                    y: 10,
+                    width: 20,
+                    height: 30,
+                }),
+            ),
+            ("0,0,1440", None),
+            ("0,0,1440,1080,1", None),
+            ("a,0,1440,1080", None),
+            ("-1,0,1440,1080", None),
+        ] {
+            assert_eq!(parse_crop(value).ok(), expected, "{value}");
+        }
+    }
+
+    #[test]
+    fn colors_are_parsed() {
+        for (value, expected) in [
+            ("#ffffff", Some([1.0, 1.0, 1.0, 1.0])),
+            ("00FF00", Some([0.0, 1.0, 0.0, 1.0])),
+            ("#ff000000", Some([1.0, 0.0, 0.0, 0.0])),
+            ("#fff", None),
+            ("#ff00000", None),
+            ("#gg0000", None),
+            ("#ff00é", None),
+        ] {
+            assert_eq!(parse_color(value).ok(), expected, "{value}");
+        }
+    }
+}
@@ -0,0 +1,69 @@
+use std::path::PathBuf;
+
+use backend::ffmpeg::{RenderError, RenderJobError, VideoInfoError};
+use thiserror::Error;
+
+/// The render finished but something went wrong along the way.
+const EXIT_RENDER_FAILED: u8 = 1;
+/// Invalid or incomplete arguments. Same code as `clap` uses for its own usage errors.
+const EXIT_USAGE: u8 = 2;
+/// ffmpeg, ffprobe or the requested encoder is not available.
+const EXIT_MISSING_DEPENDENCY: u8 = 3;
+/// One of the input files could not be read.
+const EXIT_INVALID_INPUT: u8 = 4;
+
+#[derive(Debug, Error)]
+pub enum CliError {
+    #[error("{} could not be found or failed to run", .0.display())]
+    MissingDependency(PathBuf),
+
+    #[error("Encoder {0} is not available, run with --list-encoders to see the detected encoders")]
+    EncoderNotAvailable(String),
+
+    #[error("No encoder detected, check your ffmpeg installation")]
+    NoEncoder,
+
+    #[error("No input video, pass a video file or a job file that sets `video`")]
+    NoInputVideo,
+
+    #[error("Failed to read job file {}", path.display())]
+    UnreadableJobFile {
+        path: PathBuf,
+        source: Box<dyn std::error::Error + Send + Sync>,
+    },
+
+    #[error("Failed to read video file")]
+    Video(#[from] VideoInfoError),
+
+    #[error("Invalid render job")]
+    InvalidJob(#[from] RenderJobError),
+
+    #[error("Failed to start the render")]
+    RenderNotStarted(#[source] RenderError),
+
+    #[error("Render failed")]
+    RenderFailed(#[source] RenderError),
+
+    #[error("ffmpeg stopped before the end of the video")]
+    RenderIncomplete,
+}
+
+impl CliError {
+    pub fn exit_code(&self) -> u8 {
+        match self {
+            CliError::RenderNotStarted(_) | CliError::RenderFailed(_) | CliError::RenderIncomplete => {
+                EXIT_RENDER_FAILED
+            }
+            CliError::NoInputVideo => EXIT_USAGE,
+            CliError::MissingDependency(_) | CliError::EncoderNotAvailable(_) | CliError::NoEncoder => {
+                EXIT_MISSING_DEPENDENCY
+            }
+            CliError::InvalidJob(
+                RenderJobError::FontFile { .. } | RenderJobError::OsdFile { .. } | RenderJobError::SrtFile { .. },
+            )
+            | CliError::UnreadableJobFile { .. }
+            | CliError::Video(_) => EXIT_INVALID_INPUT,
+            CliError::InvalidJob(_) => EXIT_USAGE,
+        }
+    }
+}
@@ -0,0 +1,76 @@
+use std::{fs, path::PathBuf};
+
+use backend::{
+    ffmpeg::{RenderSettings, TimeRange},
+    osd::OsdOptions,
+    srt::SrtOptions,
+};
+use serde::Deserialize;
+
+use crate::{args::Cli, error::CliError};
+
+/// Everything needed to run one render. Read from a RON or JSON job file and/or filled in from the command line.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Job {
+    pub video: Option<PathBuf>,
+    pub output: Option<PathBuf>,
+    pub osd: Option<PathBuf>,
+    pub srt: Option<PathBuf>,
+    pub font: Option<PathBuf>,
+    pub encoder: Option<String>,
+    pub osd_options: OsdOptions,
+    pub srt_options: SrtOptions,
+    pub render_settings: RenderSettings,
+    pub time_range: TimeRange,
+}
+
+impl Job {
+    #[tracing::instrument(ret, err)]
+    pub fn load(path: &PathBuf) -> Result<Self, CliError> {
+        let contents = fs::read_to_string(path).map_err(|source| CliError::UnreadableJobFile {
+            path: path.clone(),
+            source: source.into(),
+        })?;
+
+        let job = if path.extension().is_some_and(|e| e.eq_ignore_ascii_case("json")) {
+            serde_json::from_str(&contents).map_err(|e| e.to_string())
+        } else {
+            ron::from_str(&contents).map_err(|e| e.to_string())
+        };
+
+        job.map_err(|e| CliError::UnreadableJobFile {
+            path: path.clone(),
+            source: e.into(),
+        })
+    }
+
+    /// Command line flags take precedence over the values from the job file.
+    pub fn apply_cli(&mut self, cli: &Cli) {
+        fn replace(value: &mut Option<PathBuf>, new_value: &Option<PathBuf>) {
+            if new_value.is_some() {
+                value.clone_from(new_value);
+            }
+        }
+
+        replace(&mut self.video, &cli.video);
+        replace(&mut self.output, &cli.output);
+        replace(&mut self.osd, &cli.osd);
+        replace(&mut self.srt, &cli.srt);
+        replace(&mut self.font, &cli.font);
+        if cli.encoder.is_some() {
+            self.encoder.clone_from(&cli.encoder);
+        }
+
+        if cli.start.is_some() {
+            self.time_range.start = cli.start;
+        }
+        if cli.end.is_some() {
+            self.time_range.end = cli.end;
+        }
+
+        cli.osd_options.apply(&mut self.osd_options);
+        cli.srt_options.apply(&mut self.srt_options);
+        cli.render_settings.apply(&mut self.render_settings);
+    }
+}
@@ -0,0 +1,117 @@
+use std::process::ExitCode;
+
+use backend::{
+    ffmpeg::{ffmpeg_available, ffprobe_available, Encoder, RenderJob, VideoInfo},
+    util::{default_output_video_path, error_with_source, matching_file_with_extension},
+};
+use clap::Parser;
+use tracing_subscriber::filter::LevelFilter;
+
+use crate::{args::Cli, error::CliError, job::Job};
+
+mod args;
+mod error;
+mod job;
+mod render;
+
+fn main() -> ExitCode {
+    let cli = Cli::parse();
+    init_tracing(cli.verbose);
+
+    match run(cli) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            eprintln!("Error: {}", error_with_source(&e));
+            ExitCode::from(e.exit_code())
+        }
+    }
+}
+
+fn init_tracing(verbose: bool) {
+    let level = if verbose { LevelFilter::INFO } else { LevelFilter::WARN };
+    tracing_subscriber::fmt()
+        .with_max_level(level)
+        .with_writer(std::io::stderr)
+        .init();
+}
+
+fn run(cli: Cli) -> Result<(), CliError> {
+    if !ffmpeg_available(&cli.ffmpeg) {
+        return Err(CliError::MissingDependency(cli.ffmpeg));
+    }
+
+    let encoders = Encoder::sort_and_filter(&Encoder::get_available_encoders(&cli.ffmpeg));
+    if cli.list_encoders {
+        for encoder in &encoders {
+            println!("{:<20} {}", encoder.name, encoder);
+        }
+        return Ok(());
+    }
+
+    if !ffprobe_available(&cli.ffprobe) {
+        return Err(CliError::MissingDependency(cli.ffprobe));
+    }
+
+    let mut job = match &cli.job {
+        Some(path) => Job::load(path)?,
+        None => Job::default(),
+    };
+    job.apply_cli(&cli);
+
+    let video_path = job.video.ok_or(CliError::NoInputVideo)?;
+    let encoder = select_encoder(&encoders, job.encoder.as_deref())?;
+
+    let mut render_settings = job.render_settings;
+    if render_settings.keep_quality && encoder.constant_quality_args.is_none() {
+        tracing::warn!(
+            "Encoder {} has no constant quality mode, rendering at {} Mbps instead",
+            encoder.name,
+            render_settings.bitrate_mbps
+        );
+        render_settings.keep_quality = false;
+    }
+
+    let mut render_job = RenderJob::builder()
+        .output_video(job.output.unwrap_or_else(|| default_output_video_path(&video_path)))
+        .osd_file(Some(
+            job.osd
+                .unwrap_or_else(|| matching_file_with_extension(&video_path, "osd")),
+        ))
+        .srt_file(
+            job.srt
+                .or_else(|| Some(matching_file_with_extension(&video_path, "srt")).filter(|path| path.exists())),
+        )
+        .encoder(encoder.clone())
+        .osd_options(job.osd_options)
+        .srt_options(job.srt_options)
+        .render_settings(render_settings)
+        .time_range(job.time_range)
+        .input_video(video_path);
+    if let Some(font) = job.font {
+        render_job = render_job.font_file(font);
+    }
+    let render_job = render_job.build()?;
+
+    let video_info = VideoInfo::get(render_job.input_video(), &cli.ffprobe)?;
+
+    tracing::info!(
+        "Rendering {:?} to {:?} with {}",
+        render_job.input_video(),
+        render_job.output_video(),
+        encoder.name
+    );
+    render::render(&cli.ffmpeg, &render_job, &video_info)?;
+    eprintln!("Saved {}", render_job.output_video().display());
+
+    Ok(())
+}
+
+fn select_encoder<'a>(encoders: &'a [Encoder], name: Option<&str>) -> Result<&'a Encoder, CliError> {
+    match name {
+        Some(name) => encoders
+            .iter()
+            .find(|encoder| encoder.name == name)
+            .ok_or_else(|| CliError::EncoderNotAvailable(name.to_string())),
+        None => encoders.first().ok_or(CliError::NoEncoder),
+    }
+}
@@ -0,0 +1,57 @@
+use std::{io::Write, path::PathBuf, time::Duration};
+
+use backend::{
+    ffmpeg::{start_video_render, wait_for_render, FromFfmpegMessage, RenderJob, RenderOutcome, VideoInfo},
+    util::error_with_source,
+};
+
+use crate::error::CliError;
+
+/// Runs a render to completion, printing the progress to stderr.
+pub fn render(ffmpeg_path: &PathBuf, job: &RenderJob, video_info: &VideoInfo) -> Result<(), CliError> {
+    // Live preview frames are of no use without a window, dropping the receiver makes the renderer skip them
+    let (to_ffmpeg_tx, from_ffmpeg_rx, _) =
+        start_video_render(ffmpeg_path, job, video_info).map_err(CliError::RenderNotStarted)?;
+
+    let total_frames = job.frame_count(video_info) as f32;
+    let outcome = wait_for_render(
+        &to_ffmpeg_tx,
+        &from_ffmpeg_rx,
+        || false,
+        |message| match message {
+            FromFfmpegMessage::DecoderProgress(p) => {
+                let progress_pct = (p.frame as f32 / total_frames * 100.0).min(100.0);
+                let time_remaining_secs = (total_frames - p.frame as f32) / p.fps;
+                let time_remaining = if time_remaining_secs.is_finite() && time_remaining_secs.is_sign_positive() {
+                    format_duration(Duration::from_secs_f32(time_remaining_secs))
+                } else {
+                    "--:--".to_string()
+                };
+                eprint!(
+                    "\r{progress_pct:5.1}%  {:6.1} fps  {:5.2}x  {time_remaining} remaining ",
+                    p.fps, p.speed
+                );
+                std::io::stderr().flush().ok();
+            }
+            FromFfmpegMessage::Error(e) => tracing::error!("{}", e),
+            FromFfmpegMessage::Warning(e) => {
+                eprintln!();
+                tracing::warn!("{}", error_with_source(e));
+            }
+            _ => {}
+        },
+    );
+    eprintln!();
+
+    match outcome {
+        RenderOutcome::Completed => Ok(()),
+        RenderOutcome::Failed(e) => Err(CliError::RenderFailed(e)),
+        // Nothing cancels the render, only ffmpeg stopping early
+        RenderOutcome::Cancelled | RenderOutcome::Incomplete => Err(CliError::RenderIncomplete),
+    }
+}
+
+fn format_duration(duration: Duration) -> String {
+    let secs = duration.as_secs();
+    format!("{:02}:{:02}", secs / 60, secs % 60)
+}
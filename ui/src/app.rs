@@ -1,14 +1,20 @@
 use std::{
     path::PathBuf,
+    sync::Arc,
     time::{Duration, Instant},
 };
 
 use backend::{
     config::AppConfig,
-    ffmpeg::{Encoder, FromFfmpegMessage, RenderSettings, ToFfmpegMessage, VideoInfo},
+    ffmpeg::{
+        Encoder, ExtractedFrame, FrameExtractor, FromFfmpegMessage, RenderSettings, TimeRange, ToFfmpegMessage,
+        VideoInfo,
+    },
     font::{self, FontFile},
-    osd::{OsdFile, OsdOptions},
+    osd::{OsdFile, OsdOptions, TimingAnalysis},
+    overlay::default_srt_font,
     srt::{SrtFile, SrtOptions},
+    util::default_output_video_path,
 };
 use crossbeam_channel::{Receiver, Sender};
 use derivative::Derivative;
@@ -21,8 +27,9 @@ use poll_promise::Promise;
 
 use crate::{
     osd_preview::create_osd_preview,
-    render_status::RenderStatus,
-    util::{generate_default_output_file_name, generate_output_file_path, set_custom_fonts, set_style},
+    queue_window::QueueState,
+    render_status::{RenderStatus, Status},
+    util::{generate_output_file_path, open_with_default_app, set_custom_fonts, set_style},
 };
 
 // Let's try to come up with a proper architecture to manage UI state...
@@ -37,6 +44,8 @@ pub struct WalksnailOsdTool {
     pub input_video_file: Option<PathBuf>,
     pub output_video_file: Option<PathBuf>,
     pub video_info: Option<VideoInfo>,
+    /// Frames of the input video that the OSD preview is drawn on.
+    pub video_frames: Option<FrameExtractor>,
     pub osd_file: Option<OsdFile>,
     pub font_file: Option<FontFile>,
     pub srt_file: Option<SrtFile>,
@@ -45,12 +54,18 @@ pub struct WalksnailOsdTool {
     pub from_ffmpeg_receiver: Option<Receiver<FromFfmpegMessage>>,
     pub frames_for_ui_rx: Option<Receiver<RgbaImage>>,
     pub render_status: RenderStatus,
+    pub render_queue: QueueState,
     pub encoders: Vec<Encoder>,
     pub detected_encoders: Vec<Encoder>,
     pub dependencies: Dependencies,
     pub render_settings: RenderSettings,
+    /// Part of the input video that is rendered, reset when another video is loaded.
+    pub time_range: TimeRange,
+    /// Preview clip that is opened once its render is done.
+    pub preview_clip: Option<PathBuf>,
     pub osd_preview: OsdPreview,
     pub osd_options: OsdOptions,
+    pub osd_timing: Option<TimingAnalysis>,
     pub srt_options: SrtOptions,
     pub srt_font: Option<rusttype::Font<'static>>,
     pub about_window_open: bool,
@@ -85,8 +100,7 @@ impl WalksnailOsdTool {
         set_custom_fonts(ctx);
         ctx.set_visuals(visuals);
 
-        let srt_font: rusttype::Font<'static> =
-            rusttype::Font::try_from_bytes(include_bytes!("../../resources/fonts/AzeretMono-Regular.ttf")).unwrap();
+        let srt_font = default_srt_font();
 
         let srt_options = saved_settings.srt_options;
         let osd_options = saved_settings.osd_options;
@@ -100,7 +114,7 @@ impl WalksnailOsdTool {
             ..Default::default()
         };
 
-        let detected_encoders = Self::sort_and_filter_encoders(&encoders);
+        let detected_encoders = Encoder::sort_and_filter(&encoders);
 
         Self {
             dependencies: Dependencies {
@@ -137,6 +151,8 @@ pub struct OsdPreview {
     pub texture_handle: Option<TextureHandle>,
     #[derivative(Default(value = "1"))]
     pub preview_frame: u32,
+    /// Frame of the video the OSD preview is drawn on, kept while the frame for a new preview frame is extracted.
+    pub background: Option<Arc<RgbaImage>>,
     pub mask_edit_mode_enabled: bool,
 }
 
@@ -190,12 +206,14 @@ impl eframe::App for WalksnailOsdTool {
         self.update_window(ctx);
 
         // Keep updating the UI thread when rendering to make sure the indicated progress is up-to-date
-        if self.render_status.is_in_progress() {
+        if self.render_status.is_in_progress() || self.render_queue.is_running() {
             ctx.request_repaint();
         }
 
         self.receive_ffmpeg_message();
+        self.receive_queue_events();
         self.show_rendered_frame_from_ffmpeg(ctx);
+        self.poll_video_frames(ctx);
         self.poll_update_check();
 
         self.render_top_panel(ctx);
@@ -206,6 +224,8 @@ impl eframe::App for WalksnailOsdTool {
 
         self.render_central_panel(ctx);
 
+        self.render_queue_window(ctx);
+
         self.save_config_if_changed();
     }
 }
@@ -214,7 +234,10 @@ impl WalksnailOsdTool {
     pub fn update_output_video_path(&mut self) {
         if let Some(input_video_file) = &self.input_video_file {
             if self.output_video_file.is_none() || self.ui_state.output_file_name.is_empty() {
-                self.ui_state.output_file_name = generate_default_output_file_name(input_video_file);
+                let default_output_video = default_output_video_path(input_video_file);
+                self.ui_state.output_file_name = default_output_video
+                    .file_stem()
+                    .map_or("file".to_string(), |stem| stem.to_string_lossy().to_string());
             }
 
             self.output_video_file = Some(generate_output_file_path(
@@ -260,9 +283,10 @@ impl WalksnailOsdTool {
     }
 
     pub fn set_osd_preview(&mut self, ctx: &egui::Context, rgba_image: &RgbaImage) {
-        if let Some(video_info) = &self.video_info {
+        // Rendered frames are the size of the output video, which is larger than the input video when upscaling
+        if self.video_info.is_some() {
             let image = egui::ColorImage::from_rgba_unmultiplied(
-                [video_info.width as usize, video_info.height as usize],
+                [rgba_image.width() as usize, rgba_image.height() as usize],
                 &rgba_image,
             );
 
@@ -274,24 +298,38 @@ impl WalksnailOsdTool {
     pub fn update_osd_preview(&mut self, ctx: &egui::Context) {
         if let (Some(video_info), Some(osd_file), Some(font_file)) = (&self.video_info, &self.osd_file, &self.font_file)
         {
-            let osd_frame_index = self.osd_preview.preview_frame as usize - 1;
-            let osd_frame = osd_file.frames.get(osd_frame_index).unwrap();
-
-            let osd_frame_time = osd_frame.time_millis as f32 / 1000.0;
-
-            let srt_frame = if let Some(srt_file) = &self.srt_file {
-                srt_file
-                    .frames
-                    .iter()
-                    .find(|frame| frame.start_time_secs >= osd_frame_time)
-            } else {
-                None
+            let osd_frame = match osd_file.frame(self.osd_preview.preview_frame.saturating_sub(1)) {
+                Ok(osd_frame) => osd_frame.unwrap_or_default(),
+                Err(e) => {
+                    tracing::error!("Failed to read OSD frame for preview: {}", e);
+                    Default::default()
+                }
             };
 
+            let osd_frame_time = Duration::from_millis(osd_frame.time_millis.into());
+            let video_time =
+                Duration::from_secs_f32(self.video_secs_at_osd_time(osd_frame_time.as_secs_f32()).max(0.0));
+            // The SRT file is timed like the video, the render looks it up the same way
+            let srt_frame = self
+                .srt_file
+                .as_ref()
+                .and_then(|srt_file| srt_file.frame_at(video_time));
+            if let Some(video_frames) = &mut self.video_frames {
+                match video_frames.frame_at(video_time) {
+                    ExtractedFrame::Ready(frame) => self.osd_preview.background = Some(frame),
+                    ExtractedFrame::Unavailable => self.osd_preview.background = None,
+                    // Drawn again when the frame is extracted, until then the previous frame stays
+                    ExtractedFrame::Pending => {}
+                }
+            }
+
             let rgba_image = create_osd_preview(
                 video_info.width,
                 video_info.height,
-                osd_frame,
+                &self.render_settings.output_geometry,
+                self.osd_preview.background.as_deref(),
+                &osd_frame,
+                &osd_file.grid_size,
                 srt_frame,
                 font_file,
                 self.srt_font.as_ref().unwrap(),
@@ -305,17 +343,46 @@ impl WalksnailOsdTool {
         }
     }
 
+    /// Redraws the preview once the video frame for it is extracted.
+    fn poll_video_frames(&mut self, ctx: &egui::Context) {
+        if let Some(video_frames) = &mut self.video_frames {
+            if video_frames.poll() {
+                self.update_osd_preview(ctx);
+            } else if video_frames.is_pending() {
+                ctx.request_repaint_after(Duration::from_millis(50));
+            }
+        }
+    }
+
+    /// Position in the video where the OSD shows `osd_secs`, the inverse of how the OSD time is looked up while
+    /// rendering.
+    pub fn video_secs_at_osd_time(&self, osd_secs: f32) -> f32 {
+        let speed_factor = match (&self.video_info, &self.osd_file) {
+            (Some(video_info), Some(osd_file)) => self
+                .osd_options
+                .playback_speed_factor(video_info.duration, osd_file.duration),
+            _ => 1.0,
+        };
+        (osd_secs + self.osd_options.osd_playback_offset) * speed_factor
+    }
+
     pub fn receive_ffmpeg_message(&mut self) {
-        if let (Some(tx), Some(rx), Some(video_info)) =
-            (&self.to_ffmpeg_sender, &self.from_ffmpeg_receiver, &self.video_info)
-        {
+        if let (Some(tx), Some(rx)) = (&self.to_ffmpeg_sender, &self.from_ffmpeg_receiver) {
             while let Ok(message) = rx.try_recv() {
-                if matches!(message, FromFfmpegMessage::EncoderFatalError(_))
-                    || matches!(message, FromFfmpegMessage::EncoderFinished)
-                {
+                if matches!(
+                    message,
+                    FromFfmpegMessage::Error(_) | FromFfmpegMessage::EncoderFinished
+                ) {
                     tx.send(ToFfmpegMessage::AbortRender).ok();
                 }
-                self.render_status.update_from_ffmpeg_message(message, video_info)
+                // The clip can only be played once the encoder has finished writing it
+                let encoder_finished = matches!(message, FromFfmpegMessage::EncoderFinished);
+                self.render_status.update_from_ffmpeg_message(message);
+                if encoder_finished && self.render_status.decoder_status == Status::Completed {
+                    if let Some(preview_clip) = self.preview_clip.take() {
+                        open_with_default_app(&preview_clip);
+                    }
+                }
             }
         }
     }
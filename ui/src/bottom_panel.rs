@@ -1,7 +1,13 @@
-use backend::ffmpeg::{start_video_render, ToFfmpegMessage};
+use backend::{
+    ffmpeg::{start_video_render, RenderJob, RenderJobBuilder, ToFfmpegMessage, PREVIEW_CLIP_SECS},
+    util::error_with_source,
+};
 use egui::{vec2, Align, Button, Color32, Layout, ProgressBar, RichText, Ui};
 
-use super::{util::format_minutes_seconds, WalksnailOsdTool};
+use super::{
+    util::{format_minutes_seconds, tooltip_text},
+    WalksnailOsdTool,
+};
 use crate::render_status::Status;
 
 impl WalksnailOsdTool {
@@ -28,61 +34,22 @@ impl WalksnailOsdTool {
                 .clicked()
             {
                 tracing::info!("Start render button clicked");
-                self.render_status.start_render();
-                if let (
-                    Some(input_video_path),
-                    Some(output_video_path),
-                    Some(osd_file),
-                    Some(font_file),
-                    Some(video_info),
-                    Some(encoder),
-                ) = (
-                    &self.input_video_file,
-                    &self.output_video_file,
-                    &self.osd_file,
-                    &self.font_file,
-                    &self.video_info,
-                    self.get_selected_encoder(),
-                ) {
-                    self.osd_options.osd_playback_speed_factor = if self.osd_options.adjust_playback_speed {
-                        let video_duration = video_info.duration;
-                        let osd_duration = osd_file.duration;
-                        video_duration.as_secs_f32() / osd_duration.as_secs_f32()
-                    } else {
-                        1.0
-                    };
-                    match start_video_render(
-                        &self.dependencies.ffmpeg_path,
-                        input_video_path,
-                        output_video_path,
-                        osd_file.frames.clone(),
-                        self.srt_file.as_ref().map(|file| file.frames.clone()),
-                        font_file.clone(),
-                        self.srt_font.as_ref().unwrap().clone(),
-                        &self.osd_options,
-                        &self.srt_options,
-                        video_info,
-                        &self.render_settings,
-                        &encoder,
-                    ) {
-                        Ok((to_ffmpeg_sender, from_ffmpeg_receiver, frames_for_ui_rx)) => {
-                            self.to_ffmpeg_sender = Some(to_ffmpeg_sender);
-                            self.from_ffmpeg_receiver = Some(from_ffmpeg_receiver);
-                            self.frames_for_ui_rx = Some(frames_for_ui_rx);
-                        }
-                        Err(_) => {
-                            self.render_status.decoder_status = Status::Error {
-                                progress_pct: 0.0,
-                                error: "Failed to start video render".to_string(),
-                            }
-                        }
-                    };
-                } else {
-                    self.render_status.decoder_status = Status::Error {
-                        progress_pct: 0.0,
-                        error: "Failed to start video render. Unexpected state detected.".to_string(),
-                    }
-                }
+                self.start_render(false);
+            }
+            if ui
+                .add_enabled(
+                    self.is_start_render_allowed(),
+                    Button::new("Preview clip").min_size(button_size),
+                )
+                .on_hover_text(tooltip_text(&format!(
+                    "Quickly render {PREVIEW_CLIP_SECS:.0} seconds around the preview frame at low resolution and play \
+                     them to check the position, size and sync of the OSD."
+                )))
+                .on_disabled_hover_text("First load video, OSD, SRT and font files")
+                .clicked()
+            {
+                tracing::info!("Preview clip button clicked");
+                self.start_render(true);
             }
         } else {
             if ui.add(Button::new("Stop render").min_size(button_size)).clicked() {
@@ -98,6 +65,89 @@ impl WalksnailOsdTool {
         }
     }
 
+    /// Starts rendering the whole video, or only a preview clip around the preview frame.
+    fn start_render(&mut self, preview: bool) {
+        let Some(video_info) = &self.video_info else {
+            self.render_status.decoder_status = Status::Error {
+                progress_pct: 0.0,
+                error: "Failed to start video render. Unexpected state detected.".to_string(),
+            };
+            return;
+        };
+        let job = match self.render_job().build() {
+            Ok(job) if preview => job.preview_clip(video_info, self.preview_position_secs()),
+            Ok(job) => job,
+            Err(e) => {
+                self.render_status.decoder_status = Status::Error {
+                    progress_pct: 0.0,
+                    error: error_with_source(&e),
+                };
+                return;
+            }
+        };
+
+        self.render_status.start_render(job.frame_count(video_info));
+        match start_video_render(&self.dependencies.ffmpeg_path, &job, video_info) {
+            Ok((to_ffmpeg_sender, from_ffmpeg_receiver, frames_for_ui_rx)) => {
+                self.to_ffmpeg_sender = Some(to_ffmpeg_sender);
+                self.from_ffmpeg_receiver = Some(from_ffmpeg_receiver);
+                self.frames_for_ui_rx = Some(frames_for_ui_rx);
+                self.preview_clip = preview.then(|| job.output_video().clone());
+            }
+            Err(e) => {
+                self.render_status.decoder_status = Status::Error {
+                    progress_pct: 0.0,
+                    error: format!("Failed to start video render: {}", error_with_source(&e)),
+                }
+            }
+        }
+    }
+
+    /// Position in the video of the OSD frame that is shown in the preview.
+    fn preview_position_secs(&self) -> f32 {
+        let Some(osd_file) = &self.osd_file else {
+            return 0.0;
+        };
+        let osd_secs = match osd_file.frame(self.osd_preview.preview_frame.saturating_sub(1)) {
+            Ok(osd_frame) => osd_frame.map_or(0.0, |frame| frame.time_millis as f32 / 1000.0),
+            Err(e) => {
+                tracing::error!("Failed to read OSD frame for preview clip: {}", e);
+                0.0
+            }
+        };
+        self.video_secs_at_osd_time(osd_secs)
+    }
+
+    fn render_job(&self) -> RenderJobBuilder {
+        // The files are already open in the app, the job doesn't read them again
+        let mut job = RenderJob::builder()
+            .loaded_osd_file(self.osd_file.clone())
+            .loaded_srt_file(self.srt_file.clone())
+            .osd_options(self.osd_options.clone())
+            .srt_options(self.srt_options.clone())
+            .render_settings(self.render_settings.clone())
+            .time_range(self.time_range);
+        if let Some(input_video) = &self.input_video_file {
+            job = job.input_video(input_video.clone());
+        }
+        if let Some(output_video) = &self.output_video_file {
+            job = job.output_video(output_video.clone());
+        }
+        if let Some(font_file) = &self.font_file {
+            job = job.loaded_font_file(font_file.clone());
+        }
+        if let Some(encoder) = self.get_selected_encoder() {
+            job = job.encoder(encoder);
+        }
+        job
+    }
+
+    fn render_warning(&self, ui: &mut Ui) {
+        if let Some(warning) = &self.render_status.warning {
+            ui.label(RichText::new(warning).color(Color32::from_rgb(230, 160, 0)));
+        }
+    }
+
     fn render_progress(&mut self, ui: &mut Ui) {
         match &self.render_status.decoder_status {
             Status::Idle => {}
@@ -135,11 +185,13 @@ impl WalksnailOsdTool {
                             encoder_bitrate / 1000.0
                         ));
                     });
+                    self.render_warning(ui);
                 });
             }
             Status::Completed => {
                 ui.vertical(|ui| {
                     ui.add(ProgressBar::new(1.0).text("Done"));
+                    self.render_warning(ui);
                 });
             }
             Status::Cancelled { progress_pct } => {
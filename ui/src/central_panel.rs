@@ -1,13 +1,14 @@
 use std::time::Instant;
 
 use backend::{
-    ffmpeg::{Codec, Encoder},
+    ffmpeg::{CropRect, Encoder, OutputResolution, RenderMode, ScalingFilter},
     font::CharacterSizeClass,
+    osd::{GapBehavior, DEFAULT_GRID_SIZE},
     util::Coordinates,
 };
 use egui::{
-    vec2, Button, CentralPanel, Checkbox, CollapsingHeader, Color32, CursorIcon, Grid, Image, Rect, RichText,
-    ScrollArea, Sense, Slider, Stroke, TextStyle, Ui,
+    vec2, Button, CentralPanel, Checkbox, CollapsingHeader, Color32, CursorIcon, DragValue, Grid, Image, Rect,
+    RichText, ScrollArea, Sense, Slider, Stroke, TextStyle, Ui,
 };
 
 use crate::{
@@ -129,7 +130,15 @@ impl WalksnailOsdTool {
                                 self.update_osd_preview(ctx);
                             }
                             let masked_positions = self.osd_options.masked_grid_positions.len();
-                            ui.label(format!("{masked_positions} positions masked"));
+                            match (&self.osd_options.mask_grid_size, &self.osd_file) {
+                                (Some(mask_grid_size), Some(osd_file)) if mask_grid_size != &osd_file.grid_size => {
+                                    ui.label(format!("{masked_positions} positions masked"))
+                                        .on_hover_text(format!("The mask was made for a {mask_grid_size} grid and is not applied to this OSD file"));
+                                }
+                                _ => {
+                                    ui.label(format!("{masked_positions} positions masked"));
+                                }
+                            }
                         });
                         ui.end_row();
 
@@ -151,6 +160,19 @@ impl WalksnailOsdTool {
                         });
                         ui.end_row();
 
+                        ui.label("OSD gaps")
+                            .on_hover_text(tooltip_text("What to show where frames are missing from the OSD file, e.g. when the air unit lost the link."));
+                        egui::ComboBox::from_id_source("osd_gap_behavior")
+                            .selected_text(self.osd_options.gap_behavior.to_string())
+                            .show_ui(ui, |ui| {
+                                for gap_behavior in [GapBehavior::Hold, GapBehavior::Blank, GapBehavior::NoOsdMarker] {
+                                    changed |= ui
+                                        .selectable_value(&mut self.osd_options.gap_behavior, gap_behavior, gap_behavior.to_string())
+                                        .changed();
+                                }
+                            });
+                        ui.end_row();
+
                         // Enable playback offset only if playback speed adjustment is disabled, since it tries to fix basically the same problem
                         if !self.osd_options.adjust_playback_speed && self.video_info.is_some() {
                             ui.label("Adjust playback offset")
@@ -171,6 +193,32 @@ impl WalksnailOsdTool {
                             ui.end_row();
                         }
 
+                        if let (Some(video_info), Some(osd_file)) = (&self.video_info, &self.osd_file) {
+                            ui.label("Analyze OSD timing")
+                                .on_hover_text(tooltip_text("Compare the OSD timestamps with the video and propose a playback offset or speed correction."));
+                            ui.horizontal(|ui| {
+                                if ui.add(Button::new(RichText::new("Analyze")).small()).clicked() {
+                                    self.osd_timing = Some(osd_file.analyze_timing(video_info));
+                                }
+                                if let Some(timing) = &self.osd_timing {
+                                    ui.label(format!("{} ({:.0}% confidence)", timing.drift, timing.confidence * 100.0))
+                                        .on_hover_text(format!(
+                                            "Offset: {:.2} s\nSpeed factor: {:.4}\nOSD frame interval: {} ms\nGaps: {} ({:.1} s)",
+                                            timing.osd_playback_offset,
+                                            timing.osd_playback_speed_factor,
+                                            timing.osd_frame_interval.as_millis(),
+                                            timing.gap_count,
+                                            timing.gap_duration.as_secs_f32()
+                                        ));
+                                    if ui.add(Button::new(RichText::new("Apply")).small()).clicked() {
+                                        timing.apply(&mut self.osd_options);
+                                        changed |= true;
+                                    }
+                                }
+                            });
+                            ui.end_row();
+                        }
+
                         if self.video_info.is_some() {
                             if self.osd_options.character_size_class.is_none() {
                                 self.osd_options.character_size_class = Some(CharacterSizeClass::Normal);
@@ -374,7 +422,11 @@ impl WalksnailOsdTool {
             .show_unindented(ui, |ui| {
                 if let (Some(handle), Some(video_info)) = (&self.osd_preview.texture_handle, &self.video_info) {
                     let preview_width = ui.available_width();
-                    let aspect_ratio = video_info.width as f32 / video_info.height as f32;
+                    let (picture_width, picture_height) = self
+                        .render_settings
+                        .output_geometry
+                        .picture_size(video_info.width, video_info.height);
+                    let aspect_ratio = picture_width as f32 / picture_height as f32;
                     let preview_height = preview_width / aspect_ratio;
                     let image = Image::new(handle).max_width(preview_width).max_height(preview_height);
                     let rect = ui.add(image.bg_fill(Color32::DARK_GRAY)).rect;
@@ -403,29 +455,44 @@ impl WalksnailOsdTool {
     }
 
     fn draw_grid(&mut self, ui: &mut Ui, ctx: &egui::Context, image_rect: Rect) {
-        let video_width = self.video_info.as_ref().unwrap().width as f32;
-        let video_height = self.video_info.as_ref().unwrap().height as f32;
+        // The OSD is drawn on the cropped and scaled picture of the output video
+        let video_info = self.video_info.as_ref().unwrap();
+        let geometry = &self.render_settings.output_geometry;
+        let (picture_width, picture_height) = geometry.picture_size(video_info.width, video_info.height);
+        let osd_position = geometry.osd_position(video_info.width, video_info.height, &self.osd_options.position);
+        let (video_width, video_height) = (picture_width as f32, picture_height as f32);
 
         let top_left = image_rect.left_top();
         let preview_width = image_rect.width();
         let preview_height = image_rect.height();
 
-        let grid_width = preview_width * 0.99375;
-        let grid_height = preview_height;
-        let cell_width = grid_width / 53.0;
-        let cell_height = grid_height / 20.0;
+        // Same layout as the renderer: whole pixel cells with the remainder split around the grid
+        let grid_size = self
+            .osd_file
+            .as_ref()
+            .map(|osd_file| osd_file.grid_size.clone())
+            .unwrap_or(DEFAULT_GRID_SIZE);
+        let scale_x = preview_width / video_width;
+        let scale_y = preview_height / video_height;
+        let cell_width = (video_width as u32 / grid_size.width) as f32 * scale_x;
+        let cell_height = (video_height as u32 / grid_size.height) as f32 * scale_y;
+        let top_left = top_left
+            + vec2(
+                (video_width as u32 % grid_size.width / 2) as f32 * scale_x,
+                (video_height as u32 % grid_size.height / 2) as f32 * scale_y,
+            );
 
         let painter = ui.painter_at(image_rect);
 
-        let horizontal_offset = self.osd_options.position.x as f32 / video_width * preview_width;
-        let vertical_offset = self.osd_options.position.y as f32 / video_height * preview_height;
+        let horizontal_offset = osd_position.x as f32 / video_width * preview_width;
+        let vertical_offset = osd_position.y as f32 / video_height * preview_height;
 
         let response = ui
             .allocate_rect(image_rect, Sense::click())
             .on_hover_cursor(CursorIcon::Crosshair);
 
-        for i in 0..53 {
-            for j in 0..20 {
+        for i in 0..grid_size.width {
+            for j in 0..grid_size.height {
                 let rect = Rect::from_min_size(
                     top_left
                         + vec2(i as f32 * cell_width, j as f32 * cell_height)
@@ -434,7 +501,7 @@ impl WalksnailOsdTool {
                 );
 
                 let grid_position = Coordinates::new(i, j);
-                let masked = self.osd_options.get_mask(&grid_position);
+                let masked = self.osd_options.get_mask(&grid_position, &grid_size);
                 if masked {
                     painter.rect_filled(rect, 0.0, Color32::RED.gamma_multiply(0.5));
                 }
@@ -448,7 +515,7 @@ impl WalksnailOsdTool {
                 if response.clicked() {
                     if let Some(click_pos) = ctx.pointer_interact_pos() {
                         if rect.contains(click_pos) {
-                            self.osd_options.toggle_mask(grid_position);
+                            self.osd_options.toggle_mask(grid_position, &grid_size);
                             self.update_osd_preview(ctx);
                             self.config_changed = Instant::now().into();
                         }
@@ -459,13 +526,13 @@ impl WalksnailOsdTool {
 
         let line_stroke = Stroke::new(1.0, Color32::GRAY.gamma_multiply(0.5));
 
-        for i in 0..=53 {
+        for i in 0..=grid_size.width {
             let x = top_left.x + i as f32 * cell_width + horizontal_offset;
             let y_min = image_rect.y_range().min + vertical_offset;
             let y_max = image_rect.y_range().max + vertical_offset;
             painter.vline(x, y_min..=y_max, line_stroke);
         }
-        for i in 0..=20 {
+        for i in 0..=grid_size.height {
             let x_min = image_rect.x_range().min + horizontal_offset;
             let x_max = image_rect.x_range().max + horizontal_offset;
             let y = top_left.y + i as f32 * cell_height + vertical_offset;
@@ -536,16 +603,99 @@ impl WalksnailOsdTool {
                         changed |= ui.add_enabled(constant_quality_available, Checkbox::without_text(&mut self.render_settings.keep_quality)).changed();
                         ui.end_row();
 
-                        ui.label("Upscale to 1440p for YT").on_hover_text(tooltip_text("Upscale the output video to 1440p to get better quality after uploading to YouTube."));
-                        changed |= ui.add(Checkbox::without_text(&mut self.render_settings.upscale)).changed();
+                        ui.label("Trim").on_hover_text(tooltip_text("Render only this part of the video, in seconds from its start."));
+                        ui.horizontal(|ui| {
+                            let duration = self.video_info.as_ref().map_or(0.0, |v| v.duration.as_secs_f32());
+                            for (label, value, default) in [("From", &mut self.time_range.start, 0.0), ("to", &mut self.time_range.end, duration)] {
+                                let mut enabled = value.is_some();
+                                if ui.checkbox(&mut enabled, label).changed() {
+                                    *value = enabled.then_some(default);
+                                }
+                                if let Some(secs) = value {
+                                    ui.add(DragValue::new(secs).range(0.0..=duration).speed(0.1).suffix(" s"));
+                                }
+                            }
+                        });
+                        ui.end_row();
+
+                        let (input_width, input_height) = self.video_info.as_ref().map_or((1920, 1080), |v| (v.width, v.height));
+                        let geometry = &mut self.render_settings.output_geometry;
+
+                        ui.label("Resolution").on_hover_text(tooltip_text("Scale the output video to fit this resolution. 1440p gets a better quality after uploading to YouTube."));
+                        ui.horizontal(|ui| {
+                            egui::ComboBox::from_id_source("output_resolution")
+                                .selected_text(geometry.resolution.to_string())
+                                .show_ui(ui, |ui| {
+                                    let custom = match geometry.resolution {
+                                        custom @ OutputResolution::Custom { .. } => custom,
+                                        _ => OutputResolution::Custom { width: input_width, height: input_height },
+                                    };
+                                    for resolution in [OutputResolution::Source, OutputResolution::P720, OutputResolution::P1080, OutputResolution::P1440, OutputResolution::P2160] {
+                                        changed |= ui.selectable_value(&mut geometry.resolution, resolution, resolution.to_string()).changed();
+                                    }
+                                    changed |= ui.selectable_value(&mut geometry.resolution, custom, "Custom").changed();
+                                });
+                            if let OutputResolution::Custom { width, height } = &mut geometry.resolution {
+                                changed |= ui.add(DragValue::new(width).range(2..=7680).suffix(" px")).changed();
+                                ui.label("x");
+                                changed |= ui.add(DragValue::new(height).range(2..=4320).suffix(" px")).changed();
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Scaling filter").on_hover_text(tooltip_text("How the video is resampled when it's scaled. Nearest neighbor keeps hard pixel edges."));
+                        egui::ComboBox::from_id_source("scaling_filter")
+                            .selected_text(geometry.scaling_filter.to_string())
+                            .show_ui(ui, |ui| {
+                                for scaling_filter in [ScalingFilter::Bilinear, ScalingFilter::Bicubic, ScalingFilter::Lanczos, ScalingFilter::Neighbor] {
+                                    changed |= ui.selectable_value(&mut geometry.scaling_filter, scaling_filter, scaling_filter.to_string()).changed();
+                                }
+                            });
+                        ui.end_row();
+
+                        ui.label("Crop").on_hover_text(tooltip_text("Keep only this part of the input video, in pixels of the input video. The OSD is drawn on the cropped video."));
+                        ui.horizontal(|ui| {
+                            let mut crop_enabled = geometry.crop.is_some();
+                            if ui.add(Checkbox::without_text(&mut crop_enabled)).changed() {
+                                geometry.crop = crop_enabled.then_some(CropRect { x: 0, y: 0, width: input_width, height: input_height });
+                                changed = true;
+                            }
+                            if let Some(crop) = &mut geometry.crop {
+                                changed |= ui.add(DragValue::new(&mut crop.x).range(0..=input_width).prefix("x: ")).changed();
+                                changed |= ui.add(DragValue::new(&mut crop.y).range(0..=input_height).prefix("y: ")).changed();
+                                changed |= ui.add(DragValue::new(&mut crop.width).range(2..=input_width).prefix("w: ")).changed();
+                                changed |= ui.add(DragValue::new(&mut crop.height).range(2..=input_height).prefix("h: ")).changed();
+                            }
+                        });
+                        ui.end_row();
+
+                        ui.label("Letterbox / pillarbox").on_hover_text(tooltip_text("Pad the video with bars of this color to exactly the output resolution when its aspect ratio is different."));
+                        ui.horizontal(|ui| {
+                            let pad_available = geometry.resolution != OutputResolution::Source;
+                            changed |= ui.add_enabled(pad_available, Checkbox::without_text(&mut geometry.pad)).changed();
+                            changed |= ui.add_enabled_ui(pad_available, |ui| ui.color_edit_button_rgba_unmultiplied(&mut geometry.pad_color).changed()).inner;
+                        });
                         ui.end_row();
 
                         ui.label("Rescale to 4:3 aspect ratio").on_hover_text(tooltip_text("Rescale the output video to 4:3 aspect ratio, useful when you have 4:3 camera and recording is done by VRX in \"4:3 Fullscreen\" mode."));
                         changed |= ui.add(Checkbox::without_text(&mut self.render_settings.rescale_to_4x3_aspect)).changed();
                         ui.end_row();
 
+                        ui.label("Render mode").on_hover_text(tooltip_text("\"Composite frames\" draws the OSD on every video frame in the app. \"ffmpeg overlay filter\" only draws the OSD when it changes and lets ffmpeg put it on the video, which is faster but has no live view."));
+                        egui::ComboBox::from_id_source("render_mode")
+                            .selected_text(self.render_settings.render_mode.to_string())
+                            .show_ui(ui, |ui| {
+                                for render_mode in [RenderMode::Composite, RenderMode::FilterGraph] {
+                                    changed |= ui
+                                        .selectable_value(&mut self.render_settings.render_mode, render_mode, render_mode.to_string())
+                                        .changed();
+                                }
+                            });
+                        ui.end_row();
+
                         ui.label("Rendering live view").on_hover_text(tooltip_text("Enables live view of rendered frames once rendering is started."));
-                        changed |= ui.add(Checkbox::without_text(&mut self.render_settings.rendering_live_view)).changed();
+                        let live_view_available = self.render_settings.render_mode == RenderMode::Composite;
+                        changed |= ui.add_enabled(live_view_available, Checkbox::without_text(&mut self.render_settings.rendering_live_view)).changed();
                         ui.end_row();
 
                         ui.label("Chroma key").on_hover_text(tooltip_text("Render the video with a chroma key background instead of the input video so the OSD can be used as overlay video."));
@@ -561,6 +711,8 @@ impl WalksnailOsdTool {
 
         if changed {
             self.config_changed = Some(Instant::now());
+            // The preview shows the cropped and scaled video
+            self.update_osd_preview(&ui.ctx().clone());
         }
     }
 
@@ -571,30 +723,4 @@ impl WalksnailOsdTool {
         }
         return self.detected_encoders.clone();
     }
-
-    pub fn sort_and_filter_encoders(encoders: &Vec<Encoder>) -> Vec<Encoder> {
-        let mut filtered_encoders: Vec<Encoder> = encoders.iter().filter(|e| e.detected).map(|x| x.clone()).collect();
-
-        filtered_encoders.sort_by(|a, b| {
-            const CODEC_PRIORITY: [Codec; 4] = [Codec::H265, Codec::H264, Codec::VP9, Codec::ProRes];
-
-            let a_priority = CODEC_PRIORITY
-                .iter()
-                .position(|c| *c == a.codec)
-                .unwrap_or(CODEC_PRIORITY.len());
-            let b_priority = CODEC_PRIORITY
-                .iter()
-                .position(|c| *c == b.codec)
-                .unwrap_or(CODEC_PRIORITY.len());
-            let type_cmp = a_priority.cmp(&b_priority);
-
-            let hardware_cmp = b.hardware.cmp(&a.hardware);
-
-            let name_cmp = a.name.cmp(&b.name);
-
-            hardware_cmp.then_with(|| type_cmp).then_with(|| name_cmp)
-        });
-
-        filtered_encoders
-    }
 }
@@ -1,4 +1,5 @@
 use backend::{
+    ffmpeg::OutputGeometry,
     font,
     osd::{self, OsdOptions},
     overlay::{overlay_osd, overlay_srt_data, overlay_srt_debug_data},
@@ -7,20 +8,35 @@ use backend::{
 };
 use image::RgbaImage;
 
-#[tracing::instrument(skip(osd_frame, srt_frame, font), level = "debug")]
+/// Draws the OSD and SRT data on `background`, a frame of the video, or on a transparent image when there's no frame
+/// of the video's size. Like the render, the overlay is drawn on the video after it's cropped and scaled by `geometry`.
+#[tracing::instrument(skip(background, osd_frame, srt_frame, font), level = "debug")]
 pub fn create_osd_preview(
     width: u32,
     height: u32,
+    geometry: &OutputGeometry,
+    background: Option<&RgbaImage>,
     osd_frame: &osd::Frame,
+    osd_grid_size: &osd::GridSize,
     srt_frame: Option<&srt::SrtFrame>,
     font: &font::FontFile,
     srt_font: &rusttype::Font,
     osd_options: &OsdOptions,
     srt_options: &SrtOptions,
 ) -> RgbaImage {
-    let mut image = RgbaImage::new(width, height);
+    let mut image = match background {
+        Some(background) if background.dimensions() == (width, height) => geometry.picture(background),
+        _ => {
+            let (picture_width, picture_height) = geometry.picture_size(width, height);
+            RgbaImage::new(picture_width, picture_height)
+        }
+    };
 
-    overlay_osd(&mut image, osd_frame, font, osd_options);
+    let osd_options = OsdOptions {
+        position: geometry.osd_position(width, height, &osd_options.position),
+        ..osd_options.clone()
+    };
+    overlay_osd(&mut image, osd_frame, osd_grid_size, font, &osd_options);
 
     if !srt_options.no_srt {
         if let Some(frame) = &srt_frame {
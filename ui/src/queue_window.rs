@@ -0,0 +1,249 @@
+use std::{path::PathBuf, time::Instant};
+
+use backend::{
+    osd::OsdOptions,
+    queue::{JobStatus, QueueJob, QueueSettings, RenderQueue},
+};
+use crossbeam_channel::TryRecvError;
+use egui::{vec2, Button, Color32, Grid, ProgressBar, RichText, ScrollArea, Slider, Ui, Window};
+
+use super::WalksnailOsdTool;
+use crate::util::{filter_files_with_extensions, format_minutes_seconds, tooltip_text, VIDEO_EXTENSIONS};
+
+#[derive(Default)]
+pub struct QueueState {
+    pub window_open: bool,
+    pub jobs: Vec<QueueJob>,
+    pub statuses: Vec<JobStatus>,
+    pub queue: Option<RenderQueue>,
+}
+
+impl QueueState {
+    pub fn is_running(&self) -> bool {
+        self.queue.is_some()
+    }
+
+    pub fn add_videos(&mut self, file_handles: &[PathBuf]) {
+        for video_file in filter_files_with_extensions(file_handles, VIDEO_EXTENSIONS) {
+            if self.jobs.iter().any(|job| &job.input_video == video_file) {
+                continue;
+            }
+            self.jobs.push(QueueJob::from_video(video_file.clone()));
+            self.statuses.push(JobStatus::Pending);
+        }
+    }
+
+    fn remove_finished(&mut self) {
+        let (jobs, statuses) = self
+            .jobs
+            .drain(..)
+            .zip(self.statuses.drain(..))
+            .filter(|(_, status)| !status.is_finished())
+            .unzip();
+        self.jobs = jobs;
+        self.statuses = statuses;
+    }
+}
+
+impl WalksnailOsdTool {
+    pub fn receive_queue_events(&mut self) {
+        if let Some(queue) = &self.render_queue.queue {
+            loop {
+                match queue.events().try_recv() {
+                    Ok(event) => self.render_queue.statuses[event.job_index] = event.status,
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        tracing::info!("Render queue finished");
+                        self.render_queue.queue = None;
+                        break;
+                    }
+                }
+            }
+        }
+    }
+
+    fn queue_settings(&self) -> Option<QueueSettings> {
+        Some(QueueSettings {
+            ffmpeg_path: self.dependencies.ffmpeg_path.clone(),
+            ffprobe_path: self.dependencies.ffprobe_path.clone(),
+            font_file: self.font_file.as_ref()?.file_path.clone(),
+            // The offset and fitted speed belong to the OSD file that is loaded in the main window, not to the queued
+            // videos
+            osd_options: OsdOptions {
+                osd_playback_offset: 0.0,
+                fitted_playback_speed_factor: None,
+                ..self.osd_options.clone()
+            },
+            srt_options: self.srt_options.clone(),
+            render_settings: self.render_settings.clone(),
+            encoder: self.get_selected_encoder()?,
+        })
+    }
+
+    pub fn render_queue_window(&mut self, ctx: &egui::Context) {
+        let mut window_open = self.render_queue.window_open;
+        Window::new("Render queue")
+            .open(&mut window_open)
+            .default_size(vec2(600.0, 300.0))
+            .collapsible(false)
+            .show(ctx, |ui| {
+                self.queue_buttons(ui);
+                ui.separator();
+                self.queue_jobs(ui);
+                ui.separator();
+                self.queue_start_stop(ui);
+            });
+        self.render_queue.window_open = window_open;
+    }
+
+    fn queue_buttons(&mut self, ui: &mut Ui) {
+        let queue_running = self.render_queue.is_running();
+        ui.horizontal(|ui| {
+            if ui.add_enabled(!queue_running, Button::new("Add videos")).clicked() {
+                if let Some(file_handles) = rfd::FileDialog::new()
+                    .add_filter("Video files", VIDEO_EXTENSIONS)
+                    .pick_files()
+                {
+                    tracing::info!("Added files to render queue {:?}", file_handles);
+                    self.render_queue.add_videos(&file_handles);
+                }
+            }
+
+            if ui.add_enabled(!queue_running, Button::new("Remove finished")).clicked() {
+                self.render_queue.remove_finished();
+            }
+
+            if ui.add_enabled(!queue_running, Button::new("Clear")).clicked() {
+                self.render_queue.jobs.clear();
+                self.render_queue.statuses.clear();
+            }
+        });
+    }
+
+    fn queue_jobs(&mut self, ui: &mut Ui) {
+        if self.render_queue.jobs.is_empty() {
+            ui.label("Add videos or drop them on this window. The .osd and .srt files with the same name are used automatically.");
+            return;
+        }
+
+        let mut remove_job = None;
+        ScrollArea::vertical().max_height(400.0).show(ui, |ui| {
+            Grid::new("render_queue")
+                .striped(true)
+                .spacing(vec2(15.0, 5.0))
+                .show(ui, |ui| {
+                    ui.strong("Video");
+                    ui.strong("OSD");
+                    ui.strong("SRT");
+                    ui.strong("Status");
+                    ui.end_row();
+
+                    for (job_index, (job, status)) in self
+                        .render_queue
+                        .jobs
+                        .iter()
+                        .zip(&self.render_queue.statuses)
+                        .enumerate()
+                    {
+                        let file_name = job.input_video.file_name().unwrap_or_default().to_string_lossy();
+                        ui.label(file_name).on_hover_text(job.output_video.to_string_lossy());
+                        ui.label(if job.osd_file.is_some() { "✔" } else { "–" });
+                        ui.label(if job.srt_file.is_some() { "✔" } else { "–" });
+
+                        match status {
+                            JobStatus::Pending => {
+                                ui.label("Waiting");
+                            }
+                            JobStatus::Running {
+                                progress_pct,
+                                time_remaining,
+                            } => {
+                                let time_remaining_string = if let Some(time_remaining) = time_remaining {
+                                    format_minutes_seconds(time_remaining)
+                                } else {
+                                    "––:––".into()
+                                };
+                                ui.add(ProgressBar::new(*progress_pct).desired_width(200.0).text(format!(
+                                    "{:.0}% ({})",
+                                    progress_pct * 100.0,
+                                    time_remaining_string
+                                )));
+                            }
+                            JobStatus::Completed => {
+                                ui.label(RichText::new("Done").color(Color32::GREEN));
+                            }
+                            JobStatus::Failed(error) => {
+                                ui.label(RichText::new("Failed").color(Color32::RED))
+                                    .on_hover_text(error);
+                            }
+                            JobStatus::Cancelled => {
+                                ui.label("Cancelled");
+                            }
+                        }
+
+                        if let Some(queue) = &self.render_queue.queue {
+                            if ui
+                                .add_enabled(!status.is_finished(), Button::new("✖"))
+                                .on_hover_text("Cancel")
+                                .clicked()
+                            {
+                                tracing::info!("Cancel render queue job {}", job_index);
+                                queue.cancel(job_index);
+                            }
+                        } else if ui.button("✖").on_hover_text("Remove from queue").clicked() {
+                            remove_job = Some(job_index);
+                        }
+                        ui.end_row();
+                    }
+                });
+        });
+
+        if let Some(job_index) = remove_job {
+            self.render_queue.jobs.remove(job_index);
+            self.render_queue.statuses.remove(job_index);
+        }
+    }
+
+    fn queue_start_stop(&mut self, ui: &mut Ui) {
+        ui.horizontal(|ui| {
+            let queue_running = self.render_queue.is_running();
+
+            ui.label("Parallel renders").on_hover_text(tooltip_text(
+                "Number of videos that are rendered at the same time. More than one only helps when the encoder is not using all of your CPU or GPU.",
+            ));
+            if ui
+                .add_enabled(!queue_running, Slider::new(&mut self.render_settings.queue_concurrency, 1..=4))
+                .changed()
+            {
+                self.config_changed = Some(Instant::now());
+            }
+
+            if let Some(queue) = &self.render_queue.queue {
+                if ui.button("Cancel all").clicked() {
+                    tracing::info!("Cancel render queue");
+                    queue.cancel_all();
+                }
+            } else {
+                let start_allowed = self.font_loaded()
+                    && self.is_encoder_selected()
+                    && !self.render_queue.jobs.is_empty()
+                    && self.render_status.is_not_in_progress();
+                if ui
+                    .add_enabled(start_allowed, Button::new("Start queue"))
+                    .on_disabled_hover_text("First load a font file, select an encoder and add videos")
+                    .clicked()
+                {
+                    if let Some(settings) = self.queue_settings() {
+                        tracing::info!("Start render queue with {} jobs", self.render_queue.jobs.len());
+                        self.render_queue.statuses = vec![JobStatus::Pending; self.render_queue.jobs.len()];
+                        self.render_queue.queue = Some(RenderQueue::start(
+                            self.render_queue.jobs.clone(),
+                            settings,
+                            self.render_settings.queue_concurrency,
+                        ));
+                    }
+                }
+            }
+        });
+    }
+}
@@ -1,11 +1,15 @@
 use std::time::Duration;
 
-use backend::ffmpeg::{FromFfmpegMessage, VideoInfo};
+use backend::{ffmpeg::FromFfmpegMessage, util::error_with_source};
 
 #[derive(Default)]
 pub struct RenderStatus {
     pub decoder_status: Status,
     pub encoder_status: Status,
+    /// Frames in the rendered part of the video, the progress is measured against it.
+    pub total_frames: u32,
+    /// Last problem the render continued after.
+    pub warning: Option<String>,
 }
 
 #[derive(PartialEq, Default)]
@@ -30,7 +34,9 @@ pub enum Status {
 }
 
 impl RenderStatus {
-    pub fn start_render(&mut self) {
+    pub fn start_render(&mut self, total_frames: u32) {
+        self.total_frames = total_frames;
+        self.warning = None;
         self.decoder_status = Status::InProgress {
             time_remaining: None,
             fps: 0.0,
@@ -61,22 +67,24 @@ impl RenderStatus {
         }
     }
 
-    pub fn update_from_ffmpeg_message(&mut self, message: FromFfmpegMessage, video_info: &VideoInfo) {
+    pub fn update_from_ffmpeg_message(&mut self, message: FromFfmpegMessage) {
         match (&self.decoder_status, &message) {
-            (
-                Status::InProgress { progress_pct, .. },
-                FromFfmpegMessage::DecoderFatalError(e) | FromFfmpegMessage::EncoderFatalError(e),
-            ) => {
-                dbg!("🌤️🌤️🌤️", e);
+            (Status::InProgress { progress_pct, .. }, FromFfmpegMessage::Error(e)) => {
+                tracing::error!("Render failed: {}", e);
                 self.decoder_status = Status::Error {
                     progress_pct: *progress_pct,
-                    error: e.clone(),
+                    error: error_with_source(e),
                 }
             }
 
+            (_, FromFfmpegMessage::Warning(e)) => {
+                tracing::warn!("Render warning: {}", e);
+                self.warning = Some(error_with_source(e));
+            }
+
             (Status::InProgress { .. }, FromFfmpegMessage::DecoderProgress(p)) => {
                 let frame = p.frame as f32;
-                let total_frames = video_info.total_frames as f32;
+                let total_frames = self.total_frames as f32;
                 let progress_pct = frame / total_frames;
                 let frames_remaining = total_frames - frame;
                 let time_remaining_secs = frames_remaining / p.fps;
@@ -96,7 +104,7 @@ impl RenderStatus {
 
             (Status::InProgress { .. }, FromFfmpegMessage::EncoderProgress(p)) => {
                 let frame = p.frame as f32;
-                let total_frames = video_info.total_frames as f32;
+                let total_frames = self.total_frames as f32;
                 let progress_pct = frame / total_frames;
                 let frames_remaining = total_frames - frame;
                 let time_remaining_secs = frames_remaining / p.fps;
@@ -1,3 +1,5 @@
+use std::time::Duration;
+
 use backend::font::FontType;
 use egui::{CollapsingHeader, Color32, RichText, Ui};
 use egui_extras::{Column, TableBuilder};
@@ -5,6 +7,9 @@ use egui_extras::{Column, TableBuilder};
 use super::WalksnailOsdTool;
 use crate::util::{format_minutes_seconds, separator_with_space};
 
+/// Gaps listed in the hover text of the gap count, long flights with a bad link can have hundreds.
+const MAX_LISTED_GAPS: usize = 20;
+
 impl WalksnailOsdTool {
     pub fn render_sidepanel(&mut self, ctx: &egui::Context) {
         egui::SidePanel::left("side_panel")
@@ -155,6 +160,19 @@ impl WalksnailOsdTool {
                                 });
                             });
 
+                            body.row(row_height, |mut row| {
+                                row.col(|ui| {
+                                    ui.label("Format:");
+                                });
+                                row.col(|ui| {
+                                    if let Some(osd_file) = osd_file {
+                                        ui.label(osd_file.format.to_string());
+                                    } else {
+                                        ui.label("-");
+                                    }
+                                });
+                            });
+
                             body.row(row_height, |mut row| {
                                 row.col(|ui| {
                                     ui.label("FC firmware:");
@@ -168,13 +186,78 @@ impl WalksnailOsdTool {
                                 });
                             });
 
+                            body.row(row_height, |mut row| {
+                                row.col(|ui| {
+                                    ui.label("OSD grid:");
+                                });
+                                row.col(|ui| {
+                                    if let Some(osd_file) = osd_file {
+                                        let grid_size = osd_file.grid_size.to_string();
+                                        if osd_file.header.grid_size.is_some() {
+                                            ui.label(grid_size);
+                                        } else {
+                                            ui.label(format!("{} (detected)", grid_size)).on_hover_text(
+                                                "The OSD file does not specify a grid size, it was detected from the size of the frames",
+                                            );
+                                        }
+                                    } else {
+                                        ui.label("-");
+                                    }
+                                });
+                            });
+
+                            body.row(row_height, |mut row| {
+                                row.col(|ui| {
+                                    ui.label("Font:");
+                                });
+                                row.col(|ui| {
+                                    let font = osd_file.map(|osd_file| {
+                                        let header = &osd_file.header;
+                                        match (&header.font_variant, &header.font_size) {
+                                            (Some(variant), Some(size)) => format!("{} {}", variant, size),
+                                            (Some(variant), None) => variant.clone(),
+                                            (None, Some(size)) => size.to_string(),
+                                            (None, None) => "-".into(),
+                                        }
+                                    });
+                                    ui.label(font.unwrap_or("-".into()));
+                                });
+                            });
+
+                            body.row(row_height, |mut row| {
+                                row.col(|ui| {
+                                    ui.label("Format version:");
+                                });
+                                row.col(|ui| {
+                                    if let Some(osd_file) = osd_file {
+                                        ui.label(osd_file.header.version.to_string());
+                                    } else {
+                                        ui.label("-");
+                                    }
+                                });
+                            });
+
                             body.row(row_height, |mut row| {
                                 row.col(|ui| {
                                     ui.label("Frames:");
                                 });
                                 row.col(|ui| {
                                     if let Some(osd_file) = osd_file {
-                                        ui.label(osd_file.frame_count.to_string());
+                                        ui.horizontal(|ui| {
+                                            if !osd_file.repairs.is_empty() {
+                                                let repairs = osd_file
+                                                    .repairs
+                                                    .iter()
+                                                    .map(|repair| repair.to_string())
+                                                    .collect::<Vec<_>>()
+                                                    .join("\n");
+                                                ui.label(
+                                                    RichText::new("⚠").color(Color32::from_rgb(255, 200, 0)).strong(),
+                                                )
+                                                .on_hover_text(format!("The OSD file is damaged:\n{}", repairs));
+                                            }
+                                            ui.label(osd_file.frame_count.to_string());
+                                        });
                                     } else {
                                         ui.label("-");
                                     }
@@ -214,6 +297,33 @@ impl WalksnailOsdTool {
                                     });
                                 });
                             });
+
+                            body.row(row_height, |mut row| {
+                                row.col(|ui| {
+                                    ui.label("Gaps:");
+                                });
+                                row.col(|ui| match osd_file.map(|osd_file| &osd_file.gaps) {
+                                    Some(gaps) if !gaps.is_empty() => {
+                                        let total: Duration = gaps.iter().map(|gap| gap.duration()).sum();
+                                        let mut report = gaps
+                                            .iter()
+                                            .take(MAX_LISTED_GAPS)
+                                            .map(|gap| gap.to_string())
+                                            .collect::<Vec<_>>();
+                                        if gaps.len() > MAX_LISTED_GAPS {
+                                            report.push(format!("and {} more", gaps.len() - MAX_LISTED_GAPS));
+                                        }
+                                        ui.label(format!("{} ({:.1} s)", gaps.len(), total.as_secs_f32()))
+                                            .on_hover_text(report.join("\n"));
+                                    }
+                                    Some(_) => {
+                                        ui.label("None");
+                                    }
+                                    None => {
+                                        ui.label("-");
+                                    }
+                                });
+                            });
                         });
                 });
             });
@@ -1,5 +1,6 @@
 use std::time::Instant;
 
+use backend::ffmpeg::TimeRange;
 use egui::{vec2, Align2, Button, Frame, Label, RichText, Sense, TextStyle, Ui, Visuals, Window};
 
 use crate::util::{AVATAR_EXTENSIONS, VIDEO_EXTENSIONS};
@@ -13,6 +14,7 @@ impl WalksnailOsdTool {
             ui.horizontal(|ui| {
                 self.import_files(ui, ctx);
                 self.reset_files(ui);
+                self.toggle_render_queue(ui);
                 ui.add_space(ui.available_width() - 55.0);
                 self.toggle_light_dark_theme(ui, ctx);
                 self.about_window(ui, ctx);
@@ -50,7 +52,13 @@ impl WalksnailOsdTool {
                 .flat_map(|f| f.path.clone())
                 .collect::<Vec<_>>()
         });
-        if !file_handles.is_empty() {
+        if !file_handles.is_empty() && self.render_queue.window_open {
+            // While the queue is open dropped videos are meant for the queue
+            tracing::info!("Dropped files on render queue {:?}", file_handles);
+            if !self.render_queue.is_running() {
+                self.render_queue.add_videos(&file_handles);
+            }
+        } else if !file_handles.is_empty() {
             tracing::info!("Dropped files {:?}", file_handles);
             self.import_video_file(&file_handles);
             self.import_osd_file(&file_handles);
@@ -69,6 +77,9 @@ impl WalksnailOsdTool {
             // Note: don't do `self.font_file = None` here, that just makes UX bad.
             self.input_video_file = None;
             self.video_info = None;
+            self.video_frames = None;
+            self.osd_preview.background = None;
+            self.time_range = TimeRange::default();
             self.osd_file = None;
             self.srt_file = None;
             self.osd_preview.texture_handle = None;
@@ -79,6 +90,16 @@ impl WalksnailOsdTool {
         }
     }
 
+    fn toggle_render_queue(&mut self, ui: &mut Ui) {
+        if ui
+            .selectable_label(self.render_queue.window_open, "Render queue")
+            .on_hover_text("Render many videos with the current settings")
+            .clicked()
+        {
+            self.render_queue.window_open = !self.render_queue.window_open;
+        }
+    }
+
     fn toggle_light_dark_theme(&mut self, ui: &mut Ui, ctx: &egui::Context) {
         let icon = if self.dark_mode { "☀" } else { "🌙" };
         if ui.add(Button::new(icon).frame(false)).clicked() {
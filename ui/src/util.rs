@@ -1,10 +1,18 @@
 use std::{
     env::current_exe,
     path::{Path, PathBuf},
+    process::Command,
     time::{Duration, Instant},
 };
 
-use backend::{config::AppConfig, ffmpeg::VideoInfo, font::FontFile, osd::OsdFile, srt::SrtFile};
+use backend::{
+    config::AppConfig,
+    ffmpeg::{FrameExtractor, TimeRange, VideoInfo},
+    font::FontFile,
+    osd::OsdFile,
+    srt::SrtFile,
+    util::matching_file_with_extension,
+};
 use egui::{FontFamily, FontId, Margin, RichText, Separator, TextStyle, Ui};
 use github_release_check::{GitHubReleaseItem, LookupError};
 use semver::Version;
@@ -27,7 +35,10 @@ impl WalksnailOsdTool {
     }
 
     pub fn is_start_render_allowed(&self) -> bool {
-        self.all_files_loaded() && self.is_encoder_selected() && self.is_output_file_path_allowed()
+        self.all_files_loaded()
+            && self.is_encoder_selected()
+            && self.is_output_file_path_allowed()
+            && !self.render_queue.is_running()
     }
 
     pub fn is_output_file_path_allowed(&self) -> bool {
@@ -58,6 +69,17 @@ impl WalksnailOsdTool {
         if let Some(video_file) = first_file_with_extentions(file_handles, VIDEO_EXTENSIONS) {
             self.input_video_file = Some(video_file.clone());
             self.video_info = VideoInfo::get(video_file, &self.dependencies.ffprobe_path).ok();
+            self.video_frames = self.video_info.as_ref().map(|video_info| {
+                FrameExtractor::new(
+                    self.dependencies.ffmpeg_path.clone(),
+                    video_file.clone(),
+                    video_info.frame_rate,
+                )
+            });
+            self.osd_preview.background = None;
+            self.osd_timing = None;
+            self.osd_options.fitted_playback_speed_factor = None;
+            self.time_range = TimeRange::default();
 
             // Generate default output file name for newly imported video file.
             self.ui_state.output_file_name = "".to_owned();
@@ -71,8 +93,10 @@ impl WalksnailOsdTool {
 
     pub fn import_osd_file(&mut self, file_handles: &[PathBuf]) {
         if let Some(osd_file_path) = filter_file_with_extention(file_handles, "osd") {
-            self.osd_file = OsdFile::open(osd_file_path.clone()).ok();
+            self.osd_file = OsdFile::open_with_recovery(osd_file_path.clone()).ok();
             self.osd_preview.preview_frame = 1;
+            self.osd_timing = None;
+            self.osd_options.fitted_playback_speed_factor = None;
             self.osd_options.osd_playback_offset = 0.0;
             self.osd_options.character_size_class = None;
         }
@@ -127,13 +151,6 @@ pub fn filter_files_with_extensions<'a>(files: &'a [PathBuf], extensions: &'a [&
         .collect()
 }
 
-#[tracing::instrument(ret, level = "info")]
-pub fn matching_file_with_extension(path: &PathBuf, extention: &str) -> PathBuf {
-    let file_name = path.file_stem().unwrap();
-    let parent = path.parent().unwrap();
-    parent.join(file_name).with_extension(extention)
-}
-
 pub fn separator_with_space(ui: &mut Ui, space: f32) {
     ui.scope(|ui| {
         ui.visuals_mut().widgets.noninteractive.bg_stroke.width = 0.5;
@@ -147,14 +164,6 @@ pub fn format_minutes_seconds(duration: &Duration) -> String {
     format!("{}:{:0>2}", minutes, seconds)
 }
 
-pub fn generate_default_output_file_name(input_file_path: &PathBuf) -> String {
-    let input_file_name_no_ext = input_file_path
-        .file_stem()
-        .map_or("file".to_string(), |p| p.to_string_lossy().to_string());
-
-    format!("{}_with_osd", input_file_name_no_ext)
-}
-
 pub fn generate_output_file_path(input_file_path: &Path, output_file_name: &String) -> PathBuf {
     let input_file_path_str = input_file_path.to_string_lossy();
     tracing::debug!("generate_output_file_path for {input_file_path_str}");
@@ -277,6 +286,20 @@ pub fn init_tracing() -> Option<WorkerGuard> {
     })
 }
 
+/// Opens `path` with the application the system uses for its file type, e.g. the video player for a preview clip.
+pub fn open_with_default_app(path: &Path) {
+    let program = if cfg!(target_os = "windows") {
+        "explorer"
+    } else if cfg!(target_os = "macos") {
+        "open"
+    } else {
+        "xdg-open"
+    };
+    if let Err(e) = Command::new(program).arg(path).spawn() {
+        tracing::error!("Failed to open {}: {}", path.display(), e);
+    }
+}
+
 pub fn get_dependency_path(dependency: &str) -> PathBuf {
     let cur_exe = current_exe().unwrap();
     let exe_dir = cur_exe.parent().unwrap();